@@ -11,9 +11,12 @@ USER_ID=$1
 # Create directories
 mkdir -p /var/lib/torrust/hash2torrent/session
 mkdir -p /var/lib/torrust/hash2torrent/torrents
+mkdir -p /var/lib/torrust/hash2torrent/webhooks
+mkdir -p /var/log/torrust/hash2torrent/audit
+mkdir -p /etc/torrust/hash2torrent
 
 # Change ownership to the current user ID
-chown -R "${USER_ID}:${USER_ID}" /var/lib/torrust/hash2torrent
+chown -R "${USER_ID}:${USER_ID}" /var/lib/torrust/hash2torrent /var/log/torrust/hash2torrent /etc/torrust/hash2torrent
 
 # Set permissions
-chmod -R 2770 /var/lib/torrust/hash2torrent
+chmod -R 2770 /var/lib/torrust/hash2torrent /var/log/torrust/hash2torrent /etc/torrust/hash2torrent
@@ -0,0 +1,90 @@
+//! Checks for the addresses the service dials on behalf of its clients.
+//!
+//! Peers and trackers given in the query parameters and webhook callbacks are
+//! chosen by anonymous clients, and the peers found for the peers endpoint by
+//! anybody in the swarm. The service must not reach hosts in its own network
+//! for them, so only publicly routable addresses are allowed.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Returns `true` if the address is publicly routable. Loopback, private,
+/// link-local, shared, documentation, broadcast, multicast and unspecified
+/// addresses are not.
+#[must_use]
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    let shared = a == 100 && (b & 0b1100_0000) == 64;
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || shared
+        || a == 0)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let [first, second, ..] = ip.segments();
+    let unique_local = (first & 0xfe00) == 0xfc00;
+    let link_local = (first & 0xffc0) == 0xfe80;
+    let documentation = first == 0x2001 && second == 0x0db8;
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || unique_local
+        || link_local
+        || documentation)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::is_public;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn it_should_allow_public_addresses() {
+        assert!(is_public(ip("1.1.1.1")));
+        assert!(is_public(ip("2606:4700:4700::1111")));
+        assert!(is_public(ip("::ffff:1.1.1.1")));
+    }
+
+    #[test]
+    fn it_should_reject_addresses_of_the_local_network() {
+        for address in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.10",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:192.168.1.10",
+        ] {
+            assert!(!is_public(ip(address)), "{address}");
+        }
+    }
+}
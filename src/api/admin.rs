@@ -0,0 +1,342 @@
+//! Admin API for cache and session management.
+//!
+//! All endpoints require the `Authorization: Bearer <token>` header with the
+//! token configured in [`Api::admin_token`](crate::config::Api::admin_token).
+//! Every admin action is logged with the `audit` target.
+//!
+//! Endpoints:
+//!
+//! - `GET    /api/v1/admin/cache`: cache usage.
+//! - `DELETE /api/v1/admin/cache/:info_hash`: remove a cached torrent.
+//! - `POST   /api/v1/admin/torrents/:info_hash/resolve`: purge and resolve a torrent again.
+//! - `GET    /api/v1/admin/session/torrents`: torrents managed by the `BitTorrent` session.
+//! - `DELETE /api/v1/admin/session/torrents/:info_hash`: remove a torrent from the session.
+//! - `GET    /api/v1/admin/denylist`: denied info-hashes.
+//! - `PUT    /api/v1/admin/denylist/:info_hash`: deny a torrent and purge it from the cache.
+//! - `DELETE /api/v1/admin/denylist/:info_hash`: allow a denied torrent again.
+//! - `POST   /api/v1/admin/denylist/reload`: reload the denylist file.
+//! - `GET    /api/v1/admin/audit`: query the audit log of the torrent requests.
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Path, Query, Request, State};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use hyper::{header, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::api::audit_log::AuditQuery;
+use crate::api::handler::{resolve_and_cache, InfoHashParam, MetainfoQuery};
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::resolver::{ManageTorrentError, Progress};
+use crate::AppState;
+
+/// Builds the admin router. It has to be nested under `/api/v1/admin`, and
+/// the deprecated `/admin`.
+pub fn router(token: String) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/cache", get(cache_usage_handler))
+        .route("/cache/:info_hash", delete(delete_cached_torrent_handler))
+        .route(
+            "/torrents/:info_hash/resolve",
+            post(resolve_torrent_handler),
+        )
+        .route("/session/torrents", get(list_session_torrents_handler))
+        .route(
+            "/session/torrents/:info_hash",
+            delete(remove_session_torrent_handler),
+        )
+        .route("/denylist", get(list_denylist_handler))
+        .route("/denylist/reload", post(reload_denylist_handler))
+        .route(
+            "/denylist/:info_hash",
+            put(deny_torrent_handler).delete(allow_torrent_handler),
+        )
+        .route("/audit", get(query_audit_log_handler))
+        .route_layer(middleware::from_fn_with_state(Arc::new(token), authorize))
+}
+
+async fn authorize(
+    State(token): State<Arc<String>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // An empty token would match an empty bearer token.
+    let authorized = !token.is_empty()
+        && request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()));
+
+    if !authorized {
+        warn!(target: "audit", %client, path = %request.uri().path(), "unauthorized admin request");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    }
+
+    next.run(request).await
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn cache_usage_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Response {
+    info!(target: "audit", %client, "admin: cache usage");
+
+    match app_state.cache.usage() {
+        Ok(usage) => Json(usage).into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn delete_cached_torrent_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    info!(target: "audit", %client, %info_hash, "admin: delete cached torrent");
+
+    match app_state.cache.remove(&info_hash) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Torrent not cached").into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn resolve_torrent_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    info!(target: "audit", %client, %info_hash, "admin: force torrent resolution");
+
+    if app_state.denylist.contains(&info_hash) {
+        return (StatusCode::CONFLICT, "Torrent is denied").into_response();
+    }
+
+    if let Err(err) = app_state.cache.remove(&info_hash) {
+        return internal_error(&err);
+    }
+
+    if let Err(err) = purge_session_torrent(&app_state, &info_hash).await {
+        return internal_error(&err);
+    }
+
+    match resolve_and_cache(
+        &app_state,
+        &info_hash,
+        &MetainfoQuery::default(),
+        Progress::default(),
+    )
+    .await
+    {
+        Ok(metadata) => Json(json!({
+            "info_hash": info_hash,
+            "bytes": metadata.torrent_bytes.len()
+        }))
+        .into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn list_session_torrents_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Response {
+    info!(target: "audit", %client, "admin: list session torrents");
+
+    let Some(session) = app_state.resolver.session() else {
+        return no_session_response();
+    };
+
+    match session.list_torrents() {
+        Ok(torrents) => Json(torrents).into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn remove_session_torrent_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    info!(target: "audit", %client, %info_hash, "admin: remove session torrent");
+
+    let Some(session) = app_state.resolver.session() else {
+        return no_session_response();
+    };
+
+    match session.remove_torrent(&info_hash).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(ManageTorrentError::NotManaged) => {
+            (StatusCode::NOT_FOUND, "Torrent not managed by the session").into_response()
+        }
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn list_denylist_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Response {
+    info!(target: "audit", %client, "admin: list denylist");
+
+    Json(app_state.denylist.list()).into_response()
+}
+
+/// Optional body for the deny endpoint.
+#[derive(Deserialize, Default)]
+pub struct DenyRequest {
+    /// Reference to the takedown notice. It's stored next to the info-hash.
+    pub note: Option<String>,
+}
+
+async fn deny_torrent_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path(info_hash): Path<InfoHashParam>,
+    body: Option<Json<DenyRequest>>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    let Json(request) = body.unwrap_or_default();
+
+    info!(target: "audit", %client, %info_hash, note = ?request.note, "admin: deny torrent");
+
+    let added = match app_state.denylist.add(&info_hash, request.note.as_deref()) {
+        Ok(added) => added,
+        Err(err) => return internal_error(&err),
+    };
+
+    if let Err(err) = app_state.cache.remove(&info_hash) {
+        return internal_error(&err);
+    }
+
+    if let Err(err) = purge_session_torrent(&app_state, &info_hash).await {
+        return internal_error(&err);
+    }
+
+    if added {
+        StatusCode::CREATED.into_response()
+    } else {
+        StatusCode::NO_CONTENT.into_response()
+    }
+}
+
+async fn allow_torrent_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    info!(target: "audit", %client, %info_hash, "admin: allow torrent");
+
+    match app_state.denylist.remove(&info_hash) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Torrent not denied").into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn reload_denylist_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Response {
+    info!(target: "audit", %client, "admin: reload denylist");
+
+    match app_state.denylist.reload() {
+        Ok(len) => Json(json!({ "info_hashes": len })).into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+async fn query_audit_log_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Query(query): Query<AuditQuery>,
+) -> Response {
+    info!(target: "audit", %client, ?query, "admin: query audit log");
+
+    let Some(audit_log) = &app_state.audit_log else {
+        return (StatusCode::NOT_IMPLEMENTED, "The audit log is disabled").into_response();
+    };
+
+    match audit_log.query(&query) {
+        Ok(records) => Json(records).into_response(),
+        Err(err) => internal_error(&err),
+    }
+}
+
+/// Removes the torrent from the `BitTorrent` session, if there is one and the
+/// torrent is in it.
+async fn purge_session_torrent(
+    app_state: &AppState,
+    info_hash: &InfoHash,
+) -> Result<(), ManageTorrentError> {
+    let Some(session) = app_state.resolver.session() else {
+        return Ok(());
+    };
+
+    match session.remove_torrent(info_hash).await {
+        Ok(()) | Err(ManageTorrentError::NotManaged | ManageTorrentError::NoSession) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+fn no_session_response() -> Response {
+    (
+        StatusCode::NOT_IMPLEMENTED,
+        "The resolver does not use a BitTorrent session",
+    )
+        .into_response()
+}
+
+fn internal_error(err: &dyn std::error::Error) -> Response {
+    warn!(target: "audit", "admin action failed: {err}");
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::constant_time_eq;
+
+    #[test]
+    fn it_should_match_equal_tokens() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+    }
+
+    #[test]
+    fn it_should_not_match_different_tokens() {
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+}
@@ -0,0 +1,546 @@
+//! Persistent audit log of the torrent requests.
+//!
+//! Every request to the `/torrents/:info_hash` endpoints is appended to a
+//! [JSON Lines](https://jsonlines.org) file as an [`AuditRecord`]: when it was
+//! made, the client IP address, the info-hash, whether the torrent was in the
+//! cache, the response status and the latency. Requests with invalid or
+//! denied info-hashes are recorded too.
+//!
+//! There is a file for each UTC day, named after the date, like
+//! `2024-09-10.jsonl`. Files older than the retention are deleted when the
+//! log moves to a new day. The admin API queries the log.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use camino::Utf8PathBuf;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::api::client_ip::client_ip;
+use crate::api::dates::date;
+use crate::api::v1;
+use crate::config;
+use crate::AppState;
+
+/// Records returned by a query when it does not set a limit.
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Maximum number of records returned by a query.
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// Whether the torrent was served from the cache.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+
+impl CacheStatus {
+    #[must_use]
+    pub fn new(cached: bool) -> Self {
+        if cached {
+            Self::Hit
+        } else {
+            Self::Miss
+        }
+    }
+
+    /// Adds the status to the response, so it's recorded in the audit log.
+    #[must_use]
+    pub fn tag(self, mut response: Response) -> Response {
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// How the request ended, from the response status.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The client got what it asked for.
+    Served,
+    /// The client already had the torrent file.
+    NotModified,
+    /// Invalid info-hash or query parameters.
+    InvalidRequest,
+    /// The torrent is in the denylist.
+    Denied,
+    /// The torrent was not resolved in time.
+    TimedOut,
+    /// The resolution queue was full.
+    Overloaded,
+    Failed,
+}
+
+impl From<StatusCode> for Outcome {
+    fn from(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_MODIFIED => Self::NotModified,
+            StatusCode::BAD_REQUEST => Self::InvalidRequest,
+            StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => Self::Denied,
+            StatusCode::GATEWAY_TIMEOUT => Self::TimedOut,
+            StatusCode::SERVICE_UNAVAILABLE => Self::Overloaded,
+            status if status.is_success() => Self::Served,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// A line of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Unix time of the request.
+    pub timestamp: u64,
+    /// The client IP address. The host part is zeroed if the addresses are
+    /// anonymized.
+    pub client: IpAddr,
+    /// The info-hash in the request path, lowercased. It's recorded even if
+    /// it's not valid.
+    pub info_hash: String,
+    /// The path after the info-hash, like `info`, or `torrent` for the
+    /// torrent file.
+    pub endpoint: String,
+    /// Only set by the endpoints that resolve the torrent if it's not cached.
+    pub cache: Option<CacheStatus>,
+    pub status: u16,
+    pub outcome: Outcome,
+    /// Time to answer the request, in milliseconds. For the progress events
+    /// it's the time to start the stream.
+    pub latency_ms: u64,
+}
+
+/// The filters of an audit log query. All of them are optional.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub info_hash: Option<String>,
+    /// The client IP address, anonymized if the log is.
+    pub client: Option<IpAddr>,
+    /// Unix time of the oldest request.
+    pub since: Option<u64>,
+    /// Unix time of the newest request.
+    pub until: Option<u64>,
+    /// Maximum number of records, 100 by default and 1000 at most.
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.info_hash
+            .as_ref()
+            .is_none_or(|info_hash| info_hash.to_lowercase() == record.info_hash)
+            && self.client.is_none_or(|client| client == record.client)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+pub struct AuditLog {
+    config: config::AuditLog,
+    /// The day, in days since the Unix epoch, and the file records are being
+    /// appended to.
+    current: Mutex<Option<(u64, File)>>,
+}
+
+impl AuditLog {
+    /// Creates the log directory, if needed, and deletes the files older than
+    /// the retention.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't create or clean up the directory.
+    pub fn open(config: config::AuditLog) -> io::Result<Arc<Self>> {
+        fs::create_dir_all(&config.dir)?;
+
+        let audit_log = Self {
+            config,
+            current: Mutex::new(None),
+        };
+
+        audit_log.prune(unix_time(SystemTime::now()) / 86_400)?;
+
+        Ok(Arc::new(audit_log))
+    }
+
+    /// Returns the client address as it's recorded.
+    #[must_use]
+    pub fn client(&self, address: IpAddr) -> IpAddr {
+        if self.config.anonymize_client_ip {
+            anonymize(address)
+        } else {
+            address.to_canonical()
+        }
+    }
+
+    /// Appends the record to the file of its day.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write the record.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn append(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let day = record.timestamp / 86_400;
+
+        let mut current = self.current.lock().expect("the audit log lock is poisoned");
+
+        if current
+            .as_ref()
+            .is_none_or(|(current_day, _)| *current_day != day)
+        {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(&date(record.timestamp)))?;
+
+            if current.is_some() {
+                if let Err(err) = self.prune(day) {
+                    error!("could not delete the old audit log files: {err}");
+                }
+            }
+
+            *current = Some((day, file));
+        }
+
+        let (_, file) = current.as_mut().expect("the current file was just opened");
+
+        file.write_all(&line)
+    }
+
+    /// Returns the records that match the query, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read the log files.
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<AuditRecord>> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        let first_day = query.since.map(date);
+        let last_day = query.until.map(date);
+
+        let mut days: Vec<String> = self
+            .days()?
+            .into_iter()
+            .filter(|day| first_day.as_ref().is_none_or(|first| day >= first))
+            .filter(|day| last_day.as_ref().is_none_or(|last| day <= last))
+            .collect();
+        days.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut records = vec![];
+
+        for day in days {
+            let contents = match fs::read_to_string(self.path(&day)) {
+                Ok(contents) => contents,
+                // Deleted by the retention after it was listed.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            // Lines cut short by a crash are skipped.
+            let matching = contents
+                .lines()
+                .rev()
+                .filter_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+                .filter(|record| query.matches(record));
+
+            for record in matching {
+                if records.len() == limit {
+                    return Ok(records);
+                }
+                records.push(record);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Deletes the files of the days before the retention, counting back
+    /// from `today`, in days since the Unix epoch.
+    fn prune(&self, today: u64) -> io::Result<()> {
+        let oldest =
+            date(today.saturating_sub(self.config.retention_days.saturating_sub(1)) * 86_400);
+
+        for day in self.days()? {
+            if day < oldest {
+                fs::remove_file(self.path(&day))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The dates of the log files.
+    fn days(&self) -> io::Result<Vec<String>> {
+        let mut days = vec![];
+
+        for entry in fs::read_dir(&self.config.dir)? {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+
+            if let Some(day) = path.file_stem().and_then(|stem| stem.to_str()) {
+                days.push(day.to_string());
+            }
+        }
+
+        Ok(days)
+    }
+
+    fn path(&self, day: &str) -> Utf8PathBuf {
+        self.config.dir.join(format!("{day}.jsonl"))
+    }
+}
+
+/// Records the requests to the torrents endpoints in the audit log. The
+/// client address of the requests from trusted proxies is the forwarded one.
+pub async fn record_requests(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(audit_log) = app_state.audit_log.clone() else {
+        return next.run(request).await;
+    };
+
+    let Some((info_hash, endpoint)) = torrent_request(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let client = client_ip(
+        peer.ip(),
+        request.headers(),
+        &app_state.config.api.trusted_proxies,
+    );
+
+    let timestamp = unix_time(SystemTime::now());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let record = AuditRecord {
+        timestamp,
+        client: audit_log.client(client),
+        info_hash,
+        endpoint,
+        cache: response.extensions().get::<CacheStatus>().copied(),
+        status: response.status().as_u16(),
+        outcome: response.status().into(),
+        latency_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    };
+
+    // The file is written in a blocking task, not to hold up the async
+    // workers. It's awaited, so the record can be queried once the client
+    // gets the response.
+    match tokio::task::spawn_blocking(move || audit_log.append(&record)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => error!("could not write the audit log: {err}"),
+        Err(err) => error!("the audit log writer failed: {err}"),
+    }
+
+    response
+}
+
+/// Returns the info-hash and the endpoint of a request to the torrents
+/// endpoints, with or without the version prefix.
+fn torrent_request(path: &str) -> Option<(String, String)> {
+    let path = path.strip_prefix(v1::PREFIX).unwrap_or(path);
+    let path = path.strip_prefix("/torrents/")?;
+
+    let (info_hash, endpoint) = path.split_once('/').unwrap_or((path, ""));
+
+    if info_hash.is_empty() {
+        return None;
+    }
+
+    let endpoint = if endpoint.is_empty() {
+        "torrent"
+    } else {
+        endpoint
+    };
+
+    Some((info_hash.to_lowercase(), endpoint.to_string()))
+}
+
+/// Zeroes the host part of the address: the last byte of IPv4 addresses and
+/// the last 80 bits of IPv6 addresses.
+fn anonymize(address: IpAddr) -> IpAddr {
+    match address.to_canonical() {
+        IpAddr::V4(address) => {
+            let [a, b, c, _] = address.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(address) => {
+            let [a, b, c, ..] = address.segments();
+            IpAddr::V6(Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0))
+        }
+    }
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use camino::Utf8PathBuf;
+    use hyper::StatusCode;
+
+    use super::{
+        anonymize, torrent_request, AuditLog, AuditQuery, AuditRecord, CacheStatus, Outcome,
+    };
+    use crate::api::dates::date;
+    use crate::config;
+
+    const DAY: u64 = 86_400;
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn record(timestamp: u64, info_hash: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp,
+            client: "203.0.113.7".parse().unwrap(),
+            info_hash: info_hash.to_string(),
+            endpoint: "torrent".to_string(),
+            cache: Some(CacheStatus::Hit),
+            status: 200,
+            outcome: Outcome::Served,
+            latency_ms: 3,
+        }
+    }
+
+    fn open(dir: &tempfile::TempDir, retention_days: u64) -> std::sync::Arc<AuditLog> {
+        AuditLog::open(config::AuditLog {
+            dir: Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap(),
+            retention_days,
+            anonymize_client_ip: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn it_should_parse_the_torrent_requests_paths() {
+        assert_eq!(
+            torrent_request("/api/v1/torrents/443C7602B4FDE83D1154D6D9DA48808418B181B6"),
+            Some((
+                "443c7602b4fde83d1154d6d9da48808418b181b6".to_string(),
+                "torrent".to_string()
+            ))
+        );
+        assert_eq!(
+            torrent_request("/torrents/invalid/info"),
+            Some(("invalid".to_string(), "info".to_string()))
+        );
+        assert_eq!(torrent_request("/api/v1/health_check"), None);
+        assert_eq!(torrent_request("/api/v1/admin/torrents/abc/resolve"), None);
+    }
+
+    #[test]
+    fn it_should_zero_the_host_part_of_anonymized_addresses() {
+        let anonymized = |address: &str| anonymize(address.parse::<IpAddr>().unwrap()).to_string();
+
+        assert_eq!(anonymized("203.0.113.7"), "203.0.113.0");
+        assert_eq!(anonymized("::ffff:203.0.113.7"), "203.0.113.0");
+        assert_eq!(
+            anonymized("2001:db8:85a3:8d3:1319:8a2e:370:7348"),
+            "2001:db8:85a3::"
+        );
+    }
+
+    #[test]
+    fn it_should_map_the_response_status_to_the_outcome() {
+        assert_eq!(Outcome::from(StatusCode::OK), Outcome::Served);
+        assert_eq!(
+            Outcome::from(StatusCode::NOT_MODIFIED),
+            Outcome::NotModified
+        );
+        assert_eq!(
+            Outcome::from(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS),
+            Outcome::Denied
+        );
+        assert_eq!(
+            Outcome::from(StatusCode::INTERNAL_SERVER_ERROR),
+            Outcome::Failed
+        );
+    }
+
+    #[test]
+    fn it_should_return_the_matching_records_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = open(&dir, 7);
+        let now = now();
+
+        audit_log.append(&record(now - DAY - 60, "a")).unwrap();
+        audit_log.append(&record(now - 60, "b")).unwrap();
+        audit_log.append(&record(now, "a")).unwrap();
+
+        let all = audit_log.query(&AuditQuery::default()).unwrap();
+        let by_info_hash = audit_log
+            .query(&AuditQuery {
+                info_hash: Some("A".to_string()),
+                ..AuditQuery::default()
+            })
+            .unwrap();
+        let since = audit_log
+            .query(&AuditQuery {
+                since: Some(now - 60),
+                limit: Some(1),
+                ..AuditQuery::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            all,
+            vec![
+                record(now, "a"),
+                record(now - 60, "b"),
+                record(now - DAY - 60, "a")
+            ]
+        );
+        assert_eq!(
+            by_info_hash,
+            vec![record(now, "a"), record(now - DAY - 60, "a")]
+        );
+        assert_eq!(since, vec![record(now, "a")]);
+    }
+
+    #[test]
+    fn it_should_delete_the_files_older_than_the_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = now();
+        let old = dir.path().join(format!("{}.jsonl", date(now - 3 * DAY)));
+        let kept = dir.path().join(format!("{}.jsonl", date(now - DAY)));
+        std::fs::write(&old, "").unwrap();
+        std::fs::write(&kept, "").unwrap();
+
+        let _audit_log = open(&dir, 2);
+
+        assert!(!old.exists());
+        assert!(kept.exists());
+    }
+}
@@ -1,22 +1,51 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Read, Write},
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+    time::SystemTime,
 };
 
 use bytes::Bytes;
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
 use tracing::info;
 
 use crate::bit_torrent::info_hash::InfoHash;
 
 pub struct Cache {
     pub cache_dir: Utf8PathBuf,
+    /// The disk usage, kept up to date when torrents are added or removed.
+    /// It's counted from the directory the first time it's needed, and
+    /// again after a failed write. The lock is only taken to update the
+    /// counters, never while the files are read or written.
+    usage: Mutex<UsageCounters>,
+}
+
+#[derive(Default)]
+struct UsageCounters {
+    usage: Option<CacheUsage>,
+    /// Number of adds and removes. A count of the directory is only kept if
+    /// nothing changed while it was read.
+    changes: u64,
+}
+
+/// Disk usage of the torrents cache.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// Number of cached torrent files.
+    pub torrents: u64,
+    /// Total size of the cached torrent files in bytes.
+    pub bytes: u64,
 }
 
 impl Cache {
     #[must_use]
     pub fn new(cache_dir: Utf8PathBuf) -> Self {
-        Self { cache_dir }
+        Self {
+            cache_dir,
+            usage: Mutex::default(),
+        }
     }
 
     /// Returns true if the cache contains the torrent.
@@ -35,13 +64,56 @@ impl Cache {
 
         info!("adding torrent to cache in {filepath}");
 
-        let mut file = File::create(self.path(info_hash))?;
+        let written = self.write(&filepath, data);
+
+        let mut counters = self.lock_usage();
+        counters.changes += 1;
 
-        file.write_all(data)?;
+        match (written, counters.usage.as_mut()) {
+            (Err(err), _) => {
+                counters.usage = None;
+                return Err(err);
+            }
+            (Ok(replaced), Some(usage)) => {
+                match replaced {
+                    Some(bytes) => usage.bytes = usage.bytes.saturating_sub(bytes),
+                    None => usage.torrents += 1,
+                }
+                usage.bytes += data.len() as u64;
+            }
+            (Ok(_), None) => {}
+        }
 
         Ok(())
     }
 
+    /// Writes the file in a temporary file first, so it's never read half
+    /// written. Returns the size of the file it replaced, if there was one.
+    ///
+    /// The temporary file is linked to the path only if there is no file
+    /// there: only one of the concurrent adds of a new torrent can do it, so
+    /// it's counted once. The others replace it.
+    fn write(&self, filepath: &Utf8Path, data: &Bytes) -> io::Result<Option<u64>> {
+        let temp_path = self
+            .cache_dir
+            .join(format!(".{:016x}.tmp", rand::random::<u64>()));
+
+        let written = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(data))
+            .and_then(|()| match fs::hard_link(&temp_path, filepath) {
+                Ok(()) => Ok(None),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    let replaced = fs::metadata(filepath).map_or(0, |metadata| metadata.len());
+                    fs::rename(&temp_path, filepath).map(|()| Some(replaced))
+                }
+                Err(err) => Err(err),
+            });
+
+        let _ = fs::remove_file(&temp_path);
+
+        written
+    }
+
     /// Gets the torrent from the cache.
     ///
     /// # Errors
@@ -57,6 +129,129 @@ impl Cache {
         Ok(Bytes::from(buffer))
     }
 
+    /// Returns when the torrent was added to the cache.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the torrent is not cached.
+    pub fn modified(&self, info_hash: &InfoHash) -> io::Result<SystemTime> {
+        fs::metadata(self.path(info_hash))?.modified()
+    }
+
+    /// Removes a torrent from the cache.
+    ///
+    /// Returns `false` if the torrent was not cached.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't delete the cache file.
+    pub fn remove(&self, info_hash: &InfoHash) -> io::Result<bool> {
+        let filepath = self.path(info_hash);
+
+        let removed = fs::metadata(&filepath).map_or(0, |metadata| metadata.len());
+
+        match fs::remove_file(&filepath) {
+            Ok(()) => {
+                info!("removed torrent from cache in {filepath}");
+
+                let mut counters = self.lock_usage();
+                counters.changes += 1;
+
+                if let Some(usage) = counters.usage.as_mut() {
+                    usage.torrents = usage.torrents.saturating_sub(1);
+                    usage.bytes = usage.bytes.saturating_sub(removed);
+                }
+
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the number of cached torrents and the space they use. The
+    /// directory is only read the first time.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read the cache directory.
+    pub fn usage(&self) -> io::Result<CacheUsage> {
+        let changes = {
+            let counters = self.lock_usage();
+
+            if let Some(usage) = counters.usage {
+                return Ok(usage);
+            }
+
+            counters.changes
+        };
+
+        let counted = self.count_usage()?;
+
+        let mut counters = self.lock_usage();
+        if counters.changes == changes {
+            counters.usage = Some(counted);
+        }
+
+        Ok(counted)
+    }
+
+    /// Reads the cache directory to count the torrents and their size.
+    fn count_usage(&self) -> io::Result<CacheUsage> {
+        let mut usage = CacheUsage::default();
+
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+
+            if metadata.is_file() && entry.path().extension().is_some_and(|ext| ext == "torrent") {
+                usage.torrents += 1;
+                usage.bytes += metadata.len();
+            }
+        }
+
+        Ok(usage)
+    }
+
+    /// Returns the cached torrents with the time they were added, the most
+    /// recent first.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read the cache directory.
+    pub fn recent(&self) -> io::Result<Vec<(InfoHash, SystemTime)>> {
+        let mut torrents = vec![];
+
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.extension().is_none_or(|ext| ext != "torrent") {
+                continue;
+            }
+
+            let Some(info_hash) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| InfoHash::from_str(stem).ok())
+            else {
+                continue;
+            };
+
+            torrents.push((info_hash, entry.metadata()?.modified()?));
+        }
+
+        torrents.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+        Ok(torrents)
+    }
+
+    fn lock_usage(&self) -> MutexGuard<'_, UsageCounters> {
+        self.usage
+            .lock()
+            .expect("the cache usage lock should not be poisoned")
+    }
+
     /// Returns the cache file path.
     #[must_use]
     pub fn path(&self, info_hash: &InfoHash) -> Utf8PathBuf {
@@ -65,3 +260,78 @@ impl Cache {
         cached_torrent_path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bytes::Bytes;
+    use camino::Utf8PathBuf;
+
+    use super::{Cache, CacheUsage};
+    use crate::bit_torrent::info_hash::InfoHash;
+
+    fn info_hash(n: u8) -> InfoHash {
+        InfoHash::from_str(&format!("{n:040x}")).unwrap()
+    }
+
+    #[test]
+    fn it_should_keep_the_usage_up_to_date_when_torrents_are_added_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap());
+
+        cache
+            .add(&info_hash(1), &Bytes::from_static(b"first"))
+            .unwrap();
+
+        assert_eq!(
+            cache.usage().unwrap(),
+            CacheUsage {
+                torrents: 1,
+                bytes: 5
+            }
+        );
+
+        cache
+            .add(&info_hash(2), &Bytes::from_static(b"second"))
+            .unwrap();
+        cache
+            .add(&info_hash(1), &Bytes::from_static(b"1st"))
+            .unwrap();
+        cache.remove(&info_hash(2)).unwrap();
+        cache.remove(&info_hash(3)).unwrap();
+
+        let expected = CacheUsage {
+            torrents: 1,
+            bytes: 3,
+        };
+
+        assert_eq!(cache.usage().unwrap(), expected);
+        assert_eq!(cache.count_usage().unwrap(), expected);
+    }
+
+    #[test]
+    fn it_should_count_a_torrent_added_concurrently_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap());
+        cache.usage().unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    cache
+                        .add(&info_hash(1), &Bytes::from_static(b"first"))
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(
+            cache.usage().unwrap(),
+            CacheUsage {
+                torrents: 1,
+                bytes: 5
+            }
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! The IP address of the client that made a request.
+//!
+//! When the service runs behind a reverse proxy, the connections come from
+//! the proxy. The client address is then taken from the `X-Forwarded-For` or
+//! the `X-Real-IP` headers, but only if the connection comes from one of the
+//! configured trusted proxies: anybody else could send the headers.
+use std::net::IpAddr;
+
+use hyper::HeaderMap;
+use ipnet::IpNet;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// Returns the client address of a request made from `peer`.
+///
+/// If the peer is a trusted proxy, it's the last address in
+/// `X-Forwarded-For` that is not a trusted proxy, because proxies append the
+/// address they got the request from. Without that header, it's the one in
+/// `X-Real-IP`. The peer is returned when neither of them has a valid
+/// address.
+#[must_use]
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |address: &IpAddr| {
+        trusted_proxies
+            .iter()
+            .any(|proxy| proxy.contains(&address.to_canonical()))
+    };
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|address| address.trim().parse().ok())
+        .collect();
+
+    if let Some(last) = forwarded.last() {
+        return forwarded
+            .iter()
+            .rev()
+            .find(|address| !is_trusted(address))
+            .copied()
+            .unwrap_or(*last);
+    }
+
+    headers
+        .get(X_REAL_IP)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use hyper::HeaderMap;
+    use ipnet::IpNet;
+
+    use super::client_ip;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    fn headers(headers: &[(&'static str, &str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| (*name, value.parse().unwrap()))
+            .fold(HeaderMap::new(), |mut map, (name, value)| {
+                map.append(name, value);
+                map
+            })
+    }
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["172.16.0.0/12".parse().unwrap()]
+    }
+
+    #[test]
+    fn it_should_ignore_the_headers_from_untrusted_peers() {
+        let headers = headers(&[
+            ("x-forwarded-for", "198.51.100.1"),
+            ("x-real-ip", "198.51.100.2"),
+        ]);
+
+        assert_eq!(
+            client_ip(ip("203.0.113.7"), &headers, &trusted()),
+            ip("203.0.113.7")
+        );
+        assert_eq!(client_ip(ip("172.18.0.2"), &headers, &[]), ip("172.18.0.2"));
+    }
+
+    #[test]
+    fn it_should_take_the_last_untrusted_forwarded_address_from_trusted_proxies() {
+        let headers = headers(&[
+            ("x-forwarded-for", "192.0.2.66, 203.0.113.7"),
+            ("x-forwarded-for", "172.18.0.3"),
+        ]);
+
+        assert_eq!(
+            client_ip(ip("172.18.0.2"), &headers, &trusted()),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn it_should_fall_back_to_the_real_ip_header_and_then_to_the_peer() {
+        assert_eq!(
+            client_ip(
+                ip("::ffff:172.18.0.2"),
+                &headers(&[("x-real-ip", "203.0.113.7")]),
+                &trusted()
+            ),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(
+                ip("172.18.0.2"),
+                &headers(&[("x-forwarded-for", "unknown")]),
+                &trusted()
+            ),
+            ip("172.18.0.2")
+        );
+    }
+}
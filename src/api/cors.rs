@@ -0,0 +1,57 @@
+//! CORS policy for browser integrations.
+//!
+//! Web frontends on other origins can call the public API routes from
+//! JavaScript. Preflight `OPTIONS` requests are answered by the layer, without
+//! reaching the handlers. The admin API is not included.
+use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::Method;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{AllowedOrigins, Cors};
+
+/// Request headers the browser can send. Conditional requests need them.
+const ALLOWED_HEADERS: [HeaderName; 2] = [header::IF_NONE_MATCH, header::IF_MODIFIED_SINCE];
+
+/// The methods allowed by default.
+#[must_use]
+pub fn default_methods() -> Vec<Method> {
+    vec![Method::GET, Method::HEAD]
+}
+
+/// The headers exposed by default. They are needed to get the torrent
+/// filename, the info-hash, the caching information and the deprecation
+/// notice of the legacy routes.
+#[must_use]
+pub fn default_exposed_headers() -> Vec<HeaderName> {
+    vec![
+        header::CONTENT_DISPOSITION,
+        HeaderName::from_static("deprecation"),
+        header::ETAG,
+        header::LAST_MODIFIED,
+        header::LINK,
+        header::RETRY_AFTER,
+        HeaderName::from_static("x-torrust-torrent-infohash"),
+    ]
+}
+
+/// Builds the layer for the configured policy.
+///
+/// # Panics
+///
+/// Will panic if an allowed origin is not a valid header value. Origins are
+/// validated when the configuration is parsed.
+pub fn layer(cors: &Cors) -> CorsLayer {
+    let allow_origin = match &cors.allowed_origins {
+        AllowedOrigins::Any => AllowOrigin::any(),
+        AllowedOrigins::List(origins) => AllowOrigin::list(origins.iter().map(|origin| {
+            HeaderValue::from_str(origin).expect("the origin should be a valid header value")
+        })),
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(cors.allowed_methods.clone())
+        .allow_headers(ALLOWED_HEADERS)
+        .expose_headers(cors.exposed_headers.clone())
+        .max_age(cors.max_age)
+}
@@ -0,0 +1,77 @@
+//! Date formats for the feeds and the audit log. The dates are always in UTC,
+//! so they are computed from the Unix time without a time zone database.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The UTC date of the Unix time, like `2024-09-10`.
+#[must_use]
+pub fn date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / 86_400);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats the time as an [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339)
+/// UTC date, as required by Atom.
+#[must_use]
+pub fn rfc3339(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let seconds_of_day = seconds % 86_400;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// The date for the days since the Unix epoch, with the
+/// [algorithm by Howard Hinnant](https://howardhinnant.github.io/date_algorithms.html#civil_from_days).
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{date, rfc3339};
+
+    #[test]
+    fn it_should_format_dates_as_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "2000-02-29T12:34:56Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_hours(497_880)),
+            "2026-10-19T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn it_should_format_the_date_of_a_unix_time() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_827_696), "2000-02-29");
+    }
+}
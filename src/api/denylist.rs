@@ -0,0 +1,271 @@
+//! Info-hashes the service must not serve, usually because of legal takedown
+//! notices.
+//!
+//! The denylist is backed by a plain text file with one info-hash per line.
+//! Empty lines and anything after a `#` are ignored, so notices can be
+//! referenced next to the hash:
+//!
+//! ```text
+//! # Takedown notices
+//! 443c7602b4fde83d1154d6d9da48808418b181b6 # notice 2024-001
+//! ```
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use camino::Utf8PathBuf;
+use tracing::{info, warn};
+
+use crate::bit_torrent::info_hash::InfoHash;
+
+pub struct Denylist {
+    pub path: Utf8PathBuf,
+    info_hashes: RwLock<BTreeSet<InfoHash>>,
+}
+
+impl Denylist {
+    /// Loads the denylist from the file. A missing file is an empty denylist.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file exists but it can't be read.
+    pub fn load(path: Utf8PathBuf) -> io::Result<Self> {
+        let denylist = Self {
+            path,
+            info_hashes: RwLock::new(BTreeSet::new()),
+        };
+
+        denylist.reload()?;
+
+        Ok(denylist)
+    }
+
+    /// Replaces the in-memory denylist with the current contents of the file.
+    ///
+    /// Returns the number of denied info-hashes.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file exists but it can't be read.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn reload(&self) -> io::Result<usize> {
+        let info_hashes = match fs::read_to_string(&self.path) {
+            Ok(contents) => parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let len = info_hashes.len();
+
+        *self.info_hashes.write().expect("denylist lock poisoned") = info_hashes;
+
+        info!("loaded {len} denied info-hashes from {}", self.path);
+
+        Ok(len)
+    }
+
+    /// Returns true if the info-hash must not be served.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    #[must_use]
+    pub fn contains(&self, info_hash: &InfoHash) -> bool {
+        self.info_hashes
+            .read()
+            .expect("denylist lock poisoned")
+            .contains(info_hash)
+    }
+
+    /// Returns all the denied info-hashes.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    #[must_use]
+    pub fn list(&self) -> Vec<InfoHash> {
+        self.info_hashes
+            .read()
+            .expect("denylist lock poisoned")
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Adds an info-hash to the denylist and persists it in the file. The
+    /// optional note is stored as a comment next to the hash.
+    ///
+    /// Returns `false` if the info-hash was already denied.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write the file.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn add(&self, info_hash: &InfoHash, note: Option<&str>) -> io::Result<bool> {
+        let mut info_hashes = self.info_hashes.write().expect("denylist lock poisoned");
+
+        if info_hashes.contains(info_hash) {
+            return Ok(false);
+        }
+
+        let mut contents = fs::read_to_string(&self.path).or_else(|err| {
+            if err.kind() == io::ErrorKind::NotFound {
+                Ok(String::new())
+            } else {
+                Err(err)
+            }
+        })?;
+
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+
+        contents.push_str(&info_hash.to_hex_string());
+
+        if let Some(note) = note {
+            contents.push_str(" # ");
+            contents.push_str(&note.replace('\n', " "));
+        }
+
+        contents.push('\n');
+
+        fs::write(&self.path, contents)?;
+
+        info_hashes.insert(*info_hash);
+
+        Ok(true)
+    }
+
+    /// Removes an info-hash from the denylist and from the file.
+    ///
+    /// Returns `false` if the info-hash was not denied.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't rewrite the file.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn remove(&self, info_hash: &InfoHash) -> io::Result<bool> {
+        let mut info_hashes = self.info_hashes.write().expect("denylist lock poisoned");
+
+        if !info_hashes.contains(info_hash) {
+            return Ok(false);
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+
+        let kept: String = contents
+            .lines()
+            .filter(|line| parse_line(line) != Some(*info_hash))
+            .flat_map(|line| [line, "\n"])
+            .collect();
+
+        fs::write(&self.path, kept)?;
+
+        info_hashes.remove(info_hash);
+
+        Ok(true)
+    }
+}
+
+fn parse(contents: &str) -> BTreeSet<InfoHash> {
+    contents.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<InfoHash> {
+    let value = line.split('#').next().unwrap_or_default().trim();
+
+    if value.is_empty() {
+        return None;
+    }
+
+    let info_hash = InfoHash::from_str(&value.to_lowercase()).ok();
+
+    if info_hash.is_none() {
+        warn!("ignoring invalid info-hash in denylist: {value}");
+    }
+
+    info_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+
+    use camino::Utf8PathBuf;
+
+    use super::{parse, Denylist};
+    use crate::bit_torrent::info_hash::InfoHash;
+
+    fn info_hash() -> InfoHash {
+        InfoHash::from_str("443c7602b4fde83d1154d6d9da48808418b181b6").unwrap() // DevSkim: ignore DS173237
+    }
+
+    fn denylist_path(dir: &tempfile::TempDir) -> Utf8PathBuf {
+        Utf8PathBuf::from_path_buf(dir.path().join("denylist.txt")).unwrap()
+    }
+
+    #[test]
+    fn it_should_ignore_comments_empty_lines_and_invalid_hashes() {
+        let info_hashes = parse(
+            "# takedowns\n\n443C7602B4FDE83D1154D6D9DA48808418B181B6 # notice 1\ninvalid\n", // DevSkim: ignore DS173237
+        );
+
+        assert_eq!(
+            info_hashes.into_iter().collect::<Vec<_>>(),
+            vec![info_hash()]
+        );
+    }
+
+    #[test]
+    fn a_missing_file_should_be_an_empty_denylist() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let denylist = Denylist::load(denylist_path(&dir)).unwrap();
+
+        assert!(denylist.list().is_empty());
+    }
+
+    #[test]
+    fn added_info_hashes_should_be_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let denylist = Denylist::load(denylist_path(&dir)).unwrap();
+
+        assert!(denylist.add(&info_hash(), Some("notice 1")).unwrap());
+        assert!(!denylist.add(&info_hash(), None).unwrap());
+
+        let reloaded = Denylist::load(denylist_path(&dir)).unwrap();
+
+        assert!(reloaded.contains(&info_hash()));
+    }
+
+    #[test]
+    fn removed_info_hashes_should_be_deleted_from_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            denylist_path(&dir),
+            "# takedowns\n443c7602b4fde83d1154d6d9da48808418b181b6 # notice 1\n", // DevSkim: ignore DS173237
+        )
+        .unwrap();
+        let denylist = Denylist::load(denylist_path(&dir)).unwrap();
+
+        assert!(denylist.remove(&info_hash()).unwrap());
+
+        assert!(!denylist.contains(&info_hash()));
+        assert_eq!(
+            fs::read_to_string(denylist_path(&dir)).unwrap(),
+            "# takedowns\n"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Deprecated legacy routes.
+//!
+//! The routes at the root, like `/torrents/:info_hash`, were there before the
+//! API was versioned. They are aliases of the [`v1`](crate::api::v1) routes,
+//! and their responses have the `Deprecation` header
+//! ([RFC 9745](https://www.rfc-editor.org/rfc/rfc9745)) and a `Link` header to
+//! the successor route.
+use axum::extract::{OriginalUri, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use hyper::header::{self, HeaderValue};
+
+/// When the legacy routes were deprecated, 2026-10-19T00:00:00Z, as an HTTP
+/// structured field date.
+const DEPRECATION_DATE: &str = "@1792368000";
+
+/// Middleware for the legacy routes. They are served by the `successor`
+/// version, like [`v1::PREFIX`](crate::api::v1::PREFIX).
+pub async fn legacy_routes(
+    State(successor): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Nested routers see the path without the prefix.
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().path(), |uri| uri.path())
+        .to_string();
+
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static(DEPRECATION_DATE));
+    if let Ok(link) = format!("<{successor}{path}>; rel=\"successor-version\"").parse() {
+        headers.append(header::LINK, link);
+    }
+
+    response
+}
@@ -0,0 +1,481 @@
+//! [Atom](https://www.rfc-editor.org/rfc/rfc4287) and
+//! [RSS 2.0](https://www.rssboard.org/rss-specification) feeds of the torrents
+//! recently added to the cache.
+//!
+//! Each entry has the torrent name, size and info-hash, when it was resolved,
+//! and links to the torrent file, its JSON information and its magnet link.
+//! Denylisted torrents are never listed, and private torrents are only listed
+//! if the configuration allows it.
+//!
+//! The links are built from the configured base URL. Without one, they are
+//! relative to the root of the server: the request `Host` is chosen by the
+//! client, so it can't be trusted.
+//!
+//! Building a feed reads the cached torrents from disk, so the rendered feeds
+//! are reused for a minute, the same time clients are told to cache them.
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use hyper::{header, StatusCode};
+use tracing::error;
+
+use crate::api::dates::rfc3339;
+use crate::api::v1;
+use crate::bit_torrent::magnet::MagnetLink;
+use crate::bit_torrent::resolver::TorrentMetadata;
+use crate::AppState;
+
+const TITLE: &str = "Hash2Torrent: recently resolved torrents";
+
+/// `Cache-Control` for the feeds. New torrents are listed within a minute.
+const CACHE_CONTROL: &str = "public, max-age=60";
+
+/// How long a rendered feed is reused.
+const RENDERED_FEED_TTL: Duration = Duration::from_mins(1);
+
+/// The Atom feed ID when there is no base URL. Atom IDs can't be relative.
+const ATOM_FEED_ID: &str = "urn:hash2torrent:feeds:recent";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Format {
+    Atom,
+    Rss,
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Atom => "application/atom+xml",
+            Format::Rss => "application/rss+xml",
+        }
+    }
+}
+
+struct RenderedFeed {
+    rendered_at: Instant,
+    feed: Arc<str>,
+}
+
+impl RenderedFeed {
+    fn is_fresh(&self) -> bool {
+        self.rendered_at.elapsed() < RENDERED_FEED_TTL
+    }
+}
+
+/// The recently rendered feeds, by format.
+#[derive(Default)]
+pub struct FeedCache {
+    feeds: Mutex<HashMap<Format, RenderedFeed>>,
+}
+
+impl FeedCache {
+    fn get(&self, format: Format) -> Option<Arc<str>> {
+        let feeds = self
+            .feeds
+            .lock()
+            .expect("the feed cache lock should not be poisoned");
+
+        feeds
+            .get(&format)
+            .filter(|rendered| rendered.is_fresh())
+            .map(|rendered| rendered.feed.clone())
+    }
+
+    fn insert(&self, format: Format, feed: Arc<str>) {
+        let mut feeds = self
+            .feeds
+            .lock()
+            .expect("the feed cache lock should not be poisoned");
+
+        feeds.insert(
+            format,
+            RenderedFeed {
+                rendered_at: Instant::now(),
+                feed,
+            },
+        );
+    }
+}
+
+/// A torrent in the feed.
+struct Entry {
+    metadata: TorrentMetadata,
+    resolved_at: SystemTime,
+    magnet_link: String,
+}
+
+/// Returns the Atom feed of the recently resolved torrents.
+#[utoipa::path(
+    get,
+    path = "/feeds/recent.atom",
+    tag = "feeds",
+    responses(
+        (status = 200, description = "The Atom feed", body = String, content_type = "application/atom+xml"),
+    )
+)]
+pub async fn recent_atom_handler(State(app_state): State<Arc<AppState>>) -> Response {
+    feed_handler(app_state, Format::Atom).await
+}
+
+/// Returns the RSS feed of the recently resolved torrents.
+#[utoipa::path(
+    get,
+    path = "/feeds/recent.rss",
+    tag = "feeds",
+    responses(
+        (status = 200, description = "The RSS feed", body = String, content_type = "application/rss+xml"),
+    )
+)]
+pub async fn recent_rss_handler(State(app_state): State<Arc<AppState>>) -> Response {
+    feed_handler(app_state, Format::Rss).await
+}
+
+/// Returns the rendered feed, if it's recent enough, or renders it again.
+/// The cached torrents are read in a blocking task.
+async fn feed_handler(app_state: Arc<AppState>, format: Format) -> Response {
+    if let Some(feed) = app_state.feed_cache.get(format) {
+        return feed_response(format, &feed);
+    }
+
+    let entries = {
+        let app_state = app_state.clone();
+        tokio::task::spawn_blocking(move || recent_entries(&app_state))
+            .await
+            .unwrap_or_else(|err| Err(io::Error::other(err)))
+    };
+
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => return error_response(&err),
+    };
+
+    let base_url = base_url(&app_state);
+    let feed: Arc<str> = match format {
+        Format::Atom => atom(base_url, &entries),
+        Format::Rss => rss(base_url, &entries),
+    }
+    .into();
+
+    app_state.feed_cache.insert(format, feed.clone());
+
+    feed_response(format, &feed)
+}
+
+fn feed_response(format: Format, feed: &str) -> Response {
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                format!("{}; charset=utf-8", format.content_type()),
+            ),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+        ],
+        feed.to_string(),
+    )
+        .into_response()
+}
+
+fn error_response(err: &io::Error) -> Response {
+    error!("error reading the cache for the feeds: {err}");
+
+    (StatusCode::INTERNAL_SERVER_ERROR, "Cache error").into_response()
+}
+
+/// The most recent cached torrents that can be listed, up to the configured
+/// length.
+fn recent_entries(app_state: &AppState) -> io::Result<Vec<Entry>> {
+    let feeds = &app_state.config.feeds;
+    let mut entries = vec![];
+
+    for (info_hash, resolved_at) in app_state.cache.recent()? {
+        if entries.len() == feeds.length {
+            break;
+        }
+
+        if app_state.denylist.contains(&info_hash) {
+            continue;
+        }
+
+        let Some(metadata) = app_state
+            .cache
+            .get(&info_hash)
+            .ok()
+            .and_then(|bytes| TorrentMetadata::from_torrent_bytes(bytes).ok())
+        else {
+            continue;
+        };
+
+        if metadata.is_private() && !feeds.include_private {
+            continue;
+        }
+
+        let magnet_link = MagnetLink::for_torrent(&metadata)
+            .with_trackers(app_state.trackers.all())
+            .to_string();
+
+        entries.push(Entry {
+            metadata,
+            resolved_at,
+            magnet_link,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// The configured base URL, without the trailing slash. It's empty when it's
+/// not configured, so the links are relative to the root.
+fn base_url(app_state: &AppState) -> &str {
+    app_state
+        .config
+        .feeds
+        .base_url
+        .as_deref()
+        .map_or("", |base_url| base_url.trim_end_matches('/'))
+}
+
+fn atom(base_url: &str, entries: &[Entry]) -> String {
+    let self_url = format!("{base_url}{}/feeds/recent.atom", v1::PREFIX);
+    let updated = entries
+        .first()
+        .map_or_else(SystemTime::now, |entry| entry.resolved_at);
+
+    let mut feed = String::new();
+
+    let _ = writeln!(feed, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    let _ = writeln!(feed, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    let _ = writeln!(feed, "  <title>{TITLE}</title>");
+    let id = if base_url.is_empty() {
+        ATOM_FEED_ID
+    } else {
+        &self_url
+    };
+    let _ = writeln!(feed, "  <id>{}</id>", escape(id));
+    let _ = writeln!(feed, r#"  <link rel="self" href="{}"/>"#, escape(&self_url));
+    let _ = writeln!(
+        feed,
+        r#"  <link rel="alternate" href="{}/"/>"#,
+        escape(base_url)
+    );
+    let _ = writeln!(feed, "  <updated>{}</updated>", rfc3339(updated));
+    let _ = writeln!(feed, "  <author><name>Hash2Torrent</name></author>");
+
+    for entry in entries {
+        let info_hash = entry.metadata.info_hash.to_hex_string();
+        let torrent_url = torrent_url(base_url, &info_hash);
+
+        let _ = writeln!(feed, "  <entry>");
+        let _ = writeln!(feed, "    <title>{}</title>", escape(&title(entry)));
+        let _ = writeln!(feed, "    <id>urn:btih:{info_hash}</id>");
+        let _ = writeln!(
+            feed,
+            "    <updated>{}</updated>",
+            rfc3339(entry.resolved_at)
+        );
+        let _ = writeln!(
+            feed,
+            r#"    <link rel="enclosure" type="application/x-bittorrent" length="{}" href="{}"/>"#,
+            entry.metadata.torrent_bytes.len(),
+            escape(&torrent_url)
+        );
+        let _ = writeln!(
+            feed,
+            r#"    <link rel="alternate" type="application/json" href="{}/info"/>"#,
+            escape(&torrent_url)
+        );
+        let _ = writeln!(
+            feed,
+            r#"    <link rel="related" href="{}"/>"#,
+            escape(&entry.magnet_link)
+        );
+        let _ = writeln!(
+            feed,
+            r#"    <content type="html">{}</content>"#,
+            escape(&description(&torrent_url, entry))
+        );
+        let _ = writeln!(feed, "  </entry>");
+    }
+
+    let _ = writeln!(feed, "</feed>");
+
+    feed
+}
+
+fn rss(base_url: &str, entries: &[Entry]) -> String {
+    let self_url = format!("{base_url}{}/feeds/recent.rss", v1::PREFIX);
+    let updated = entries
+        .first()
+        .map_or_else(SystemTime::now, |entry| entry.resolved_at);
+
+    let mut feed = String::new();
+
+    let _ = writeln!(feed, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    let _ = writeln!(
+        feed,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">"#
+    );
+    let _ = writeln!(feed, "  <channel>");
+    let _ = writeln!(feed, "    <title>{TITLE}</title>");
+    let _ = writeln!(feed, "    <link>{}/</link>", escape(base_url));
+    let _ = writeln!(
+        feed,
+        "    <description>Torrents recently resolved by Hash2Torrent</description>"
+    );
+    let _ = writeln!(
+        feed,
+        r#"    <atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape(&self_url)
+    );
+    let _ = writeln!(
+        feed,
+        "    <lastBuildDate>{}</lastBuildDate>",
+        httpdate::fmt_http_date(updated)
+    );
+
+    for entry in entries {
+        let info_hash = entry.metadata.info_hash.to_hex_string();
+        let torrent_url = torrent_url(base_url, &info_hash);
+
+        let _ = writeln!(feed, "    <item>");
+        let _ = writeln!(feed, "      <title>{}</title>", escape(&title(entry)));
+        let _ = writeln!(feed, "      <link>{}</link>", escape(&torrent_url));
+        let _ = writeln!(
+            feed,
+            r#"      <guid isPermaLink="false">urn:btih:{info_hash}</guid>"#
+        );
+        let _ = writeln!(
+            feed,
+            "      <pubDate>{}</pubDate>",
+            httpdate::fmt_http_date(entry.resolved_at)
+        );
+        let _ = writeln!(
+            feed,
+            r#"      <enclosure url="{}" length="{}" type="application/x-bittorrent"/>"#,
+            escape(&torrent_url),
+            entry.metadata.torrent_bytes.len()
+        );
+        let _ = writeln!(
+            feed,
+            "      <description>{}</description>",
+            escape(&description(&torrent_url, entry))
+        );
+        let _ = writeln!(feed, "    </item>");
+    }
+
+    let _ = writeln!(feed, "  </channel>");
+    let _ = writeln!(feed, "</rss>");
+
+    feed
+}
+
+fn torrent_url(base_url: &str, info_hash: &str) -> String {
+    format!("{base_url}{}/torrents/{info_hash}", v1::PREFIX)
+}
+
+fn title(entry: &Entry) -> String {
+    entry
+        .metadata
+        .name
+        .clone()
+        .unwrap_or_else(|| entry.metadata.info_hash.to_hex_string())
+}
+
+/// The entry details in HTML. Feed readers show it as the entry contents.
+fn description(torrent_url: &str, entry: &Entry) -> String {
+    let metadata = &entry.metadata;
+
+    format!(
+        "<p>Size: {}<br/>Files: {}<br/>Info-hash: {}<br/>Resolved: {}</p>\
+         <p><a href=\"{url}\">Torrent file</a> | <a href=\"{url}/info\">JSON</a> | <a href=\"{magnet}\">Magnet link</a></p>",
+        format_size(metadata.total_length()),
+        metadata.files.len(),
+        metadata.info_hash.to_hex_string(),
+        httpdate::fmt_http_date(entry.resolved_at),
+        url = escape(torrent_url),
+        magnet = escape(&entry.magnet_link),
+    )
+}
+
+/// Escapes the text for XML and HTML. Characters that are not allowed in
+/// XML documents, like most control characters, are dropped: torrent names
+/// can have them.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if is_xml_char(c) => escaped.push(c),
+            _ => {}
+        }
+    }
+
+    escaped
+}
+
+/// Whether the character matches the
+/// [`Char` production](https://www.w3.org/TR/xml/#charsets) of XML 1.0.
+/// Surrogates are not Rust characters.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+/// The size in binary units, like `4.59 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut unit = 0;
+    let mut divisor = 1024u64;
+    while unit < UNITS.len() - 1 && bytes / divisor >= 1024 {
+        unit += 1;
+        divisor *= 1024;
+    }
+
+    let hundredths = bytes * 100 / divisor;
+
+    format!(
+        "{}.{:02} {}",
+        hundredths / 100,
+        hundredths % 100,
+        UNITS[unit]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, format_size};
+
+    #[test]
+    fn it_should_format_sizes_in_binary_units() {
+        assert_eq!(format_size(13), "13 B");
+        assert_eq!(format_size(1536), "1.50 KiB");
+        assert_eq!(format_size(4_932_407_296), "4.59 GiB");
+    }
+
+    #[test]
+    fn it_should_escape_xml_special_characters() {
+        assert_eq!(
+            escape(r#"<a href="?a=1&b='2'">"#),
+            "&lt;a href=&quot;?a=1&amp;b=&apos;2&apos;&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn it_should_drop_the_characters_not_allowed_in_xml() {
+        assert_eq!(escape("a\u{0}b\u{1b}c\u{fffe}d\u{ffff}e"), "abcde");
+        assert_eq!(escape("tab\tline\nreturn\r😀"), "tab\tline\nreturn\r😀");
+    }
+}
@@ -0,0 +1,164 @@
+//! The name of the downloaded torrent file.
+//!
+//! By default it's the torrent name in the info dictionary, like
+//! `ubuntu-23.04-desktop-amd64.iso.torrent`. The name comes from other peers,
+//! so path separators and control characters are removed. It's sent in the
+//! `Content-Disposition` header as described in [RFC 6266](https://www.rfc-editor.org/rfc/rfc6266),
+//! with an ASCII fallback for old clients.
+use std::fmt::Write;
+use std::str::FromStr;
+
+use utoipa::ToSchema;
+
+use crate::bit_torrent::info_hash::InfoHash;
+
+/// Maximum number of characters taken from the torrent name.
+const MAX_NAME_CHARS: usize = 200;
+
+/// How the torrent file is named. It's chosen with the `filename` query
+/// parameter.
+#[derive(ToSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[schema(rename_all = "snake_case")]
+pub enum FilenameStyle {
+    /// The torrent name, or the info-hash if the torrent has no valid name.
+    #[default]
+    Name,
+    /// The info-hash in hexadecimal.
+    InfoHash,
+}
+
+impl FromStr for FilenameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "info_hash" => Ok(Self::InfoHash),
+            _ => Err(format!("Invalid filename: {s}")),
+        }
+    }
+}
+
+/// The torrent filename, with the `.torrent` extension.
+#[must_use]
+pub fn torrent_filename(name: Option<&str>, info_hash: &InfoHash, style: FilenameStyle) -> String {
+    let name = match style {
+        FilenameStyle::Name => name.and_then(sanitize),
+        FilenameStyle::InfoHash => None,
+    };
+
+    format!(
+        "{}.torrent",
+        name.unwrap_or_else(|| info_hash.to_hex_string())
+    )
+}
+
+/// Removes the characters that are not safe in a filename. It returns `None`
+/// if nothing is left.
+fn sanitize(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .take(MAX_NAME_CHARS)
+        .collect();
+
+    // Leading dots would make hidden files, or `..`.
+    let name = name.trim().trim_start_matches('.').trim();
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The `Content-Disposition` header value for the filename. It has a
+/// `filename` parameter with the non-ASCII characters replaced, and a
+/// `filename*` parameter with the UTF-8 name percent-encoded.
+#[must_use]
+pub fn content_disposition(filename: &str) -> String {
+    let ascii_filename: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let mut encoded_filename = String::new();
+    for byte in filename.bytes() {
+        if is_attr_char(byte) {
+            encoded_filename.push(char::from(byte));
+        } else {
+            let _ = write!(encoded_filename, "%{byte:02X}");
+        }
+    }
+
+    format!("attachment; filename=\"{ascii_filename}\"; filename*=UTF-8''{encoded_filename}")
+}
+
+/// The characters that don't need to be percent-encoded in the `filename*`
+/// parameter ([RFC 8187](https://www.rfc-editor.org/rfc/rfc8187#section-3.2.1)).
+fn is_attr_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_disposition, torrent_filename, FilenameStyle};
+    use crate::bit_torrent::info_hash::InfoHash;
+
+    fn info_hash() -> InfoHash {
+        "443c7602b4fde83d1154d6d9da48808418b181b6".parse().unwrap()
+    }
+
+    #[test]
+    fn it_should_name_the_file_after_the_torrent() {
+        assert_eq!(
+            torrent_filename(
+                Some("ubuntu-23.04-desktop-amd64.iso"),
+                &info_hash(),
+                FilenameStyle::Name
+            ),
+            "ubuntu-23.04-desktop-amd64.iso.torrent"
+        );
+    }
+
+    #[test]
+    fn it_should_remove_path_separators_and_control_characters() {
+        assert_eq!(
+            torrent_filename(
+                Some("../etc/pass\nwd\\x"),
+                &info_hash(),
+                FilenameStyle::Name
+            ),
+            "_etc_passwd_x.torrent"
+        );
+    }
+
+    #[test]
+    fn it_should_fall_back_to_the_info_hash() {
+        let hex_filename = "443c7602b4fde83d1154d6d9da48808418b181b6.torrent";
+
+        assert_eq!(
+            torrent_filename(None, &info_hash(), FilenameStyle::Name),
+            hex_filename
+        );
+        assert_eq!(
+            torrent_filename(Some(" .. \u{7}"), &info_hash(), FilenameStyle::Name),
+            hex_filename
+        );
+        assert_eq!(
+            torrent_filename(Some("sample.txt"), &info_hash(), FilenameStyle::InfoHash),
+            hex_filename
+        );
+    }
+
+    #[test]
+    fn it_should_encode_the_filename_as_described_in_rfc_6266() {
+        assert_eq!(
+            content_disposition("Ünïcode \"name\".torrent"),
+            "attachment; filename=\"_n_code _name_.torrent\"; filename*=UTF-8''%C3%9Cn%C3%AFcode%20%22name%22.torrent"
+        );
+    }
+}
@@ -1,20 +1,40 @@
-use axum::extract::{Path, State};
-use axum::response::{Html, IntoResponse, Response};
+use axum::extract::{Path, RawQuery, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use bytes::Bytes;
+use futures_util::stream::{self, StreamExt};
 use hyper::{header, HeaderMap, StatusCode};
+use librqbit::dht::Id20;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, error, info, trace};
+use utoipa::ToSchema;
 
+use crate::api::address;
+use crate::api::audit_log::CacheStatus;
+use crate::api::filename::{self, torrent_filename, FilenameStyle};
+use crate::api::http_cache;
+use crate::api::webhooks::{self, Callback, NotificationEvent, PendingResolution, Webhooks};
 use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::magnet::MagnetLink;
+use crate::bit_torrent::peer_discovery::PeerLookup;
+use crate::bit_torrent::resolver::{
+    Progress, ResolutionEvent, ResolveError, ResolveOptions, ResolverStats, TorrentFile,
+    TorrentMetadata,
+};
+use crate::bit_torrent::swarm::SwarmScrape;
+use crate::bit_torrent::trackers;
 
 use crate::AppState;
 
 /// The info hash URL path parameter.
 ///
-/// For example: ` http://127.0.0.1:3000/torrents/443c7602b4fde83d1154d6d9da48808418b181b6`.
+/// For example: ` http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6`.
 ///
 /// The info hash represents the value collected from the URL path parameter.
 /// It does not include validation as this is done by the API endpoint handler,
@@ -23,64 +43,983 @@ use crate::AppState;
 pub struct InfoHashParam(pub String);
 
 impl InfoHashParam {
-    fn lowercase(&self) -> String {
+    pub(crate) fn lowercase(&self) -> String {
         self.0.to_lowercase()
     }
 }
 
+/// Maximum number of trackers a client can add to a request.
+const MAX_REQUEST_TRACKERS: usize = 10;
+
+/// Maximum number of peers a client can add to a request.
+const MAX_REQUEST_PEERS: usize = 10;
+
+/// Number of nodes of the routing table the DHT scrapes start from.
+const SWARM_DHT_START_NODES: usize = 8;
+
+/// Seconds a client should wait before trying again when the resolution
+/// queue is full.
+const RETRY_AFTER_SECONDS: u64 = 10;
+
+/// The query parameters for the metainfo file endpoint.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6?timeout=30&peer=1.2.3.4:51000`.
+///
+/// - `tr`: a tracker to announce to, besides the configured default trackers.
+///   It can be repeated. Like peers, only trackers on public addresses are
+///   allowed.
+/// - `timeout`: seconds the client has to resolve the torrent. It's capped
+///   to the configured maximum.
+/// - `peer`: the `ip:port` of a peer that has the torrent. It's dialed
+///   directly to get the metadata. It can be repeated. Only public addresses
+///   are allowed, so clients can't make the service dial its own network.
+/// - `filename`: `name` (default) to name the torrent file after the torrent,
+///   or `info_hash` to name it after the info-hash.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MetainfoQuery {
+    pub trackers: Vec<String>,
+    pub timeout: Option<Duration>,
+    pub peers: Vec<SocketAddr>,
+    pub filename: FilenameStyle,
+}
+
+impl MetainfoQuery {
+    /// Parses the raw query string.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error message if a parameter is not valid.
+    pub fn parse(query: Option<&str>) -> Result<Self, String> {
+        let mut metainfo_query = Self::default();
+
+        let Some(query) = query else {
+            return Ok(metainfo_query);
+        };
+
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            if key == "tr" {
+                if !trackers::is_supported(&value) {
+                    return Err(format!("Unsupported tracker: {value}"));
+                }
+                if tracker_host(&value)
+                    .and_then(|host| host.parse().ok())
+                    .is_some_and(|ip| !address::is_public(ip))
+                {
+                    return Err(format!("Tracker address not allowed: {value}"));
+                }
+                if metainfo_query.trackers.len() == MAX_REQUEST_TRACKERS {
+                    return Err(format!(
+                        "Too many trackers, the maximum is {MAX_REQUEST_TRACKERS}"
+                    ));
+                }
+                metainfo_query.trackers.push(value.into_owned());
+            } else if key == "timeout" {
+                match value.parse::<u64>() {
+                    Ok(seconds) if seconds > 0 => {
+                        metainfo_query.timeout = Some(Duration::from_secs(seconds));
+                    }
+                    _ => return Err(format!("Invalid timeout: {value}")),
+                }
+            } else if key == "peer" {
+                let Ok(peer) = value.parse::<SocketAddr>() else {
+                    return Err(format!("Invalid peer address: {value}"));
+                };
+                if !address::is_public(peer.ip()) {
+                    return Err(format!("Peer address not allowed: {value}"));
+                }
+                if metainfo_query.peers.len() == MAX_REQUEST_PEERS {
+                    return Err(format!(
+                        "Too many peers, the maximum is {MAX_REQUEST_PEERS}"
+                    ));
+                }
+                metainfo_query.peers.push(peer);
+            } else if key == "filename" {
+                metainfo_query.filename = value.parse()?;
+            }
+        }
+
+        Ok(metainfo_query)
+    }
+
+    /// Resolves the hosts of the trackers given in the query and checks that
+    /// all their addresses are public, like the `peer` addresses.
+    ///
+    /// Hosts that don't resolve are accepted: the resolver can't reach them
+    /// either.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error message if a tracker host resolves to an address
+    /// that is not public.
+    pub async fn check_trackers(&self) -> Result<(), String> {
+        let lookups = self.trackers.iter().map(|tracker| async move {
+            let Some(host) = tracker_host(tracker) else {
+                return Ok(());
+            };
+            let Ok(mut addresses) = tokio::net::lookup_host((host.as_str(), 0)).await else {
+                return Ok(());
+            };
+            if addresses.any(|address| !address::is_public(address.ip())) {
+                return Err(format!("Tracker address not allowed: {tracker}"));
+            }
+            Ok(())
+        });
+
+        futures_util::future::join_all(lookups)
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Parses the raw query string and checks the tracker hosts.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error message if a parameter is not valid or a tracker
+    /// host is not public.
+    pub async fn parse_and_check(query: Option<&str>) -> Result<Self, String> {
+        let metainfo_query = Self::parse(query)?;
+        metainfo_query.check_trackers().await?;
+        Ok(metainfo_query)
+    }
+}
+
+/// Returns the host of a tracker URL, without the brackets of IPv6 hosts.
+fn tracker_host(tracker: &str) -> Option<String> {
+    let url = reqwest::Url::parse(tracker).ok()?;
+    let host = url.host_str()?;
+
+    Some(
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+    )
+}
+
+/// Returns the torrent file for the info-hash. It's served from the cache or
+/// resolved from the `BitTorrent` network.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}",
+    tag = "torrents",
+    params(
+        ("info_hash" = String, Path, description = "The info-hash in hexadecimal", example = "443c7602b4fde83d1154d6d9da48808418b181b6"),
+        ("tr" = Option<Vec<String>>, Query, description = "A tracker to announce to, besides the default trackers. It can be repeated, up to 10 times. Only public addresses are allowed"),
+        ("timeout" = Option<u64>, Query, description = "Seconds the client has to resolve the torrent, up to the configured maximum"),
+        ("peer" = Option<Vec<String>>, Query, description = "The `ip:port` of a peer that has the torrent. It can be repeated, up to 10 times. Only public addresses are allowed"),
+        ("filename" = inline(Option<FilenameStyle>), Query, description = "How the torrent file is named"),
+        ("If-None-Match" = Option<String>, Header, description = "The `ETag` of a torrent file the client already has"),
+        ("If-Modified-Since" = Option<String>, Header, description = "The `Last-Modified` date of a torrent file the client already has"),
+    ),
+    responses(
+        (status = 200, description = "The torrent file", body = [u8], content_type = "application/x-bittorrent",
+            headers(
+                ("Content-Disposition" = String, description = "The torrent filename"),
+                ("ETag" = String, description = "The quoted info-hash"),
+                ("Last-Modified" = String, description = "When the torrent was resolved"),
+                ("X-Torrust-Torrent-Infohash" = String, description = "The info-hash in hexadecimal"),
+            )
+        ),
+        (status = 304, description = "The client already has the torrent file"),
+        (status = 400, description = "Invalid info-hash or query parameters", body = String),
+        (status = 504, description = "The torrent was not resolved in time", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+        (status = 503, description = "The resolution queue is full", body = String,
+            headers(("Retry-After" = u64, description = "Seconds to wait before trying again"))
+        ),
+    )
+)]
 #[allow(clippy::module_name_repetitions)]
 pub async fn get_metainfo_file_handler(
     State(app_state): State<Arc<AppState>>,
     Path(info_hash): Path<InfoHashParam>,
+    RawQuery(query): RawQuery,
+    request_headers: HeaderMap,
 ) -> Response {
     let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
         return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
     };
 
+    let query = match MetainfoQuery::parse_and_check(query.as_deref()).await {
+        Ok(query) => query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
     info!("req: {}", info_hash.to_hex_string());
 
-    if app_state.cache.contains(&info_hash) {
+    if app_state.denylist.contains(&info_hash) {
+        info!("denied: {}", info_hash.to_hex_string());
+
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    let cached_at = app_state.cache.modified(&info_hash).ok();
+
+    // The same status goes to the statistics and the audit log.
+    let cache_status = CacheStatus::new(cached_at.is_some());
+
+    app_state.stats.record_request(&info_hash, cache_status);
+
+    let etag = http_cache::etag(&info_hash);
+    if http_cache::is_not_modified(&request_headers, &etag, cached_at) {
+        return cache_status.tag(http_cache::not_modified_response(&etag, cached_at));
+    }
+
+    if let Some(cached_at) = cached_at {
         if let Ok(bytes) = app_state.cache.get(&info_hash) {
             debug!("cached torrent: {}", app_state.cache.path(&info_hash));
 
-            return torrent_file_response(
+            let name = TorrentMetadata::from_torrent_bytes(bytes.clone())
+                .ok()
+                .and_then(|metadata| metadata.name);
+
+            return cache_status.tag(torrent_file_response(
                 bytes,
-                &format!("{}.torrent", info_hash.to_hex_string()),
+                &torrent_filename(name.as_deref(), &info_hash, query.filename),
                 &info_hash.to_hex_string(),
-            );
+                cached_at,
+            ));
+        }
+    }
+
+    let metadata =
+        match resolve_and_cache(&app_state, &info_hash, &query, Progress::default()).await {
+            Ok(metadata) => metadata,
+            Err(err) => return cache_status.tag(resolve_error_response(&err)),
+        };
+
+    let cached_at = app_state
+        .cache
+        .modified(&info_hash)
+        .unwrap_or_else(|_| SystemTime::now());
+
+    cache_status.tag(torrent_file_response(
+        metadata.torrent_bytes,
+        &torrent_filename(metadata.name.as_deref(), &info_hash, query.filename),
+        &info_hash.to_hex_string(),
+        cached_at,
+    ))
+}
+
+/// The torrent information in the info dictionary, without the piece hashes.
+#[derive(Serialize, ToSchema)]
+pub struct TorrentInfo {
+    #[schema(example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: String,
+    pub name: Option<String>,
+    /// Sum of the lengths of all the files, in bytes.
+    pub total_length: u64,
+    /// Length of each piece, in bytes.
+    pub piece_length: u32,
+    /// Number of pieces.
+    pub pieces: usize,
+    pub files: Vec<TorrentFile>,
+}
+
+impl From<&TorrentMetadata> for TorrentInfo {
+    fn from(metadata: &TorrentMetadata) -> Self {
+        Self {
+            info_hash: metadata.info_hash.to_hex_string(),
+            name: metadata.name.clone(),
+            total_length: metadata.total_length(),
+            piece_length: metadata.piece_length,
+            pieces: metadata.pieces,
+            files: metadata.files.clone(),
+        }
+    }
+}
+
+/// Returns the torrent information in JSON: the name, the files and the
+/// pieces. It's served from the cache or resolved like the torrent file.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6/info`.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}/info",
+    tag = "torrents",
+    params(
+        ("info_hash" = String, Path, description = "The info-hash in hexadecimal"),
+        ("tr" = Option<Vec<String>>, Query, description = "A tracker to announce to, besides the default trackers. It can be repeated, up to 10 times. Only public addresses are allowed"),
+        ("timeout" = Option<u64>, Query, description = "Seconds the client has to resolve the torrent, up to the configured maximum"),
+        ("peer" = Option<Vec<String>>, Query, description = "The `ip:port` of a peer that has the torrent. It can be repeated, up to 10 times. Only public addresses are allowed"),
+    ),
+    responses(
+        (status = 200, description = "The torrent information", body = TorrentInfo),
+        (status = 400, description = "Invalid info-hash or query parameters", body = String),
+        (status = 504, description = "The torrent was not resolved in time", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+        (status = 503, description = "The resolution queue is full", body = String,
+            headers(("Retry-After" = u64, description = "Seconds to wait before trying again"))
+        ),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn get_info_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+    RawQuery(query): RawQuery,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    let query = match MetainfoQuery::parse_and_check(query.as_deref()).await {
+        Ok(query) => query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    let cached = app_state
+        .cache
+        .get(&info_hash)
+        .ok()
+        .and_then(|bytes| TorrentMetadata::from_torrent_bytes(bytes).ok());
+
+    let cache_status = CacheStatus::new(cached.is_some());
+
+    app_state.stats.record_request(&info_hash, cache_status);
+
+    let metadata = match cached {
+        Some(metadata) => metadata,
+        None => {
+            match resolve_and_cache(&app_state, &info_hash, &query, Progress::default()).await {
+                Ok(metadata) => metadata,
+                Err(err) => return cache_status.tag(resolve_error_response(&err)),
+            }
+        }
+    };
+
+    cache_status.tag(Json(TorrentInfo::from(&metadata)).into_response())
+}
+
+/// The JSON representation of a magnet link.
+#[derive(Serialize, ToSchema)]
+pub struct Magnet {
+    #[schema(
+        example = "magnet:?xt=urn:btih:443c7602b4fde83d1154d6d9da48808418b181b6&dn=ubuntu-23.04-desktop-amd64.iso&xl=4932407296"
+    )]
+    pub magnet: String,
+    #[schema(example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: String,
+    /// The v2 info-hash of hybrid torrents, as a hexadecimal multihash.
+    pub v2_info_hash: Option<String>,
+    pub name: Option<String>,
+    /// Sum of the lengths of all the files, in bytes.
+    pub length: Option<u64>,
+    pub trackers: Vec<String>,
+}
+
+/// Returns a magnet link for the torrent, in plain text or, if the request
+/// accepts `application/json`, in JSON.
+///
+/// The link has the configured trackers and the ones in the `tr` query
+/// parameters. If the torrent is cached, it also has the name (`dn`), the
+/// total length (`xl`) and, for hybrid torrents, the v2 info-hash (`btmh`).
+/// The torrent is not resolved.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6/magnet`.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}/magnet",
+    tag = "torrents",
+    params(
+        ("info_hash" = String, Path, description = "The info-hash in hexadecimal"),
+        ("tr" = Option<Vec<String>>, Query, description = "A tracker to include, besides the default trackers. It can be repeated, up to 10 times. Only public addresses are allowed"),
+    ),
+    responses(
+        (status = 200, description = "The magnet link", content(
+            (String = "text/plain"),
+            (Magnet = "application/json"),
+        )),
+        (status = 400, description = "Invalid info-hash or query parameters", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn get_magnet_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+    RawQuery(query): RawQuery,
+    request_headers: HeaderMap,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    let query = match MetainfoQuery::parse_and_check(query.as_deref()).await {
+        Ok(query) => query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    let cached = app_state
+        .cache
+        .get(&info_hash)
+        .ok()
+        .and_then(|bytes| TorrentMetadata::from_torrent_bytes(bytes).ok());
+
+    let magnet_link = match &cached {
+        Some(metadata) => MagnetLink::for_torrent(metadata),
+        None => MagnetLink::new(info_hash),
+    }
+    .with_trackers(app_state.trackers.all())
+    .with_trackers(query.trackers);
+
+    let accepts_json = request_headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("application/json"));
+
+    let headers = [(header::VARY, "Accept")];
+
+    if accepts_json {
+        return (
+            headers,
+            Json(Magnet {
+                magnet: magnet_link.to_string(),
+                info_hash: info_hash.to_hex_string(),
+                v2_info_hash: magnet_link.v2_multihash(),
+                name: magnet_link.name,
+                length: magnet_link.length,
+                trackers: magnet_link.trackers,
+            }),
+        )
+            .into_response();
+    }
+
+    (headers, magnet_link.to_string()).into_response()
+}
+
+/// The body of the background resolution requests.
+#[derive(Deserialize, ToSchema)]
+pub struct ResolutionRequest {
+    /// The `http` or `https` URL the notification is sent to. Its host must
+    /// resolve to public addresses.
+    #[schema(example = "https://pipeline.example.com/hooks/torrents")]
+    pub callback_url: String,
+    /// The webhook key used to sign the notification.
+    #[schema(example = "pipeline")]
+    pub key_id: String,
+}
+
+/// The response to an accepted background resolution.
+#[derive(Serialize, ToSchema)]
+pub struct ResolutionAccepted {
+    #[schema(example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: String,
+    /// The id of the notification that will be sent to the callback URL.
+    pub notification_id: String,
+}
+
+/// Resolves the torrent in the background and notifies the callback URL when
+/// the torrent is cached or the resolution fails. Torrents already in the
+/// cache are notified right away.
+///
+/// It takes the same query parameters as the metainfo file endpoint. See
+/// [`webhooks`] for the notification format and signature.
+#[utoipa::path(
+    post,
+    path = "/torrents/{info_hash}/resolutions",
+    tag = "torrents",
+    params(
+        ("info_hash" = String, Path, description = "The info-hash in hexadecimal"),
+        ("tr" = Option<Vec<String>>, Query, description = "A tracker to announce to, besides the default trackers. It can be repeated, up to 10 times. Only public addresses are allowed"),
+        ("timeout" = Option<u64>, Query, description = "Seconds the client has to resolve the torrent, up to the configured maximum"),
+        ("peer" = Option<Vec<String>>, Query, description = "The `ip:port` of a peer that has the torrent. It can be repeated, up to 10 times. Only public addresses are allowed"),
+    ),
+    request_body = ResolutionRequest,
+    responses(
+        (status = 202, description = "The torrent is being resolved", body = ResolutionAccepted),
+        (status = 400, description = "Invalid info-hash, query parameters, callback URL or key", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+        (status = 501, description = "Webhooks are not enabled", body = String),
+        (status = 503, description = "Too many pending notifications", body = String),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn post_resolution_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+    RawQuery(query): RawQuery,
+    Json(request): Json<ResolutionRequest>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    let metainfo_query = match MetainfoQuery::parse_and_check(query.as_deref()).await {
+        Ok(metainfo_query) => metainfo_query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let Some(webhooks) = app_state.webhooks.clone() else {
+        return (StatusCode::NOT_IMPLEMENTED, "Webhooks are not enabled").into_response();
+    };
+
+    if let Err(err) = webhooks.check_callback_url(&request.callback_url).await {
+        return (StatusCode::BAD_REQUEST, err.to_string()).into_response();
+    }
+
+    if !webhooks.has_key(&request.key_id) {
+        return (StatusCode::BAD_REQUEST, "Unknown webhook key").into_response();
+    }
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    if !webhooks.try_reserve() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECONDS.to_string())],
+            "Too many pending notifications, try again later",
+        )
+            .into_response();
+    }
+
+    let resolution = PendingResolution {
+        callback: Callback {
+            id: webhooks::new_id(),
+            url: request.callback_url,
+            key_id: request.key_id,
+        },
+        info_hash: info_hash.to_hex_string(),
+        query,
+    };
+
+    if let Err(err) = webhooks.accept(&resolution) {
+        error!("error storing pending resolution: {err}");
+        webhooks.release();
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not store the resolution",
+        )
+            .into_response();
+    }
+
+    let accepted = ResolutionAccepted {
+        info_hash: resolution.info_hash.clone(),
+        notification_id: resolution.callback.id.clone(),
+    };
+
+    info!(
+        "req resolution: {} (notification {})",
+        accepted.info_hash, accepted.notification_id
+    );
+
+    resolve_in_background(
+        app_state,
+        webhooks,
+        resolution.callback,
+        info_hash,
+        metainfo_query,
+    );
+
+    (StatusCode::ACCEPTED, Json(accepted)).into_response()
+}
+
+/// Resumes the background resolutions accepted by a previous run. The ones
+/// that can't be parsed anymore are discarded.
+pub fn resume_background_resolutions(app_state: &Arc<AppState>) {
+    let Some(webhooks) = app_state.webhooks.clone() else {
+        return;
+    };
+
+    let resolutions = match webhooks.pending_resolutions() {
+        Ok(resolutions) => resolutions,
+        Err(err) => {
+            error!("error reading pending resolutions: {err}");
+            return;
         }
+    };
+
+    if !resolutions.is_empty() {
+        info!("resuming {} pending resolutions", resolutions.len());
     }
 
-    let magnet_link = format!("magnet:?xt=urn:btih:{}", info_hash.to_hex_string());
+    for resolution in resolutions {
+        let parsed = InfoHash::from_str(&resolution.info_hash)
+            .map_err(|_| "Invalid info hash".to_string())
+            .and_then(|info_hash| {
+                MetainfoQuery::parse(resolution.query.as_deref()).map(|query| (info_hash, query))
+            });
+
+        match parsed {
+            Ok((info_hash, query)) => resolve_in_background(
+                app_state.clone(),
+                webhooks.clone(),
+                resolution.callback,
+                info_hash,
+                query,
+            ),
+            Err(err) => {
+                error!(
+                    "discarding pending resolution {}: {err}",
+                    resolution.callback.id
+                );
+                webhooks.discard(&resolution);
+            }
+        }
+    }
+}
+
+/// Resolves the torrent, unless it's cached, and stores the notification
+/// for the callback. While the resolution queue is full, the resolution is
+/// tried again later instead of failing.
+fn resolve_in_background(
+    app_state: Arc<AppState>,
+    webhooks: Arc<Webhooks>,
+    callback: Callback,
+    info_hash: InfoHash,
+    query: MetainfoQuery,
+) {
+    tokio::spawn(async move {
+        let cached = app_state
+            .cache
+            .get(&info_hash)
+            .ok()
+            .and_then(|bytes| TorrentMetadata::from_torrent_bytes(bytes).ok());
+
+        let result = match cached {
+            Some(metadata) => Ok(metadata),
+            None => resolve_when_queued(&app_state, &webhooks, &info_hash, &query).await,
+        };
+
+        let (event, name, reason) = match result {
+            Ok(metadata) => (NotificationEvent::Cached, metadata.name, None),
+            Err(err) => (NotificationEvent::Failed, None, Some(err.to_string())),
+        };
+
+        if let Err(err) = webhooks.notify(callback, event, &info_hash, name, reason) {
+            error!("error storing webhook notification: {err}");
+        }
+    });
+}
+
+/// Resolves and caches the torrent, trying again with backoff while the
+/// resolution queue is full.
+async fn resolve_when_queued(
+    app_state: &AppState,
+    webhooks: &Webhooks,
+    info_hash: &InfoHash,
+    query: &MetainfoQuery,
+) -> Result<TorrentMetadata, ResolveError> {
+    let mut attempts = 0;
 
-    let Ok((_info, bytes)) = app_state.client.resolve_magnet(magnet_link).await else {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "BitTorrent client error").into_response();
+    loop {
+        match resolve_and_cache(app_state, info_hash, query, Progress::default()).await {
+            Err(ResolveError::QueueFull) => {
+                attempts += 1;
+                let wait = webhooks.resolution_backoff(attempts);
+                debug!(
+                    "resolution queue full, resolving {} again in {wait:?}",
+                    info_hash.to_hex_string()
+                );
+                tokio::time::sleep(wait).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The response for a failed resolution.
+fn resolve_error_response(err: &ResolveError) -> Response {
+    match err {
+        ResolveError::Timeout => (
+            StatusCode::GATEWAY_TIMEOUT,
+            "Torrent metadata not found in time",
+        )
+            .into_response(),
+        ResolveError::QueueFull => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECONDS.to_string())],
+            "Too many torrents being resolved, try again later",
+        )
+            .into_response(),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "BitTorrent client error").into_response(),
+    }
+}
+
+/// Gets the torrent metadata with the configured resolver and stores the
+/// torrent file in the cache.
+///
+/// The resolver gets the configured trackers and the extra ones provided by
+/// the client. The resolution timeout is the one in the query, capped to the
+/// configured maximum, or the configured default.
+///
+/// Failing to write the cache is only logged, the torrent is returned anyway.
+///
+/// The progress receives the resolver events, and then the `cached` or the
+/// `failed` event.
+///
+/// # Errors
+///
+/// Will return an error if the resolver can't get the torrent metadata.
+pub async fn resolve_and_cache(
+    app_state: &AppState,
+    info_hash: &InfoHash,
+    query: &MetainfoQuery,
+    progress: Progress,
+) -> Result<TorrentMetadata, ResolveError> {
+    let mut trackers = app_state.trackers.all();
+    for tracker in &query.trackers {
+        if !trackers.contains(tracker) {
+            trackers.push(tracker.clone());
+        }
+    }
+
+    let timeout = query
+        .timeout
+        .unwrap_or(app_state.config.api.resolution_timeout)
+        .min(app_state.config.api.max_resolution_timeout);
+
+    let options = ResolveOptions {
+        timeout: Some(timeout),
+        trackers,
+        initial_peers: query.peers.clone(),
+        progress: progress.clone(),
     };
 
-    match app_state.cache.add(&info_hash, &bytes) {
+    let started = Instant::now();
+
+    let metadata = match app_state.resolver.resolve(info_hash, &options).await {
+        Ok(metadata) => {
+            app_state.stats.record_resolution(started.elapsed());
+            metadata
+        }
+        Err(err) => {
+            app_state.stats.record_failure(&err);
+            progress.report(ResolutionEvent::Failed {
+                reason: err.to_string(),
+            });
+            return Err(err);
+        }
+    };
+
+    match app_state.cache.add(info_hash, &metadata.torrent_bytes) {
         Ok(()) => {
             trace!("added torrent to cache: {}", info_hash.to_hex_string());
+            progress.report(ResolutionEvent::Cached);
         }
         Err(err) => {
             error!("error adding torrent to cache: {}", err);
         }
+    }
+
+    Ok(metadata)
+}
+
+/// Streams the progress of the torrent resolution as server-sent events. The
+/// event name is the phase, and the data is the [`ResolutionEvent`] in JSON.
+///
+/// It takes the same query parameters as the metainfo file endpoint. The
+/// torrent is resolved and cached in the background, so it's available later
+/// even if the client disconnects. A torrent that is already in the cache
+/// only gets the `cached` event.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6/events`.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}/events",
+    tag = "torrents",
+    params(
+        ("info_hash" = String, Path, description = "The info-hash in hexadecimal"),
+        ("tr" = Option<Vec<String>>, Query, description = "A tracker to announce to, besides the default trackers. It can be repeated, up to 10 times. Only public addresses are allowed"),
+        ("timeout" = Option<u64>, Query, description = "Seconds the client has to resolve the torrent, up to the configured maximum"),
+        ("peer" = Option<Vec<String>>, Query, description = "The `ip:port` of a peer that has the torrent. It can be repeated, up to 10 times. Only public addresses are allowed"),
+    ),
+    responses(
+        (status = 200, description = "The resolution events, until the torrent is cached or the resolution fails", body = ResolutionEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid info-hash or query parameters", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn get_events_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+    RawQuery(query): RawQuery,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
     };
 
-    torrent_file_response(
-        bytes,
-        &format!("{}.torrent", info_hash.to_hex_string()),
-        &info_hash.to_hex_string(),
+    let query = match MetainfoQuery::parse_and_check(query.as_deref()).await {
+        Ok(query) => query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    let (progress, receiver) = Progress::channel();
+
+    let cache_status = CacheStatus::new(app_state.cache.contains(&info_hash));
+
+    app_state.stats.record_request(&info_hash, cache_status);
+
+    if cache_status == CacheStatus::Hit {
+        progress.report(ResolutionEvent::Cached);
+    } else {
+        info!("req events: {}", info_hash.to_hex_string());
+
+        tokio::spawn(async move {
+            let _ = resolve_and_cache(&app_state, &info_hash, &query, progress).await;
+        });
+    }
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+
+        Some((
+            Event::default().event(event.name()).json_data(&event),
+            receiver,
+        ))
+    });
+
+    cache_status.tag(
+        Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response(),
+    )
+}
+
+/// Returns the peers found in the DHT, the trackers and with `ut_pex` for the
+/// torrent and whether they support the `ut_metadata` extension. The DHT is
+/// only used if the resolver uses one.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6/peers`.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}/peers",
+    tag = "torrents",
+    params(("info_hash" = String, Path, description = "The info-hash in hexadecimal")),
+    responses(
+        (status = 200, description = "The peers found in the DHT, the trackers and with PEX", body = PeerLookup),
+        (status = 400, description = "Invalid info-hash", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn get_peers_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    // The DHT search is only started if there isn't a recent lookup. If it
+    // fails, the lookup uses the trackers only.
+    let dht_peers = || match app_state.resolver.dht() {
+        Some(dht) => match dht.get_peers(Id20::new(info_hash.0), None) {
+            Ok(peers) => peers.boxed(),
+            Err(err) => {
+                error!("error looking for peers in the DHT: {err}");
+                stream::empty().boxed()
+            }
+        },
+        None => stream::empty().boxed(),
+    };
+
+    let lookup = app_state
+        .peer_discovery
+        .lookup(&info_hash, &app_state.trackers.all(), dht_peers)
+        .await;
+
+    Json(lookup.as_ref()).into_response()
+}
+
+/// Handler for the swarm endpoint. It estimates the number of seeders and
+/// leechers with tracker scrapes and, if the resolver uses the DHT, DHT
+/// scrapes.
+///
+/// For example: `http://127.0.0.1:3000/api/v1/torrents/443c7602b4fde83d1154d6d9da48808418b181b6/swarm`.
+#[utoipa::path(
+    get,
+    path = "/torrents/{info_hash}/swarm",
+    tag = "torrents",
+    params(("info_hash" = String, Path, description = "The info-hash in hexadecimal")),
+    responses(
+        (status = 200, description = "The seeders and leechers estimates", body = SwarmScrape),
+        (status = 400, description = "Invalid info-hash", body = String),
+        (status = 451, description = "The torrent is in the denylist", body = String),
     )
+)]
+pub async fn get_swarm_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(info_hash): Path<InfoHashParam>,
+) -> Response {
+    let Ok(info_hash) = InfoHash::from_str(&info_hash.lowercase()) else {
+        return (StatusCode::BAD_REQUEST, "Invalid info hash").into_response();
+    };
+
+    if app_state.denylist.contains(&info_hash) {
+        return unavailable_for_legal_reasons_response(
+            app_state.config.denylist.reference_url.as_deref(),
+        );
+    }
+
+    // `RoutingTableNode` is not exported by the DHT crate, so its `addr`
+    // method can't be named instead of the closure.
+    #[allow(clippy::redundant_closure_for_method_calls)]
+    let dht_nodes = app_state.resolver.dht().map(|dht| {
+        dht.with_routing_table(|routing_table| {
+            routing_table
+                .sorted_by_distance_from(Id20::new(info_hash.0))
+                .into_iter()
+                .take(SWARM_DHT_START_NODES)
+                .map(|node| node.addr())
+                .collect()
+        })
+    });
+
+    let scrape = app_state
+        .swarm_scraper
+        .scrape(&info_hash, &app_state.trackers.all(), dht_nodes)
+        .await;
+
+    Json(scrape.as_ref()).into_response()
 }
 
-/// Builds the binary response for a torrent file.
+/// Builds the binary response for a torrent file. It can be cached forever:
+/// the metadata of an info-hash never changes.
 ///
 /// # Panics
 ///
 /// Panics if the filename is not a valid header value for the `content-disposition`
 /// header.
 #[must_use]
-pub fn torrent_file_response(bytes: Bytes, filename: &str, info_hash: &str) -> Response {
+pub fn torrent_file_response(
+    bytes: Bytes,
+    filename: &str,
+    info_hash: &str,
+    last_modified: SystemTime,
+) -> Response {
     let mut headers = HeaderMap::new();
     headers.insert(
         header::CONTENT_TYPE,
@@ -90,7 +1029,7 @@ pub fn torrent_file_response(bytes: Bytes, filename: &str, info_hash: &str) -> R
     );
     headers.insert(
         header::CONTENT_DISPOSITION,
-        format!("attachment; filename={filename}").parse().expect(
+        filename::content_disposition(filename).parse().expect(
             "Torrent filename should be a valid header value for the content disposition header",
         ),
     );
@@ -100,128 +1039,177 @@ pub fn torrent_file_response(bytes: Bytes, filename: &str, info_hash: &str) -> R
             "Torrent infohash should be a valid header value for the content disposition header",
         ),
     );
+    http_cache::insert_headers(
+        &mut headers,
+        &format!("\"{info_hash}\""),
+        Some(last_modified),
+    );
 
     (StatusCode::OK, headers, bytes).into_response()
 }
 
-#[allow(clippy::module_name_repetitions)]
-pub async fn health_check_handler() -> Response {
-    (StatusCode::OK, "OK").into_response()
+/// Builds the `451 Unavailable For Legal Reasons` response for denied torrents.
+///
+/// The reference URL, if any, is sent in the `Link` header with the
+/// `blocked-by` relation as described in [RFC 7725](https://www.rfc-editor.org/rfc/rfc7725).
+#[must_use]
+pub fn unavailable_for_legal_reasons_response(reference_url: Option<&str>) -> Response {
+    let mut headers = HeaderMap::new();
+
+    if let Some(reference_url) = reference_url {
+        match format!("<{reference_url}>; rel=\"blocked-by\"").parse() {
+            Ok(link) => {
+                headers.insert(header::LINK, link);
+            }
+            Err(err) => {
+                error!("invalid denylist reference URL {reference_url}: {err}");
+            }
+        }
+    }
+
+    let body = match reference_url {
+        Some(reference_url) => {
+            format!("Unavailable For Legal Reasons. See: {reference_url}")
+        }
+        None => "Unavailable For Legal Reasons".to_string(),
+    };
+
+    (StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, headers, body).into_response()
+}
+
+/// The health check response.
+#[derive(Serialize, ToSchema)]
+pub struct HealthCheck {
+    #[schema(example = "Ok")]
+    pub status: &'static str,
+    #[serde(flatten)]
+    pub stats: ResolverStats,
 }
 
+/// Returns the service status and the resolver statistics, like the state of
+/// the resolution queue and the number of torrents in the `BitTorrent` session.
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "service",
+    responses((status = 200, description = "The service is running", body = HealthCheck))
+)]
 #[allow(clippy::module_name_repetitions)]
-pub async fn entrypoint_handler() -> Html<&'static str> {
-    let html = r#"
-    <!DOCTYPE html>
-    <html lang="en">
-
-    <head>
-        <meta charset="UTF-8">
-        <meta http-equiv="X-UA-Compatible" content="IE=edge">
-        <meta name="viewport" content="width=device-width, initial-scale=1.0">
-        <title>Hash2Torrent</title>
-        <style>
-            body {
-                font-family: Arial, sans-serif;
-                display: flex;
-                flex-direction: column;
-                justify-content: center;
-                align-items: center;
-                height: 100vh;
-                margin: 0;
-                background-color: #f8f9fa;
-            }
+pub async fn health_check_handler(State(app_state): State<Arc<AppState>>) -> Response {
+    Json(HealthCheck {
+        status: "Ok",
+        stats: app_state.resolver.stats(),
+    })
+    .into_response()
+}
 
-            h1 {
-                font-size: 3em;
-                margin-bottom: 20px;
-                color: #333;
-            }
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
 
-            p {
-                font-size: 1.1em;
-                margin-bottom: 30px;
-                color: #555;
-                text-align: center;
-            }
+    use super::MetainfoQuery;
+    use crate::api::filename::FilenameStyle;
 
-            .input-container {
-                display: flex;
-                flex-direction: column;
-                align-items: center;
-                margin-bottom: 50px;
-            }
+    #[test]
+    fn the_metainfo_query_should_be_empty_without_query_string() {
+        assert_eq!(
+            MetainfoQuery::parse(None).unwrap(),
+            MetainfoQuery::default()
+        );
+    }
 
-            input[type="text"] {
-                width: 400px;
-                padding: 10px;
-                font-size: 1.1em;
-                margin-bottom: 20px;
-                border: 1px solid #ccc;
-                border-radius: 4px;
-                transition: border-color 0.3s;
-            }
+    #[test]
+    fn the_metainfo_query_should_collect_repeated_tracker_params() {
+        let query = MetainfoQuery::parse(Some(
+            "tr=udp%3A%2F%2Fa.com%3A6969%2Fannounce&tr=https%3A%2F%2Fb.com%2Fannounce",
+        ))
+        .unwrap();
 
-            input[type="text"]:focus {
-                border-color: #007bff;
-                outline: none;
-            }
+        assert_eq!(
+            query.trackers,
+            vec!["udp://a.com:6969/announce", "https://b.com/announce"]
+        );
+    }
 
-            button {
-                padding: 10px 20px;
-                font-size: 1.1em;
-                color: #fff;
-                background-color: #007bff;
-                border: none;
-                border-radius: 4px;
-                cursor: pointer;
-                transition: background-color 0.3s;
-            }
+    #[test]
+    fn the_metainfo_query_should_reject_unsupported_trackers() {
+        assert!(MetainfoQuery::parse(Some("tr=wss%3A%2F%2Fa.com")).is_err());
+    }
 
-            button:hover {
-                background-color: #0056b3;
-            }
+    #[test]
+    fn the_metainfo_query_should_reject_trackers_in_the_local_network() {
+        assert!(MetainfoQuery::parse(Some("tr=udp%3A%2F%2F127.0.0.1%3A6969")).is_err());
+        assert!(MetainfoQuery::parse(Some("tr=http%3A%2F%2F10.0.0.5%2Fannounce")).is_err());
+        assert!(MetainfoQuery::parse(Some("tr=http%3A%2F%2F169.254.169.254%2F")).is_err());
+        assert!(MetainfoQuery::parse(Some("tr=udp%3A%2F%2F%5B%3A%3A1%5D%3A6969")).is_err());
+    }
 
-            .github-link {
-                margin-top: 20px;
-                font-size: 1em;
-                color: #007bff;
-                text-decoration: none;
-                transition: color 0.3s;
-            }
+    #[tokio::test]
+    async fn the_metainfo_query_should_reject_tracker_hosts_resolving_to_the_local_network() {
+        let query = MetainfoQuery::parse(Some("tr=udp%3A%2F%2Flocalhost%3A6969")).unwrap();
 
-            .github-link:hover {
-                color: #0056b3;
-            }
-        </style>
-    </head>
+        assert!(query.check_trackers().await.is_err());
+    }
 
-    <body>
-        <h1>Hash2Torrent</h1>
+    #[test]
+    fn the_metainfo_query_should_parse_the_timeout_in_seconds() {
+        let query = MetainfoQuery::parse(Some("timeout=30")).unwrap();
 
-        <div class="input-container">
-            <input type="text" id="infohash" placeholder="">
-            <button onclick="downloadTorrent()">Download metadata</button>
-        </div>
+        assert_eq!(query.timeout, Some(Duration::from_secs(30)));
+    }
 
-        <p>Introduce a torrent v1 infohash like <a href="/torrents/443c7602b4fde83d1154d6d9da48808418b181b6">443c7602b4fde83d1154d6d9da48808418b181b6</a><br/> and download the bencoded torrent metadata (only info dictionary)</p>
+    #[test]
+    fn the_metainfo_query_should_reject_invalid_timeouts() {
+        assert!(MetainfoQuery::parse(Some("timeout=0")).is_err());
+        assert!(MetainfoQuery::parse(Some("timeout=-1")).is_err());
+        assert!(MetainfoQuery::parse(Some("timeout=ten")).is_err());
+    }
 
-        <a href="https://github.com/torrust/torrust-hash2torrent" class="github-link" target="_blank">Fork on GitHub</a>
+    #[test]
+    fn the_metainfo_query_should_collect_repeated_peer_params() {
+        let query = MetainfoQuery::parse(Some(
+            "peer=1.1.1.1:51000&peer=%5B2606%3A4700%3A%3A1111%5D:6881",
+        ))
+        .unwrap();
 
-        <script>
-            function downloadTorrent() {
-                var infohash = document.getElementById('infohash').value.trim();
-                if (infohash) {
-                    window.location.href = '/torrents/' + infohash;
-                } else {
-                    alert('Please enter a valid infohash like 443c7602b4fde83d1154d6d9da48808418b181b6');
-                }
-            }
-        </script>
-    </body>
+        assert_eq!(
+            query.peers,
+            vec![
+                "1.1.1.1:51000".parse::<SocketAddr>().unwrap(),
+                "[2606:4700::1111]:6881".parse::<SocketAddr>().unwrap()
+            ]
+        );
+    }
 
-    </html>
-    "#;
+    #[test]
+    fn the_metainfo_query_should_reject_invalid_peer_addresses() {
+        assert!(MetainfoQuery::parse(Some("peer=192.168.1.10")).is_err());
+    }
+
+    #[test]
+    fn the_metainfo_query_should_reject_peers_in_the_local_network() {
+        assert!(MetainfoQuery::parse(Some("peer=127.0.0.1:6881")).is_err());
+        assert!(MetainfoQuery::parse(Some("peer=192.168.1.10:51000")).is_err());
+        assert!(MetainfoQuery::parse(Some("peer=169.254.169.254:80")).is_err());
+        assert!(MetainfoQuery::parse(Some("peer=%5B%3A%3A1%5D:6881")).is_err());
+    }
 
-    Html(html) // Wrap HTML content in Html response type
+    #[test]
+    fn the_metainfo_query_should_limit_the_number_of_trackers() {
+        let query = ["tr=udp%3A%2F%2Fa.com%3A6969"; 11].join("&");
+
+        assert!(MetainfoQuery::parse(Some(&query)).is_err());
+    }
+
+    #[test]
+    fn the_metainfo_query_should_parse_the_filename_style() {
+        assert_eq!(
+            MetainfoQuery::parse(Some("filename=info_hash"))
+                .unwrap()
+                .filename,
+            FilenameStyle::InfoHash
+        );
+        assert!(MetainfoQuery::parse(Some("filename=other")).is_err());
+    }
 }
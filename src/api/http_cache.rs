@@ -0,0 +1,190 @@
+//! HTTP caching for the torrents routes.
+//!
+//! The metadata of an info-hash never changes, so torrent files are sent with
+//! a strong `ETag` derived from the info-hash and long-lived immutable
+//! `Cache-Control` headers. Conditional requests (`If-None-Match` and
+//! `If-Modified-Since`) are answered with `304 Not Modified` without
+//! resolving the torrent again.
+//!
+//! Failures can be temporary, like resolution timeouts, so they are only
+//! cached for a short time.
+use std::time::SystemTime;
+
+use axum::response::{IntoResponse, Response};
+use hyper::header::HeaderValue;
+use hyper::{header, HeaderMap, StatusCode};
+
+use crate::bit_torrent::info_hash::InfoHash;
+
+/// `Cache-Control` for torrent files.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `Cache-Control` for failed requests.
+pub const SHORT_LIVED: &str = "public, max-age=10";
+
+/// The strong entity tag of the torrent file.
+#[must_use]
+pub fn etag(info_hash: &InfoHash) -> String {
+    format!("\"{}\"", info_hash.to_hex_string())
+}
+
+/// Returns true if the client already has the torrent file, following the
+/// precedence in [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-13.2.2):
+/// `If-Modified-Since` is ignored when the request has `If-None-Match`.
+///
+/// `last_modified` is `None` if the torrent has not been resolved yet. In
+/// that case only an exact `ETag` match counts.
+#[must_use]
+pub fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        let Ok(if_none_match) = if_none_match.to_str() else {
+            return false;
+        };
+
+        return if_none_match.split(',').map(str::trim).any(|tag| {
+            (tag == "*" && last_modified.is_some()) || tag.strip_prefix("W/").unwrap_or(tag) == etag
+        });
+    }
+
+    let (Some(if_modified_since), Some(last_modified)) = (
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| httpdate::parse_http_date(date).ok()),
+        last_modified,
+    ) else {
+        return false;
+    };
+
+    // HTTP dates have a precision of seconds.
+    httpdate::fmt_http_date(last_modified) == httpdate::fmt_http_date(if_modified_since)
+        || last_modified <= if_modified_since
+}
+
+/// Adds the caching headers of a torrent file.
+///
+/// # Panics
+///
+/// Will panic if the `ETag` is not a valid header value.
+pub fn insert_headers(headers: &mut HeaderMap, etag: &str, last_modified: Option<SystemTime>) {
+    headers.insert(
+        header::ETAG,
+        etag.parse()
+            .expect("the ETag should be a valid header value"),
+    );
+    if let Some(last_modified) = last_modified {
+        headers.insert(
+            header::LAST_MODIFIED,
+            httpdate::fmt_http_date(last_modified)
+                .parse()
+                .expect("an HTTP date should be a valid header value"),
+        );
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE));
+}
+
+#[must_use]
+pub fn not_modified_response(etag: &str, last_modified: Option<SystemTime>) -> Response {
+    let mut headers = HeaderMap::new();
+
+    insert_headers(&mut headers, etag, last_modified);
+
+    (StatusCode::NOT_MODIFIED, headers).into_response()
+}
+
+/// Middleware that makes the failed responses short-lived in caches, unless
+/// the handler has set its own `Cache-Control`.
+pub async fn short_lived_failures(mut response: Response) -> Response {
+    let status = response.status();
+
+    if (status.is_client_error() || status.is_server_error())
+        && !response.headers().contains_key(header::CACHE_CONTROL)
+    {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static(SHORT_LIVED));
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use hyper::{header, HeaderMap};
+
+    use super::is_not_modified;
+
+    const ETAG: &str = "\"443c7602b4fde83d1154d6d9da48808418b181b6\"";
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn it_should_match_the_etag_in_if_none_match() {
+        let last_modified = Some(SystemTime::now());
+
+        assert!(is_not_modified(
+            &headers(header::IF_NONE_MATCH, &format!("\"other\", {ETAG}")),
+            ETAG,
+            None
+        ));
+        assert!(is_not_modified(
+            &headers(header::IF_NONE_MATCH, &format!("W/{ETAG}")),
+            ETAG,
+            None
+        ));
+        assert!(!is_not_modified(
+            &headers(header::IF_NONE_MATCH, "\"other\""),
+            ETAG,
+            last_modified
+        ));
+    }
+
+    #[test]
+    fn it_should_only_match_any_etag_if_the_torrent_has_been_resolved() {
+        let any = headers(header::IF_NONE_MATCH, "*");
+
+        assert!(is_not_modified(&any, ETAG, Some(SystemTime::now())));
+        assert!(!is_not_modified(&any, ETAG, None));
+    }
+
+    #[test]
+    fn it_should_compare_if_modified_since_with_the_last_modification() {
+        let last_modified = SystemTime::now() - Duration::from_hours(1);
+        let date = |time| headers(header::IF_MODIFIED_SINCE, &httpdate::fmt_http_date(time));
+
+        assert!(is_not_modified(
+            &date(last_modified),
+            ETAG,
+            Some(last_modified)
+        ));
+        assert!(is_not_modified(
+            &date(SystemTime::now()),
+            ETAG,
+            Some(last_modified)
+        ));
+        assert!(!is_not_modified(
+            &date(last_modified - Duration::from_mins(1)),
+            ETAG,
+            Some(last_modified)
+        ));
+        assert!(!is_not_modified(&date(last_modified), ETAG, None));
+    }
+
+    #[test]
+    fn it_should_ignore_if_modified_since_when_there_is_if_none_match() {
+        let last_modified = SystemTime::now() - Duration::from_hours(1);
+        let mut headers = headers(header::IF_NONE_MATCH, "\"other\"");
+        headers.insert(
+            header::IF_MODIFIED_SINCE,
+            httpdate::fmt_http_date(SystemTime::now()).parse().unwrap(),
+        );
+
+        assert!(!is_not_modified(&headers, ETAG, Some(last_modified)));
+    }
+}
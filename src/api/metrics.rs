@@ -0,0 +1,103 @@
+//! Metrics endpoint in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+use std::fmt::Write;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use hyper::{header, StatusCode};
+
+use crate::AppState;
+
+/// Returns the resolver statistics in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "service",
+    responses((status = 200, description = "The metrics", body = String, content_type = "text/plain; version=0.0.4"))
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn metrics_handler(State(app_state): State<Arc<AppState>>) -> Response {
+    let mut metrics = Metrics::default();
+
+    let stats = app_state.resolver.stats();
+
+    if let Some(resolutions) = stats.resolutions {
+        metrics.gauge(
+            "hash2torrent_resolutions_running",
+            "Magnet link resolutions in progress.",
+            resolutions.running,
+        );
+        metrics.gauge(
+            "hash2torrent_resolutions_queued",
+            "Magnet link resolutions waiting for a free slot.",
+            resolutions.queued,
+        );
+        metrics.gauge(
+            "hash2torrent_resolutions_max_concurrency",
+            "Maximum number of magnet link resolutions running at the same time.",
+            resolutions.max_concurrency,
+        );
+        metrics.gauge(
+            "hash2torrent_resolutions_max_queue_length",
+            "Maximum number of magnet link resolutions waiting for a free slot.",
+            resolutions.max_queue_length,
+        );
+    }
+
+    if let Some(session) = stats.session {
+        metrics.gauge(
+            "hash2torrent_session_torrents",
+            "Torrents managed by the BitTorrent session.",
+            session.torrents,
+        );
+        metrics.counter(
+            "hash2torrent_session_removed_torrents_total",
+            "Torrents removed from the BitTorrent session after their resolution.",
+            session.removed_torrents,
+        );
+    }
+
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.0,
+    )
+        .into_response()
+}
+
+/// Prometheus text format writer.
+#[derive(Default)]
+struct Metrics(String);
+
+impl Metrics {
+    fn gauge(&mut self, name: &str, help: &str, value: impl std::fmt::Display) {
+        self.metric(name, "gauge", help, value);
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: impl std::fmt::Display) {
+        self.metric(name, "counter", help, value);
+    }
+
+    fn metric(&mut self, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+        let _ = writeln!(self.0, "{name} {value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn it_should_write_gauges_in_the_prometheus_text_format() {
+        let mut metrics = Metrics::default();
+
+        metrics.gauge("queued", "Queued resolutions.", 3);
+
+        assert_eq!(
+            metrics.0,
+            "# HELP queued Queued resolutions.\n# TYPE queued gauge\nqueued 3\n"
+        );
+    }
+}
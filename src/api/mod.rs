@@ -1,16 +1,35 @@
+pub mod address;
+pub mod admin;
+pub mod audit_log;
 pub mod cache;
+pub mod client_ip;
+pub mod cors;
+pub mod dates;
+pub mod denylist;
+pub mod deprecation;
+pub mod feeds;
+pub mod filename;
 pub mod handler;
+pub mod http_cache;
+pub mod metrics;
+pub mod openapi;
 pub mod slowloris;
+pub mod stats;
+pub mod tls;
+pub mod ui;
+pub mod v1;
+pub mod webhooks;
 
 use axum::error_handling::HandleErrorLayer;
 
 use axum::routing::get;
-use axum::{BoxError, Router};
+use axum::{middleware, BoxError, Router};
+use axum_server::tls_rustls::RustlsAcceptor;
 use axum_server::Server;
 
-use handler::{entrypoint_handler, get_metainfo_file_handler, health_check_handler};
 use hyper::StatusCode;
 use hyper_util::rt::TokioTimer;
+use std::io;
 use std::net::{SocketAddr, TcpListener};
 
 use std::sync::Arc;
@@ -23,49 +42,156 @@ use tracing::info;
 use crate::api::slowloris::TimeoutAcceptor;
 use crate::AppState;
 
-const TIMEOUT: Duration = Duration::from_secs(10);
+/// Extra time given to requests on top of the maximum resolution timeout,
+/// so that the handler can answer when the resolution times out.
+const REQUEST_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
 
-/// It starts the web server.
+/// It starts the web server and, if it's configured, the plain HTTP listener
+/// that redirects to HTTPS.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Will panic if it can get the local server address
-pub async fn start(bind_to: &SocketAddr, state: AppState) {
-    let socket =
-        std::net::TcpListener::bind(bind_to).expect("Could not bind tcp_listener to address.");
+/// Will return an error if it can't bind to the addresses or the redirect
+/// listener fails.
+pub async fn start(bind_to: &SocketAddr, state: AppState) -> io::Result<()> {
+    let socket = std::net::TcpListener::bind(bind_to)?;
+
+    let Some(tls) = state.config.api.tls.as_ref() else {
+        serve(socket, state).await;
+        return Ok(());
+    };
+
+    let Some(redirect_bind_to) = tls.http_redirect_bind_address else {
+        serve(socket, state).await;
+        return Ok(());
+    };
+
+    let redirect_socket = std::net::TcpListener::bind(redirect_bind_to)?;
+    let https_port = tls.public_https_port.unwrap_or(bind_to.port());
+    let redirect = tls::serve_redirect(
+        redirect_socket,
+        https_port,
+        state.config.api.connection_timeout,
+    );
+
+    tokio::select! {
+        result = redirect => result,
+        () = serve(socket, state) => Ok(()),
+    }
+}
 
+/// It starts the web server on an already bound socket. Tests use it to run
+/// the API on an ephemeral port. It serves HTTPS if TLS is configured.
+///
+/// # Panics
+///
+/// Will panic if it can get the local server address or it can't load the
+/// TLS certificate.
+pub async fn serve(socket: TcpListener, state: AppState) {
     let server_address = socket
         .local_addr()
         .expect("Could not get local_addr from tcp_listener.");
 
-    info!("API bound to address: http://{server_address}"); // DevSkim: ignore DS137138
+    let scheme = if state.config.api.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    info!("API bound to address: {scheme}://{server_address}"); // DevSkim: ignore DS137138
 
     let server = from_tcp_with_timeouts(socket);
 
-    let app = Router::new()
-        .route("/", get(entrypoint_handler))
-        .route("/health_check", get(health_check_handler))
-        .route("/torrents/:info_hash", get(get_metainfo_file_handler))
+    let state = Arc::new(state);
+
+    handler::resume_background_resolutions(&state);
+
+    let mut app = public_router();
+
+    if state.audit_log.is_some() {
+        app = app.layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit_log::record_requests,
+        ));
+    }
+
+    if let Some(cors) = &state.config.api.cors {
+        app = app.layer(cors::layer(cors));
+    }
+
+    let request_timeout = state.config.api.max_resolution_timeout + REQUEST_TIMEOUT_MARGIN;
+    let connection_timeout = state.config.api.connection_timeout;
+    let tls = state.config.api.tls.clone();
+
+    if let Some(token) = state.config.api.admin_token.clone() {
+        app = app
+            .nest(
+                &format!("{}/admin", v1::PREFIX),
+                admin::router(token.clone()),
+            )
+            .nest("/admin", legacy(admin::router(token)));
+    } else {
+        info!("admin API disabled: no admin token configured");
+    }
+
+    let app = app
         .layer(TraceLayer::new_for_http())
         .layer(
             ServiceBuilder::new()
                 // this middleware goes above `TimeoutLayer` because it will receive
                 // errors returned by `TimeoutLayer`
                 .layer(HandleErrorLayer::new(|_: BoxError| async {
-                    StatusCode::REQUEST_TIMEOUT
+                    StatusCode::GATEWAY_TIMEOUT
                 }))
-                .layer(TimeoutLayer::new(TIMEOUT)),
+                .layer(TimeoutLayer::new(request_timeout)),
         )
-        .with_state(Arc::new(state));
+        .with_state(state.clone());
+
+    let make_service = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let acceptor = TimeoutAcceptor::new(connection_timeout);
+
+    let result = match tls {
+        Some(tls) => {
+            let rustls_config = tls::load(&tls)
+                .await
+                .expect("Could not load the TLS certificate.");
+
+            tls::reload_on_change(rustls_config.clone(), tls);
+
+            // The TLS handshake runs on top of the timeout stream, so slow
+            // handshakes are closed too.
+            server
+                .acceptor(RustlsAcceptor::new(rustls_config).acceptor(acceptor))
+                .serve(make_service)
+                .await
+        }
+        None => server.acceptor(acceptor).serve(make_service).await,
+    };
+
+    result.expect("Axum server crashed.");
+}
 
-    server
-        .acceptor(TimeoutAcceptor)
-        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
-        .await
-        .expect("Axum server crashed.");
+/// The public routes: the web UI, the API docs and the API versions. The version 1 routes
+/// are also served at the root, deprecated.
+fn public_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(ui::index_handler))
+        .route("/assets/:name", get(ui::asset_handler))
+        .route("/api/openapi.json", get(v1::openapi_json_handler))
+        .route("/api/docs", get(openapi::docs_handler))
+        .nest(v1::PREFIX, v1::router())
+        .merge(legacy(v1::routes()))
+}
+
+/// Marks the routes as deprecated legacy aliases of the version 1 routes.
+fn legacy(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.layer(middleware::from_fn_with_state(
+        v1::PREFIX,
+        deprecation::legacy_routes,
+    ))
 }
 
-fn from_tcp_with_timeouts(socket: TcpListener) -> Server {
+pub(crate) fn from_tcp_with_timeouts(socket: TcpListener) -> Server {
     let mut server = axum_server::from_tcp(socket);
 
     server.http_builder().http1().timer(TokioTimer::new());
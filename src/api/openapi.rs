@@ -0,0 +1,41 @@
+//! Interactive API documentation.
+//!
+//! Each API version has its own [OpenAPI](https://spec.openapis.org/oas/v3.1.0)
+//! document, generated from the handler annotations, like
+//! `/api/v1/openapi.json`. `/api/openapi.json` is the document of the current
+//! version, and the page at `/api/docs` renders it with
+//! [Swagger UI](https://swagger.io/tools/swagger-ui/). The admin API is not
+//! included.
+use axum::response::Html;
+
+/// The Swagger UI page. Its assets are embedded and served from `/assets`;
+/// the query carries the Swagger UI version so upgrades aren't cached.
+pub async fn docs_handler() -> Html<&'static str> {
+    Html(
+        r##"<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Hash2Torrent API</title>
+    <link rel="stylesheet" href="/assets/swagger-ui.css?v=5.17.14">
+</head>
+
+<body>
+    <div id="swagger-ui"></div>
+    <script src="/assets/swagger-ui-bundle.js?v=5.17.14"></script>
+    <script>
+        window.onload = () => {
+            window.ui = SwaggerUIBundle({
+                url: "/api/openapi.json",
+                dom_id: "#swagger-ui",
+            });
+        };
+    </script>
+</body>
+
+</html>
+"##,
+    )
+}
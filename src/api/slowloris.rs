@@ -17,10 +17,20 @@ use tokio::{
 };
 use tower::Service;
 
-const TIMEOUT: Duration = Duration::from_secs(10);
-
+/// Closes connections that are idle, or take too long to send a request, for
+/// longer than the timeout. The timer is paused while a response is being
+/// sent.
 #[derive(Clone)]
-pub struct TimeoutAcceptor;
+pub struct TimeoutAcceptor {
+    timeout: Duration,
+}
+
+impl TimeoutAcceptor {
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
 
 impl<I, S> Accept<I, S> for TimeoutAcceptor {
     type Stream = TimeoutStream<I>;
@@ -30,7 +40,7 @@ impl<I, S> Accept<I, S> for TimeoutAcceptor {
     fn accept(&self, stream: I, service: S) -> Self::Future {
         let (tx, rx) = mpsc::unbounded_channel();
 
-        let stream = TimeoutStream::new(stream, TIMEOUT, rx);
+        let stream = TimeoutStream::new(stream, self.timeout, rx);
         let service = TimeoutService::new(service, tx);
 
         std::future::ready(Ok((stream, service)))
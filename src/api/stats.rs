@@ -0,0 +1,525 @@
+//! Service statistics for people, in JSON or HTML.
+//!
+//! The handlers count the torrent requests and whether they were served from
+//! the cache, and the resolutions report their duration or why they failed.
+//! The counters live in memory, so they start again when the service is
+//! restarted. Use the Prometheus metrics for monitoring.
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use hyper::{header, HeaderMap};
+use serde::Serialize;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::api::audit_log::CacheStatus;
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::resolver::ResolveError;
+use crate::AppState;
+
+/// Number of hours in the requests per hour.
+const HOURS: u64 = 24;
+
+/// Number of recent resolution times the percentiles are computed from.
+const MAX_RESOLUTION_TIMES: usize = 1000;
+
+/// Number of info-hashes whose requests are counted. When a new one comes,
+/// the least requested is replaced.
+const MAX_TRACKED_INFO_HASHES: usize = 1000;
+
+/// Number of info-hashes in the most requested list.
+const TOP_INFO_HASHES: usize = 10;
+
+/// In-process counters of the torrent requests and resolutions.
+#[derive(Default)]
+pub struct Stats {
+    counters: Mutex<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    /// Requests by the hour they were made, in hours since the Unix epoch,
+    /// oldest first.
+    hourly_requests: VecDeque<(u64, u64)>,
+    cache_hits: u64,
+    cache_misses: u64,
+    /// The most recent resolution times, oldest first.
+    resolution_times: VecDeque<Duration>,
+    requests_by_info_hash: HashMap<InfoHash, u64>,
+    failures: Failures,
+}
+
+impl Stats {
+    /// Counts a request for the torrent.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn record_request(&self, info_hash: &InfoHash, cache_status: CacheStatus) {
+        let hour = current_hour();
+        let mut counters = self.counters.lock().expect("the stats lock is poisoned");
+
+        match counters.hourly_requests.back_mut() {
+            Some((last_hour, requests)) if *last_hour == hour => *requests += 1,
+            _ => counters.hourly_requests.push_back((hour, 1)),
+        }
+
+        while counters
+            .hourly_requests
+            .front()
+            .is_some_and(|(first_hour, _)| first_hour + HOURS <= hour)
+        {
+            counters.hourly_requests.pop_front();
+        }
+
+        match cache_status {
+            CacheStatus::Hit => counters.cache_hits += 1,
+            CacheStatus::Miss => counters.cache_misses += 1,
+        }
+
+        counters.count_info_hash(info_hash);
+    }
+
+    /// Records the time a successful resolution took.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn record_resolution(&self, duration: Duration) {
+        let mut counters = self.counters.lock().expect("the stats lock is poisoned");
+
+        if counters.resolution_times.len() == MAX_RESOLUTION_TIMES {
+            counters.resolution_times.pop_front();
+        }
+
+        counters.resolution_times.push_back(duration);
+    }
+
+    /// Counts a failed resolution by its cause.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn record_failure(&self, err: &ResolveError) {
+        let mut counters = self.counters.lock().expect("the stats lock is poisoned");
+
+        match err {
+            ResolveError::Timeout => counters.failures.timeout += 1,
+            ResolveError::QueueFull => counters.failures.queue_full += 1,
+            ResolveError::NotFound(_) => counters.failures.not_found += 1,
+            ResolveError::Other(_) => counters.failures.other += 1,
+        }
+    }
+
+    /// Returns the statistics computed from the counters. The cache totals
+    /// are left at zero.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    #[must_use]
+    pub fn report(&self) -> StatsReport {
+        let hour = current_hour();
+        let counters = self.counters.lock().expect("the stats lock is poisoned");
+
+        let requests_per_hour = (hour + 1 - HOURS..=hour)
+            .map(|hour| HourlyRequests {
+                hour: hour * 3600,
+                requests: counters
+                    .hourly_requests
+                    .iter()
+                    .find(|(counted_hour, _)| *counted_hour == hour)
+                    .map_or(0, |(_, requests)| *requests),
+            })
+            .collect();
+
+        let requests = counters.cache_hits + counters.cache_misses;
+
+        #[allow(clippy::cast_precision_loss)]
+        let hit_ratio = (requests > 0).then(|| counters.cache_hits as f64 / requests as f64);
+
+        let mut resolution_times: Vec<Duration> =
+            counters.resolution_times.iter().copied().collect();
+        resolution_times.sort_unstable();
+
+        let mut most_requested: Vec<InfoHashRequests> = counters
+            .requests_by_info_hash
+            .iter()
+            .map(|(info_hash, requests)| InfoHashRequests {
+                info_hash: info_hash.to_hex_string(),
+                requests: *requests,
+            })
+            .collect();
+        most_requested.sort_unstable_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.info_hash.cmp(&b.info_hash))
+        });
+        most_requested.truncate(TOP_INFO_HASHES);
+
+        StatsReport {
+            requests_per_hour,
+            cache: CacheStats {
+                hits: counters.cache_hits,
+                misses: counters.cache_misses,
+                hit_ratio,
+                torrents: 0,
+                bytes: 0,
+            },
+            resolution_time: ResolutionTimes {
+                samples: resolution_times.len(),
+                median_ms: percentile(&resolution_times, 50),
+                p95_ms: percentile(&resolution_times, 95),
+                p99_ms: percentile(&resolution_times, 99),
+            },
+            most_requested,
+            failures: counters.failures,
+        }
+    }
+}
+
+impl Counters {
+    fn count_info_hash(&mut self, info_hash: &InfoHash) {
+        if let Some(requests) = self.requests_by_info_hash.get_mut(info_hash) {
+            *requests += 1;
+            return;
+        }
+
+        // The new info-hash takes the place of the least requested one and
+        // its count, so frequent info-hashes are not pushed out by a burst
+        // of new ones.
+        let mut requests = 1;
+
+        if self.requests_by_info_hash.len() == MAX_TRACKED_INFO_HASHES {
+            if let Some((least_requested, least_requests)) = self
+                .requests_by_info_hash
+                .iter()
+                .min_by_key(|(_, requests)| **requests)
+                .map(|(info_hash, requests)| (*info_hash, *requests))
+            {
+                self.requests_by_info_hash.remove(&least_requested);
+                requests += least_requests;
+            }
+        }
+
+        self.requests_by_info_hash.insert(*info_hash, requests);
+    }
+}
+
+/// The service statistics since it was started.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct StatsReport {
+    /// Torrent requests in each of the last 24 hours, oldest first.
+    pub requests_per_hour: Vec<HourlyRequests>,
+    pub cache: CacheStats,
+    /// Time to resolve the torrents that were not cached, from the most
+    /// recent 1000 resolutions.
+    pub resolution_time: ResolutionTimes,
+    /// The 10 most requested info-hashes.
+    pub most_requested: Vec<InfoHashRequests>,
+    pub failures: Failures,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HourlyRequests {
+    /// Unix time of the start of the hour.
+    pub hour: u64,
+    pub requests: u64,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    /// Requests served from the cache.
+    pub hits: u64,
+    /// Requests that needed a resolution.
+    pub misses: u64,
+    /// Hits over all the requests. It's not set until there is a request.
+    pub hit_ratio: Option<f64>,
+    /// Number of cached torrent files.
+    pub torrents: u64,
+    /// Total size of the cached torrent files in bytes.
+    pub bytes: u64,
+}
+
+/// Resolution time percentiles in milliseconds. They are not set until a
+/// torrent is resolved.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolutionTimes {
+    pub samples: usize,
+    pub median_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct InfoHashRequests {
+    #[schema(example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: String,
+    pub requests: u64,
+}
+
+/// Failed resolutions by cause.
+#[derive(Serialize, ToSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Failures {
+    /// The metadata was not found in time.
+    pub timeout: u64,
+    /// The resolution queue was full.
+    pub queue_full: u64,
+    /// The resolver could not find the metadata.
+    pub not_found: u64,
+    pub other: u64,
+}
+
+/// Returns the service statistics: the requests per hour, the cache hit
+/// ratio, the resolution time percentiles, the most requested info-hashes and
+/// the failed resolutions. It's an HTML page if the request accepts
+/// `text/html`.
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "service",
+    responses(
+        (status = 200, description = "The service statistics", content(
+            (StatsReport = "application/json"),
+            (String = "text/html")
+        ),
+            headers(("Vary" = String, description = "`Accept`: the format depends on it"))
+        ),
+    )
+)]
+#[allow(clippy::module_name_repetitions)]
+pub async fn stats_handler(
+    State(app_state): State<Arc<AppState>>,
+    request_headers: HeaderMap,
+) -> Response {
+    let mut report = app_state.stats.report();
+
+    match app_state.cache.usage() {
+        Ok(usage) => {
+            report.cache.torrents = usage.torrents;
+            report.cache.bytes = usage.bytes;
+        }
+        Err(err) => error!("could not get the cache usage: {err}"),
+    }
+
+    let accepts_html = request_headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("text/html"));
+
+    let headers = [(header::VARY, "Accept")];
+
+    if accepts_html {
+        return (headers, Html(html(&report))).into_response();
+    }
+
+    (headers, Json(report)).into_response()
+}
+
+/// The nearest-rank percentile of the sorted durations, in milliseconds.
+fn percentile(sorted: &[Duration], percent: usize) -> Option<u64> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+
+    sorted
+        .get(rank - 1)
+        .map(|duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+fn current_hour() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+        / 3600
+}
+
+fn html(report: &StatsReport) -> String {
+    let optional = |value: Option<u64>, unit: &str| {
+        value.map_or_else(|| "-".to_string(), |value| format!("{value} {unit}"))
+    };
+
+    let mut page = String::from(
+        r#"<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Hash2Torrent statistics</title>
+    <style>
+        body { font-family: sans-serif; margin: 2rem; }
+        table { border-collapse: collapse; margin-bottom: 2rem; }
+        th, td { border: 1px solid #ccc; padding: 0.25rem 0.75rem; text-align: left; }
+        td.number { text-align: right; }
+    </style>
+</head>
+
+<body>
+    <h1>Hash2Torrent statistics</h1>
+"#,
+    );
+
+    let _ = write!(
+        page,
+        r#"
+    <h2>Cache</h2>
+    <table>
+        <tr><th>Hits</th><td class="number">{}</td></tr>
+        <tr><th>Misses</th><td class="number">{}</td></tr>
+        <tr><th>Hit ratio</th><td class="number">{}</td></tr>
+        <tr><th>Cached torrents</th><td class="number">{}</td></tr>
+        <tr><th>Cached bytes</th><td class="number">{}</td></tr>
+    </table>
+
+    <h2>Resolution time</h2>
+    <table>
+        <tr><th>Resolutions</th><td class="number">{}</td></tr>
+        <tr><th>Median</th><td class="number">{}</td></tr>
+        <tr><th>95th percentile</th><td class="number">{}</td></tr>
+        <tr><th>99th percentile</th><td class="number">{}</td></tr>
+    </table>
+
+    <h2>Failed resolutions</h2>
+    <table>
+        <tr><th>Timeout</th><td class="number">{}</td></tr>
+        <tr><th>Queue full</th><td class="number">{}</td></tr>
+        <tr><th>Not found</th><td class="number">{}</td></tr>
+        <tr><th>Other</th><td class="number">{}</td></tr>
+    </table>
+
+    <h2>Most requested</h2>
+    <table>
+        <tr><th>Info-hash</th><th>Requests</th></tr>
+"#,
+        report.cache.hits,
+        report.cache.misses,
+        report.cache.hit_ratio.map_or_else(
+            || "-".to_string(),
+            |ratio| format!("{:.1} %", ratio * 100.0)
+        ),
+        report.cache.torrents,
+        report.cache.bytes,
+        report.resolution_time.samples,
+        optional(report.resolution_time.median_ms, "ms"),
+        optional(report.resolution_time.p95_ms, "ms"),
+        optional(report.resolution_time.p99_ms, "ms"),
+        report.failures.timeout,
+        report.failures.queue_full,
+        report.failures.not_found,
+        report.failures.other,
+    );
+
+    for info_hash in &report.most_requested {
+        let _ = writeln!(
+            page,
+            r#"        <tr><td><code>{}</code></td><td class="number">{}</td></tr>"#,
+            info_hash.info_hash, info_hash.requests
+        );
+    }
+
+    page.push_str(
+        r"    </table>
+
+    <h2>Requests per hour</h2>
+    <table>
+        <tr><th>Hour (UTC)</th><th>Requests</th></tr>
+",
+    );
+
+    for hour in &report.requests_per_hour {
+        let _ = writeln!(
+            page,
+            r#"        <tr><td>{:02}:00</td><td class="number">{}</td></tr>"#,
+            hour.hour % 86_400 / 3600,
+            hour.requests
+        );
+    }
+
+    page.push_str(
+        r"    </table>
+</body>
+
+</html>
+",
+    );
+
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use super::{percentile, Stats, MAX_TRACKED_INFO_HASHES};
+    use crate::api::audit_log::CacheStatus;
+    use crate::bit_torrent::info_hash::InfoHash;
+    use crate::bit_torrent::resolver::ResolveError;
+
+    fn info_hash(n: usize) -> InfoHash {
+        InfoHash::from_str(&format!("{n:040x}")).unwrap()
+    }
+
+    #[test]
+    fn it_should_compute_nearest_rank_percentiles() {
+        let durations: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&durations, 50), Some(50));
+        assert_eq!(percentile(&durations, 95), Some(95));
+        assert_eq!(percentile(&durations, 99), Some(99));
+        assert_eq!(percentile(&durations[..1], 99), Some(1));
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn it_should_report_the_cache_hit_ratio_and_the_failures() {
+        let stats = Stats::default();
+
+        stats.record_request(&info_hash(1), CacheStatus::Miss);
+        stats.record_request(&info_hash(1), CacheStatus::Hit);
+        stats.record_request(&info_hash(1), CacheStatus::Hit);
+        stats.record_request(&info_hash(2), CacheStatus::Hit);
+        stats.record_failure(&ResolveError::Timeout);
+        stats.record_failure(&ResolveError::NotFound("no peers".to_string()));
+
+        let report = stats.report();
+
+        assert_eq!(report.cache.hits, 3);
+        assert_eq!(report.cache.misses, 1);
+        assert_eq!(report.cache.hit_ratio, Some(0.75));
+        assert_eq!(report.requests_per_hour.len(), 24);
+        assert_eq!(report.requests_per_hour[23].requests, 4);
+        assert_eq!(report.failures.timeout, 1);
+        assert_eq!(report.failures.not_found, 1);
+        assert_eq!(
+            report.most_requested[0].info_hash,
+            info_hash(1).to_hex_string()
+        );
+        assert_eq!(report.most_requested[0].requests, 3);
+    }
+
+    #[test]
+    fn it_should_keep_the_most_requested_info_hashes_when_there_are_too_many() {
+        let stats = Stats::default();
+
+        for _ in 0..5 {
+            stats.record_request(&info_hash(0), CacheStatus::Hit);
+        }
+        for n in 1..=MAX_TRACKED_INFO_HASHES {
+            stats.record_request(&info_hash(n), CacheStatus::Hit);
+        }
+
+        let report = stats.report();
+
+        assert_eq!(
+            report.most_requested[0].info_hash,
+            info_hash(0).to_hex_string()
+        );
+        assert_eq!(report.most_requested[0].requests, 5);
+    }
+}
@@ -0,0 +1,146 @@
+//! HTTPS support with `rustls`.
+//!
+//! The certificate files are checked periodically and loaded again when they
+//! change, so renewed certificates are used without restarting the service.
+//! An optional plain HTTP listener redirects every request to HTTPS.
+use std::io;
+use std::net::TcpListener;
+use std::time::{Duration, SystemTime};
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use hyper::{header, HeaderMap, StatusCode, Uri};
+use tracing::{error, info};
+
+use crate::api::from_tcp_with_timeouts;
+use crate::api::slowloris::TimeoutAcceptor;
+use crate::config::Tls;
+
+/// Loads the certificate and the private key.
+///
+/// # Errors
+///
+/// Will return an error if the files can't be read or they are not valid PEM
+/// files.
+pub async fn load(tls: &Tls) -> io::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&tls.certificate_path, &tls.private_key_path).await
+}
+
+/// Checks the certificate files at the configured interval and loads them
+/// again when they change. The current certificate is kept if the new files
+/// are not valid.
+pub fn reload_on_change(rustls_config: RustlsConfig, tls: Tls) {
+    tokio::spawn(async move {
+        let mut last_modified = modified(&tls);
+
+        let mut interval = tokio::time::interval(tls.reload_check_interval);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let modified = modified(&tls);
+            if modified == last_modified {
+                continue;
+            }
+
+            match rustls_config
+                .reload_from_pem_file(&tls.certificate_path, &tls.private_key_path)
+                .await
+            {
+                Ok(()) => {
+                    info!("reloaded TLS certificate from {}", tls.certificate_path);
+                    last_modified = modified;
+                }
+                Err(err) => error!("error reloading TLS certificate: {err}"),
+            }
+        }
+    });
+}
+
+/// The modification times of the certificate and the private key files.
+fn modified(tls: &Tls) -> Option<(SystemTime, SystemTime)> {
+    let certificate = std::fs::metadata(&tls.certificate_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let private_key = std::fs::metadata(&tls.private_key_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+
+    Some((certificate, private_key))
+}
+
+/// Serves the plain HTTP listener that redirects to the HTTPS port.
+///
+/// # Errors
+///
+/// Will return an error if it can't get the local server address or the
+/// server fails.
+pub async fn serve_redirect(
+    socket: TcpListener,
+    https_port: u16,
+    connection_timeout: Duration,
+) -> io::Result<()> {
+    let server_address = socket.local_addr()?;
+
+    info!("HTTP to HTTPS redirect bound to address: http://{server_address}"); // DevSkim: ignore DS137138
+
+    let app = Router::new()
+        .fallback(redirect_handler)
+        .with_state(https_port);
+
+    from_tcp_with_timeouts(socket)
+        .acceptor(TimeoutAcceptor::new(connection_timeout))
+        .serve(app.into_make_service())
+        .await
+}
+
+async fn redirect_handler(State(https_port): State<u16>, headers: HeaderMap, uri: Uri) -> Response {
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing host header").into_response();
+    };
+
+    let path_and_query = uri.path_and_query().map_or("/", |path| path.as_str());
+
+    Redirect::permanent(&https_url(host, https_port, path_and_query)).into_response()
+}
+
+/// The HTTPS URL for the request. The port in the `Host` header is replaced
+/// with the HTTPS port.
+fn https_url(host: &str, https_port: u16, path_and_query: &str) -> String {
+    // IPv6 addresses are enclosed in brackets, and they contain colons.
+    let hostname = match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => &host[..colon],
+        _ => host,
+    };
+
+    if https_port == 443 {
+        format!("https://{hostname}{path_and_query}")
+    } else {
+        format!("https://{hostname}:{https_port}{path_and_query}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::https_url;
+
+    #[test]
+    fn it_should_redirect_to_the_https_port() {
+        assert_eq!(
+            https_url("example.com:80", 3443, "/torrents/abc?timeout=30"),
+            "https://example.com:3443/torrents/abc?timeout=30"
+        );
+        assert_eq!(https_url("example.com", 443, "/"), "https://example.com/");
+        assert_eq!(
+            https_url("[::1]:8080", 443, "/health_check"),
+            "https://[::1]/health_check"
+        );
+        assert_eq!(https_url("[::1]", 443, "/"), "https://[::1]/");
+    }
+}
@@ -0,0 +1,88 @@
+//! The web UI.
+//!
+//! A single page that takes an info-hash or a magnet link, follows the
+//! resolution with the progress events and shows the torrent information.
+//! The page template and its assets are embedded in the binary, and so is
+//! the [Swagger UI](https://github.com/swagger-api/swagger-ui) used by the
+//! API docs page (Apache-2.0, see `ui/swagger-ui/LICENSE`).
+use axum::extract::Path;
+use axum::response::{Html, IntoResponse, Response};
+use hyper::{header, StatusCode};
+
+use crate::api::v1;
+
+const INDEX_TEMPLATE: &str = include_str!("ui/index.html");
+
+/// `Cache-Control` for the assets. Their URLs change with the version.
+const ASSETS_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// An embedded asset: its name in `/assets`, the content type and the
+/// contents.
+const ASSETS: [(&str, &str, &str); 4] = [
+    (
+        "app.js",
+        "text/javascript; charset=utf-8",
+        include_str!("ui/app.js"),
+    ),
+    (
+        "style.css",
+        "text/css; charset=utf-8",
+        include_str!("ui/style.css"),
+    ),
+    (
+        "swagger-ui-bundle.js",
+        "text/javascript; charset=utf-8",
+        include_str!("ui/swagger-ui/swagger-ui-bundle.js"),
+    ),
+    (
+        "swagger-ui.css",
+        "text/css; charset=utf-8",
+        include_str!("ui/swagger-ui/swagger-ui.css"),
+    ),
+];
+
+/// Renders the page template.
+///
+/// - `{{api}}`: the prefix of the API the page calls.
+/// - `{{version}}`: the service version. It's added to the assets URLs so
+///   browsers get the new assets after an upgrade.
+#[must_use]
+pub fn render_index() -> String {
+    INDEX_TEMPLATE
+        .replace("{{api}}", v1::PREFIX)
+        .replace("{{version}}", env!("CARGO_PKG_VERSION"))
+}
+
+pub async fn index_handler() -> Html<String> {
+    Html(render_index())
+}
+
+pub async fn asset_handler(Path(name): Path<String>) -> Response {
+    let Some((_, content_type, contents)) = ASSETS.iter().find(|(asset, _, _)| *asset == name)
+    else {
+        return (StatusCode::NOT_FOUND, "Asset not found").into_response();
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, *content_type),
+            (header::CACHE_CONTROL, ASSETS_CACHE_CONTROL),
+        ],
+        *contents,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_index;
+
+    #[test]
+    fn it_should_fill_in_all_the_template_placeholders() {
+        let index = render_index();
+
+        assert!(!index.contains("{{"), "unrendered placeholder in: {index}");
+        assert!(index.contains(r#"data-api="/api/v1""#));
+        assert!(index.contains(&format!("/assets/app.js?v={}", env!("CARGO_PKG_VERSION"))));
+    }
+}
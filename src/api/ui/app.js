@@ -0,0 +1,244 @@
+"use strict";
+
+const API = document.body.dataset.api;
+
+const PHASES = {
+    queued: () => "Waiting for a free resolution slot",
+    dht_lookup_started: () => "Looking for peers in the DHT",
+    peers_found: (event) => `Found ${event.count} peer(s)`,
+    connected_to_peer: (event) => `Connected to ${event.peer}`,
+    metadata_piece_received: (event) =>
+        `Received metadata piece ${event.piece + 1} of ${event.pieces} from ${event.peer}`,
+    verified: () => "Metadata verified",
+    cached: () => "Torrent file ready",
+    failed: (event) => `Failed: ${event.reason}`,
+};
+
+const BASE32_ALPHABET = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+let source = null;
+
+// Returns the hexadecimal info-hash and the trackers of an info-hash or a
+// magnet link, or null if it's not valid.
+function parseQuery(text) {
+    let infoHash = text;
+    let trackers = [];
+
+    if (text.startsWith("magnet:")) {
+        const params = new URLSearchParams(text.slice(text.indexOf("?") + 1));
+        const topic = params.getAll("xt").find((xt) => xt.toLowerCase().startsWith("urn:btih:"));
+        if (!topic) {
+            return null;
+        }
+        infoHash = topic.slice("urn:btih:".length);
+        trackers = params.getAll("tr");
+    }
+
+    if (/^[0-9a-fA-F]{40}$/.test(infoHash)) {
+        return { infoHash: infoHash.toLowerCase(), trackers };
+    }
+
+    if (/^[A-Za-z2-7]{32}$/.test(infoHash)) {
+        return { infoHash: base32ToHex(infoHash.toUpperCase()), trackers };
+    }
+
+    return null;
+}
+
+function base32ToHex(text) {
+    let bits = "";
+    for (const char of text) {
+        bits += BASE32_ALPHABET.indexOf(char).toString(2).padStart(5, "0");
+    }
+    let hex = "";
+    for (let i = 0; i < bits.length; i += 4) {
+        hex += parseInt(bits.slice(i, i + 4), 2).toString(16);
+    }
+    return hex;
+}
+
+function formatBytes(bytes) {
+    const units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let value = bytes;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+        value /= 1024;
+        unit += 1;
+    }
+    const size = unit === 0 ? value : value.toFixed(2);
+    return `${size} ${units[unit]} (${bytes.toLocaleString()} bytes)`;
+}
+
+function show(id, visible) {
+    document.getElementById(id).hidden = !visible;
+}
+
+function addEvent(event) {
+    const item = document.createElement("li");
+    item.className = event.phase;
+    item.textContent = PHASES[event.phase](event);
+    document.getElementById("events").append(item);
+}
+
+function showError(message) {
+    const error = document.getElementById("error");
+    error.textContent = message;
+    show("error", true);
+}
+
+function torrentUrl(infoHash, trackers, suffix) {
+    const params = new URLSearchParams();
+    for (const tracker of trackers) {
+        params.append("tr", tracker);
+    }
+    const query = params.toString();
+    return `${API}/torrents/${infoHash}${suffix}${query ? `?${query}` : ""}`;
+}
+
+// Builds the nested directories of the torrent files.
+function fileTree(files) {
+    const root = { children: new Map(), length: 0 };
+    for (const file of files) {
+        let node = root;
+        for (const component of file.path) {
+            if (!node.children.has(component)) {
+                node.children.set(component, { children: new Map(), length: 0 });
+            }
+            node = node.children.get(component);
+            node.length += file.length;
+        }
+    }
+    return root;
+}
+
+function renderTree(node, list) {
+    for (const [name, child] of node.children) {
+        const item = document.createElement("li");
+        const label = document.createElement("span");
+        label.textContent = child.children.size > 0 ? `📁 ${name}` : `📄 ${name}`;
+        const size = document.createElement("span");
+        size.className = "size";
+        size.textContent = formatBytes(child.length);
+        item.append(label, size);
+
+        if (child.children.size > 0) {
+            const children = document.createElement("ul");
+            renderTree(child, children);
+            item.append(children);
+        }
+
+        list.append(item);
+    }
+}
+
+function renderTorrent(info, trackers) {
+    document.getElementById("torrent-name").textContent = info.name || info.info_hash;
+    document.getElementById("torrent-info-hash").textContent = info.info_hash;
+    document.getElementById("torrent-size").textContent = formatBytes(info.total_length);
+    document.getElementById("torrent-pieces").textContent =
+        `${info.pieces.toLocaleString()} × ${formatBytes(info.piece_length)}`;
+    document.getElementById("torrent-file-count").textContent = info.files.length.toLocaleString();
+
+    document.getElementById("download").href = torrentUrl(info.info_hash, trackers, "");
+    document.getElementById("json").href = torrentUrl(info.info_hash, trackers, "/info");
+
+    const copy = document.getElementById("copy-magnet");
+    copy.onclick = async () => {
+        const response = await fetch(torrentUrl(info.info_hash, trackers, "/magnet"));
+        await navigator.clipboard.writeText(await response.text());
+        copy.textContent = "Copied!";
+        setTimeout(() => {
+            copy.textContent = "Copy magnet link";
+        }, 2000);
+    };
+
+    const files = document.getElementById("files");
+    files.replaceChildren();
+    renderTree(fileTree(info.files), files);
+
+    show("torrent", true);
+}
+
+// Gets the torrent information, or shows the error the API returns.
+async function loadTorrent(infoHash, trackers) {
+    const response = await fetch(torrentUrl(infoHash, trackers, "/info"));
+    if (!response.ok) {
+        showError(await response.text());
+        return;
+    }
+    renderTorrent(await response.json(), trackers);
+}
+
+function resolve(infoHash, trackers) {
+    if (source) {
+        source.close();
+    }
+
+    document.getElementById("events").replaceChildren();
+    show("progress", true);
+    show("error", false);
+    show("torrent", false);
+
+    let finished = false;
+
+    source = new EventSource(torrentUrl(infoHash, trackers, "/events"));
+
+    for (const phase of Object.keys(PHASES)) {
+        source.addEventListener(phase, (message) => {
+            const event = JSON.parse(message.data);
+            addEvent(event);
+
+            if (phase === "cached") {
+                finished = true;
+                source.close();
+                loadTorrent(infoHash, trackers);
+            } else if (phase === "failed") {
+                finished = true;
+                source.close();
+                showError(event.reason);
+            }
+        });
+    }
+
+    // The stream can't be opened for invalid or denied info-hashes. The JSON
+    // endpoint returns the reason.
+    source.onerror = () => {
+        if (!finished) {
+            source.close();
+            loadTorrent(infoHash, trackers);
+        }
+    };
+}
+
+function lookup(text) {
+    const query = parseQuery(text.trim());
+    if (!query) {
+        showError("Please enter a valid info-hash like 443c7602b4fde83d1154d6d9da48808418b181b6, or a magnet link");
+        return;
+    }
+    resolve(query.infoHash, query.trackers);
+}
+
+document.getElementById("lookup").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const text = document.getElementById("query").value.trim();
+    if (!text.startsWith("magnet:") && window.location.hash !== `#${text}`) {
+        // The `hashchange` listener looks it up.
+        window.location.hash = text;
+        return;
+    }
+    lookup(text);
+});
+
+// Info-hashes in the URL fragment, like `/#443c7602b4fde83d1154d6d9da48808418b181b6`,
+// are looked up when the page is loaded.
+function lookupFragment() {
+    const fragment = decodeURIComponent(window.location.hash.slice(1));
+    if (fragment) {
+        document.getElementById("query").value = fragment;
+        lookup(fragment);
+    }
+}
+
+window.addEventListener("hashchange", lookupFragment);
+lookupFragment();
@@ -0,0 +1,193 @@
+//! Version 1 of the API, served under `/api/v1`.
+//!
+//! The legacy routes at the root are aliases of these routes. A new version
+//! goes in its own module, with its own router and `OpenAPI` document, and it's
+//! nested next to this one.
+use std::sync::Arc;
+
+use axum::routing::{get, post, MethodRouter};
+use axum::{middleware, Json, Router};
+use utoipa::OpenApi;
+
+use crate::api::feeds::{self, recent_atom_handler, recent_rss_handler};
+use crate::api::handler::{
+    self, get_events_handler, get_info_handler, get_magnet_handler, get_metainfo_file_handler,
+    get_peers_handler, get_swarm_handler, health_check_handler, post_resolution_handler,
+};
+use crate::api::http_cache;
+use crate::api::metrics::{self, metrics_handler};
+use crate::api::stats::{self, stats_handler};
+use crate::AppState;
+
+pub const PREFIX: &str = "/api/v1";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Torrust Hash2Torrent",
+        description = "A web service to get torrents' metadata from the infohashes."
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        handler::get_metainfo_file_handler,
+        handler::get_events_handler,
+        handler::get_info_handler,
+        handler::get_magnet_handler,
+        handler::get_peers_handler,
+        handler::post_resolution_handler,
+        handler::get_swarm_handler,
+        handler::health_check_handler,
+        metrics::metrics_handler,
+        stats::stats_handler,
+        feeds::recent_atom_handler,
+        feeds::recent_rss_handler,
+    ),
+    tags(
+        (name = "torrents", description = "Torrent files and diagnostics"),
+        (name = "service", description = "Service status"),
+        (name = "feeds", description = "Feeds of recently resolved torrents")
+    )
+)]
+pub struct ApiDoc;
+
+/// The version 1 router, with its `OpenAPI` document. It has to be nested
+/// under [`PREFIX`].
+pub fn router() -> Router<Arc<AppState>> {
+    routes().route("/openapi.json", get(openapi_json_handler))
+}
+
+/// The documented routes. They are also served at the root for backward
+/// compatibility.
+pub fn routes() -> Router<Arc<AppState>> {
+    documented_routes().router
+}
+
+fn documented_routes() -> Routes {
+    Routes::new()
+        .route("/health_check", get(health_check_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/stats", get(stats_handler))
+        .route("/feeds/recent.atom", get(recent_atom_handler))
+        .route("/feeds/recent.rss", get(recent_rss_handler))
+        .nest("/torrents", torrents_routes())
+}
+
+/// The torrents routes. `get` routes also answer `HEAD` requests, without
+/// the body.
+fn torrents_routes() -> Routes {
+    Routes::new()
+        .route("/:info_hash", get(get_metainfo_file_handler))
+        .route("/:info_hash/events", get(get_events_handler))
+        .route("/:info_hash/info", get(get_info_handler))
+        .route("/:info_hash/magnet", get(get_magnet_handler))
+        .route("/:info_hash/peers", get(get_peers_handler))
+        .route("/:info_hash/resolutions", post(post_resolution_handler))
+        .route("/:info_hash/swarm", get(get_swarm_handler))
+        .map_router(|router| {
+            router.layer(middleware::map_response(http_cache::short_lived_failures))
+        })
+}
+
+/// A router that records the paths of its routes, in the `OpenAPI` format,
+/// so the tests can check that every route is documented.
+struct Routes {
+    router: Router<Arc<AppState>>,
+    paths: Vec<String>,
+}
+
+impl Routes {
+    fn new() -> Self {
+        Self {
+            router: Router::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    fn route(self, path: &str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        let mut paths = self.paths;
+        paths.push(openapi_path(path));
+
+        Self {
+            router: self.router.route(path, method_router),
+            paths,
+        }
+    }
+
+    fn nest(self, prefix: &str, routes: Routes) -> Self {
+        let mut paths = self.paths;
+        paths.extend(
+            routes
+                .paths
+                .iter()
+                .map(|path| format!("{}{path}", openapi_path(prefix))),
+        );
+
+        Self {
+            router: self.router.nest(prefix, routes.router),
+            paths,
+        }
+    }
+
+    fn map_router(self, map: impl FnOnce(Router<Arc<AppState>>) -> Router<Arc<AppState>>) -> Self {
+        Self {
+            router: map(self.router),
+            paths: self.paths,
+        }
+    }
+}
+
+/// Converts an axum path, with `:param` segments, to an `OpenAPI` path, with
+/// `{param}` segments.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("{{{param}}}"),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub async fn openapi_json_handler() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use utoipa::OpenApi;
+
+    use super::{documented_routes, ApiDoc};
+
+    #[test]
+    fn it_should_document_the_response_schemas() {
+        let openapi = ApiDoc::openapi();
+
+        let schemas = openapi
+            .components
+            .expect("it should have components")
+            .schemas;
+
+        for schema in [
+            "HealthCheck",
+            "PeerLookup",
+            "ResolutionEvent",
+            "StatsReport",
+            "SwarmScrape",
+            "TorrentInfo",
+        ] {
+            assert!(schemas.contains_key(schema), "missing schema: {schema}");
+        }
+    }
+
+    /// The e2e tests check the methods of each documented path.
+    #[test]
+    fn it_should_document_all_the_routes() {
+        let documented: BTreeSet<String> = ApiDoc::openapi().paths.paths.keys().cloned().collect();
+        let routes: BTreeSet<String> = documented_routes().paths.into_iter().collect();
+
+        assert!(routes.contains("/torrents/{info_hash}/swarm"));
+        assert_eq!(routes, documented);
+    }
+}
@@ -0,0 +1,721 @@
+//! Webhook notifications for background resolutions.
+//!
+//! Callers can ask for a torrent to be resolved in the background and give a
+//! callback URL. When the torrent is cached, or its resolution fails, the
+//! service sends a `POST` request to the URL with a JSON [`Notification`].
+//!
+//! The request is signed with the secret of the webhook key chosen by the
+//! caller:
+//!
+//! - `X-Torrust-Webhook-Id`: the notification id. It's the same in all the
+//!   attempts, so receivers can ignore duplicates.
+//! - `X-Torrust-Webhook-Key`: the key id.
+//! - `X-Torrust-Webhook-Timestamp`: Unix time of the attempt.
+//! - `X-Torrust-Webhook-Signature`: `sha256=` and the hexadecimal
+//!   HMAC-SHA256 of `{timestamp}.{body}` with the key secret.
+//!
+//! Failed deliveries are retried with exponential backoff. Accepted
+//! resolutions and pending deliveries are stored in JSON files, one per
+//! notification, so they are resumed after a restart.
+//!
+//! Callers choose the callback URLs, so the service only sends the requests
+//! to public addresses, checked after resolving the host and again for every
+//! connection, and it doesn't follow redirects.
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use camino::Utf8PathBuf;
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::api::address;
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::config;
+
+pub const ID_HEADER: &str = "x-torrust-webhook-id";
+pub const KEY_HEADER: &str = "x-torrust-webhook-key";
+pub const TIMESTAMP_HEADER: &str = "x-torrust-webhook-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-torrust-webhook-signature";
+
+/// Subdirectory of the store for the accepted resolutions.
+const RESOLUTIONS_DIR: &str = "resolutions";
+
+/// Why the caller is notified.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// The torrent file is in the cache.
+    Cached,
+    /// The torrent could not be resolved.
+    Failed,
+}
+
+/// The body of the webhook requests.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub id: String,
+    pub event: NotificationEvent,
+    #[schema(example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: String,
+    /// The torrent name, for `cached` notifications.
+    pub name: Option<String>,
+    /// Why the resolution failed, for `failed` notifications.
+    pub reason: Option<String>,
+    /// Unix time when the resolution finished.
+    pub created_at: u64,
+}
+
+/// Where the caller wants to be notified.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Callback {
+    /// The notification id.
+    pub id: String,
+    pub url: String,
+    /// The webhook key used to sign the requests.
+    pub key_id: String,
+}
+
+/// An accepted background resolution, as stored in its file until its
+/// notification is stored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PendingResolution {
+    pub callback: Callback,
+    pub info_hash: String,
+    /// The query of the request. It's parsed again when the resolution is
+    /// resumed.
+    pub query: Option<String>,
+}
+
+/// A pending delivery, as stored in its file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Delivery {
+    id: String,
+    url: String,
+    key_id: String,
+    /// The serialized notification. It's kept as sent, so the signature is
+    /// computed over the same bytes in every attempt.
+    body: String,
+    /// Failed attempts so far.
+    attempts: u32,
+    /// Unix time of the next attempt.
+    next_attempt_at: u64,
+}
+
+/// Why a callback URL is rejected.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CallbackError {
+    #[error("Invalid callback URL")]
+    InvalidUrl,
+    #[error("Callback host not found")]
+    HostNotFound,
+    #[error("Callback address not allowed: {0}")]
+    AddressNotAllowed(IpAddr),
+}
+
+/// Resolves the callback hosts for the HTTP client, leaving out the
+/// addresses that are not allowed. The URLs are checked before they are
+/// accepted, but the host could resolve to another address when the request
+/// is sent.
+struct CallbackResolver {
+    allow_private: bool,
+}
+
+impl Resolve for CallbackResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private = self.allow_private;
+
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| allow_private || address::is_public(address.ip()))
+                .collect();
+
+            if addresses.is_empty() {
+                return Err(CallbackError::HostNotFound.into());
+            }
+
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+pub struct Webhooks {
+    config: config::Webhooks,
+    http_client: reqwest::Client,
+    /// Notifications accepted and not sent or abandoned yet.
+    pending: AtomicUsize,
+}
+
+impl Webhooks {
+    /// Creates the directories for the pending resolutions and deliveries,
+    /// if needed, and resumes the deliveries left by a previous run. The
+    /// resolutions are resumed by the API, see
+    /// [`Webhooks::pending_resolutions`].
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't create or read the directories.
+    pub fn start(config: config::Webhooks) -> io::Result<Arc<Self>> {
+        fs::create_dir_all(config.store_dir.join(RESOLUTIONS_DIR))?;
+
+        let http_client = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .redirect(redirect::Policy::none())
+            .dns_resolver(Arc::new(CallbackResolver {
+                allow_private: config.allow_private_callbacks,
+            }))
+            .build()
+            .map_err(io::Error::other)?;
+
+        let webhooks = Arc::new(Self {
+            config,
+            http_client,
+            pending: AtomicUsize::new(0),
+        });
+
+        let pending = webhooks.pending_deliveries()?;
+        let resolutions = webhooks.pending_resolutions()?;
+
+        webhooks
+            .pending
+            .store(pending.len() + resolutions.len(), Ordering::SeqCst);
+
+        if !pending.is_empty() {
+            info!("resuming {} pending webhook deliveries", pending.len());
+        }
+
+        for delivery in pending {
+            tokio::spawn(webhooks.clone().deliver(delivery));
+        }
+
+        Ok(webhooks)
+    }
+
+    /// Returns true if there is a secret for the key.
+    #[must_use]
+    pub fn has_key(&self, key_id: &str) -> bool {
+        self.config.keys.contains_key(key_id)
+    }
+
+    /// Checks that the callback URL is `http` or `https` and that its host
+    /// only resolves to public addresses, unless private callbacks are
+    /// allowed.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the URL is invalid, its host can't be
+    /// resolved or it resolves to an address that is not allowed.
+    pub async fn check_callback_url(&self, url: &str) -> Result<(), CallbackError> {
+        let url = Url::parse(url).map_err(|_| CallbackError::InvalidUrl)?;
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(CallbackError::InvalidUrl);
+        }
+
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return Err(CallbackError::InvalidUrl);
+        };
+
+        // IPv6 hosts are written in brackets in URLs.
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| CallbackError::HostNotFound)?
+            .collect();
+
+        if addresses.is_empty() {
+            return Err(CallbackError::HostNotFound);
+        }
+
+        if self.config.allow_private_callbacks {
+            return Ok(());
+        }
+
+        match addresses
+            .iter()
+            .find(|address| !address::is_public(address.ip()))
+        {
+            Some(address) => Err(CallbackError::AddressNotAllowed(address.ip())),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes a place for a new notification. It returns `false` when there
+    /// are already the maximum number of pending notifications.
+    ///
+    /// The place is released when the notification is sent or abandoned, or
+    /// with [`Webhooks::release`] if it's not going to be sent.
+    pub fn try_reserve(&self) -> bool {
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                (pending < self.config.max_pending).then_some(pending + 1)
+            })
+            .is_ok()
+    }
+
+    /// The wait before resolving the torrent again when the resolution
+    /// queue was full: the same as the backoff of the deliveries.
+    #[must_use]
+    pub fn resolution_backoff(&self, attempts: u32) -> Duration {
+        backoff(&self.config, attempts)
+    }
+
+    /// Releases a place taken with [`Webhooks::try_reserve`].
+    pub fn release(&self) {
+        self.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Stores an accepted resolution, so it's resumed after a restart. It's
+    /// removed when its notification is stored.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write the file.
+    pub fn accept(&self, resolution: &PendingResolution) -> io::Result<()> {
+        write_json(&self.resolution_path(&resolution.callback.id), resolution)
+    }
+
+    /// Removes a resolution that won't be resumed and releases its place.
+    pub fn discard(&self, resolution: &PendingResolution) {
+        if let Err(err) = remove_file(&self.resolution_path(&resolution.callback.id)) {
+            error!(
+                "error removing pending resolution {}: {err}",
+                resolution.callback.id
+            );
+        }
+
+        self.release();
+    }
+
+    /// The resolutions left by a previous run. Resolutions whose
+    /// notification was already stored are removed.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read the directory.
+    pub fn pending_resolutions(&self) -> io::Result<Vec<PendingResolution>> {
+        let mut resolutions = vec![];
+
+        for resolution in
+            read_json_files::<PendingResolution>(&self.config.store_dir.join(RESOLUTIONS_DIR))?
+        {
+            if self.delivery_path(&resolution.callback.id).exists() {
+                remove_file(&self.resolution_path(&resolution.callback.id))?;
+            } else {
+                resolutions.push(resolution);
+            }
+        }
+
+        Ok(resolutions)
+    }
+
+    /// Stores the notification, removes its pending resolution and starts
+    /// delivering it. It takes the place reserved for the notification.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't store the pending delivery.
+    pub fn notify(
+        self: &Arc<Self>,
+        callback: Callback,
+        event: NotificationEvent,
+        info_hash: &InfoHash,
+        name: Option<String>,
+        reason: Option<String>,
+    ) -> io::Result<()> {
+        let notification = Notification {
+            id: callback.id.clone(),
+            event,
+            info_hash: info_hash.to_hex_string(),
+            name,
+            reason,
+            created_at: unix_time(),
+        };
+
+        let resolution_path = self.resolution_path(&callback.id);
+
+        let delivery = Delivery {
+            id: callback.id,
+            url: callback.url,
+            key_id: callback.key_id,
+            body: serde_json::to_string(&notification)?,
+            attempts: 0,
+            next_attempt_at: notification.created_at,
+        };
+
+        if let Err(err) = write_json(&self.delivery_path(&delivery.id), &delivery) {
+            self.release();
+            return Err(err);
+        }
+
+        if let Err(err) = remove_file(&resolution_path) {
+            error!("error removing pending resolution {}: {err}", delivery.id);
+        }
+
+        tokio::spawn(self.clone().deliver(delivery));
+
+        Ok(())
+    }
+
+    /// Sends the notification until the receiver accepts it or the attempts
+    /// run out. The pending delivery is updated after every failed attempt.
+    async fn deliver(self: Arc<Self>, mut delivery: Delivery) {
+        let mut wait = Duration::from_secs(delivery.next_attempt_at.saturating_sub(unix_time()));
+
+        loop {
+            tokio::time::sleep(wait).await;
+
+            let Err(reason) = self.send(&delivery).await else {
+                break;
+            };
+
+            delivery.attempts += 1;
+
+            warn!(
+                "webhook delivery {} to {} failed (attempt {}): {reason}",
+                delivery.id, delivery.url, delivery.attempts
+            );
+
+            if delivery.attempts >= self.config.max_attempts {
+                error!(
+                    "webhook delivery {} to {} abandoned after {} attempts",
+                    delivery.id, delivery.url, delivery.attempts
+                );
+                break;
+            }
+
+            wait = backoff(&self.config, delivery.attempts);
+            delivery.next_attempt_at = unix_time() + wait.as_secs();
+
+            if let Err(err) = write_json(&self.delivery_path(&delivery.id), &delivery) {
+                error!("error saving webhook delivery {}: {err}", delivery.id);
+            }
+        }
+
+        if let Err(err) = remove_file(&self.delivery_path(&delivery.id)) {
+            error!("error removing webhook delivery {}: {err}", delivery.id);
+        }
+
+        self.release();
+    }
+
+    /// Sends one signed request. Only `2xx` responses are a success.
+    async fn send(&self, delivery: &Delivery) -> Result<(), String> {
+        let Some(secret) = self.config.keys.get(&delivery.key_id) else {
+            return Err(format!("unknown webhook key: {}", delivery.key_id));
+        };
+
+        self.check_callback_url(&delivery.url)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let timestamp = unix_time();
+
+        let response = self
+            .http_client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(ID_HEADER, &delivery.id)
+            .header(KEY_HEADER, &delivery.key_id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                signature(secret.as_bytes(), timestamp, &delivery.body),
+            )
+            .body(delivery.body.clone())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+
+        if response.status().is_success() {
+            info!("webhook delivery {} sent to {}", delivery.id, delivery.url);
+            Ok(())
+        } else {
+            Err(format!("unexpected response status {}", response.status()))
+        }
+    }
+
+    /// The pending deliveries in the directory.
+    fn pending_deliveries(&self) -> io::Result<Vec<Delivery>> {
+        read_json_files(&self.config.store_dir)
+    }
+
+    fn delivery_path(&self, id: &str) -> Utf8PathBuf {
+        self.config.store_dir.join(format!("{id}.json"))
+    }
+
+    fn resolution_path(&self, id: &str) -> Utf8PathBuf {
+        self.config
+            .store_dir
+            .join(RESOLUTIONS_DIR)
+            .join(format!("{id}.json"))
+    }
+}
+
+/// Writes the file. It's written to a temporary file first, so a crash
+/// never leaves a partial file.
+fn write_json(path: &Utf8PathBuf, value: &impl Serialize) -> io::Result<()> {
+    let temporary_path = path.with_extension("json.tmp");
+
+    fs::write(&temporary_path, serde_json::to_vec(value)?)?;
+    fs::rename(temporary_path, path)
+}
+
+fn remove_file(path: &Utf8PathBuf) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// The JSON files in the directory. Invalid files are skipped.
+fn read_json_files<T: DeserializeOwned>(dir: &Utf8PathBuf) -> io::Result<Vec<T>> {
+    let mut values = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            match serde_json::from_slice(&fs::read(&path)?) {
+                Ok(value) => values.push(value),
+                Err(err) => warn!("invalid webhook file {}: {err}", path.display()),
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// A new random notification id.
+#[must_use]
+pub fn new_id() -> String {
+    hex(&rand::random::<[u8; 16]>())
+}
+
+/// The value of the `X-Torrust-Webhook-Signature` header.
+///
+/// # Panics
+///
+/// Will panic if the HMAC rejects the secret, but it accepts keys of any
+/// length.
+#[must_use]
+pub fn signature(secret: &[u8], timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+/// The wait before the next attempt: the initial backoff doubled for every
+/// failed attempt, up to the maximum.
+fn backoff(config: &config::Webhooks, attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+
+    config
+        .initial_backoff
+        .saturating_mul(factor)
+        .min(config.max_backoff)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::routing::post;
+    use axum::Router;
+    use camino::Utf8PathBuf;
+    use hyper::HeaderMap;
+
+    use super::{
+        backoff, signature, Callback, CallbackError, Delivery, NotificationEvent,
+        PendingResolution, Webhooks, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+    use crate::bit_torrent::info_hash::InfoHash;
+    use crate::config;
+
+    fn config(store_dir: Utf8PathBuf) -> config::Webhooks {
+        config::Webhooks {
+            keys: HashMap::from([("pipeline".to_string(), "secret".to_string())]),
+            store_dir,
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            request_timeout: Duration::from_secs(5),
+            max_pending: 10,
+            allow_private_callbacks: true,
+        }
+    }
+
+    #[test]
+    fn the_signature_should_be_the_hmac_sha256_of_the_timestamp_and_the_body() {
+        assert_eq!(
+            signature(b"secret", 1_700_000_000, r#"{"id":"0123"}"#),
+            "sha256=2275c3df073e3fb4897be53bb9630dc744675b066871002c700a2d91c31898ea"
+        );
+    }
+
+    #[test]
+    fn the_backoff_should_double_up_to_the_maximum() {
+        let config = config("unused".into());
+
+        assert_eq!(backoff(&config, 1), Duration::from_secs(1));
+        assert_eq!(backoff(&config, 2), Duration::from_secs(2));
+        assert_eq!(backoff(&config, 3), Duration::from_secs(3));
+        assert_eq!(backoff(&config, 30), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn it_should_only_accept_http_callbacks_to_public_addresses() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let webhooks = Webhooks::start(config::Webhooks {
+            allow_private_callbacks: false,
+            ..config(store_dir)
+        })
+        .unwrap();
+
+        assert_eq!(
+            webhooks.check_callback_url("https://1.1.1.1/hook").await,
+            Ok(())
+        );
+        assert_eq!(
+            webhooks.check_callback_url("ftp://1.1.1.1/hook").await,
+            Err(CallbackError::InvalidUrl)
+        );
+        assert_eq!(
+            webhooks.check_callback_url("http://127.0.0.1/hook").await,
+            Err(CallbackError::AddressNotAllowed(
+                "127.0.0.1".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            webhooks.check_callback_url("http://[fe80::1]/hook").await,
+            Err(CallbackError::AddressNotAllowed("fe80::1".parse().unwrap()))
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_accepted_resolutions_until_the_notification_is_stored() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let webhooks = Webhooks::start(config(store_dir)).unwrap();
+        let info_hash = InfoHash::from_str("443c7602b4fde83d1154d6d9da48808418b181b6").unwrap();
+        let resolution = PendingResolution {
+            callback: Callback {
+                id: "0123".to_string(),
+                url: "http://127.0.0.1:1/hook".to_string(), // DevSkim: ignore DS137138
+                key_id: "pipeline".to_string(),
+            },
+            info_hash: info_hash.to_hex_string(),
+            query: Some("timeout=30".to_string()),
+        };
+
+        assert!(webhooks.try_reserve());
+        webhooks.accept(&resolution).unwrap();
+
+        assert_eq!(
+            webhooks.pending_resolutions().unwrap(),
+            vec![resolution.clone()]
+        );
+
+        webhooks
+            .notify(
+                resolution.callback,
+                NotificationEvent::Failed,
+                &info_hash,
+                None,
+                Some("timeout".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(webhooks.pending_resolutions().unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn it_should_resume_the_pending_deliveries() {
+        let received: Arc<Mutex<Vec<(HeaderMap, String)>>> = Arc::default();
+
+        let app = Router::new().route(
+            "/hook",
+            post({
+                let received = received.clone();
+                move |headers: HeaderMap, body: String| async move {
+                    received.lock().unwrap().push((headers, body));
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        let delivery = Delivery {
+            id: "0123".to_string(),
+            url: format!("http://{address}/hook"), // DevSkim: ignore DS137138
+            key_id: "pipeline".to_string(),
+            body: r#"{"id":"0123"}"#.to_string(),
+            attempts: 1,
+            next_attempt_at: 0,
+        };
+        std::fs::write(
+            store_dir.join("0123.json"),
+            serde_json::to_vec(&delivery).unwrap(),
+        )
+        .unwrap();
+
+        let _webhooks = Webhooks::start(config(store_dir.clone())).unwrap();
+
+        for _ in 0..50 {
+            if !store_dir.join("0123.json").exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        let timestamp: u64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(body, r#"{"id":"0123"}"#);
+        assert_eq!(
+            headers[SIGNATURE_HEADER],
+            signature(b"secret", timestamp, body).as_str()
+        );
+        assert!(!store_dir.join("0123.json").exists());
+    }
+}
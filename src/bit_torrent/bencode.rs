@@ -0,0 +1,283 @@
+//! Minimal [bencode](https://www.bittorrent.org/beps/bep_0003.html#bencoding)
+//! support for the peer wire and tracker messages, the torrent files and the
+//! test fixtures.
+//!
+//! The decoder is used on data from peers and trackers, so it must be safe for
+//! untrusted input: it never reads past the buffer and it rejects values
+//! nested more than 64 levels deep.
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+/// Maximum nesting of lists and dictionaries. The decoder is recursive, so
+/// deeper values are rejected before they exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Invalid bencode at byte {0}")]
+pub struct DecodeError(pub usize);
+
+/// A decoded value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    /// Returns the value for the key if this is a dictionary.
+    #[must_use]
+    pub fn get(&self, key: &[u8]) -> Option<&Value> {
+        match self {
+            Value::Dict(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Appends a byte string.
+pub fn encode_bytes(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(value.len().to_string().as_bytes());
+    out.push(b':');
+    out.extend_from_slice(value);
+}
+
+/// Appends an integer.
+pub fn encode_int(out: &mut Vec<u8>, value: i64) {
+    out.push(b'i');
+    out.extend_from_slice(value.to_string().as_bytes());
+    out.push(b'e');
+}
+
+/// Decodes the value at the beginning of the buffer. It returns the value and
+/// the number of bytes it takes, so the caller can read what follows it.
+///
+/// # Errors
+///
+/// Will return an error if the buffer does not start with a valid value.
+pub fn decode(buf: &[u8]) -> Result<(Value, usize), DecodeError> {
+    let mut decoder = Decoder::new(buf);
+    let value = decoder.value()?;
+    Ok((value, decoder.pos))
+}
+
+/// Returns the raw bytes of the value for the key in the dictionary at the
+/// beginning of the buffer. For example, the info dictionary of a torrent
+/// file, which is needed to calculate the info-hash.
+///
+/// # Errors
+///
+/// Will return an error if the buffer does not start with a valid dictionary.
+pub fn raw_dict_value<'a>(buf: &'a [u8], key: &[u8]) -> Result<Option<&'a [u8]>, DecodeError> {
+    let mut decoder = Decoder::new(buf);
+
+    decoder.expect(b'd')?;
+    decoder.depth = 1;
+
+    while decoder.peek()? != b'e' {
+        let current_key = decoder.bytes()?;
+        let start = decoder.pos;
+        decoder.value()?;
+
+        if current_key == key {
+            return Ok(Some(&buf[start..decoder.pos]));
+        }
+    }
+
+    Ok(None)
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Lists and dictionaries the decoder is in.
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Result<u8, DecodeError> {
+        self.buf.get(self.pos).copied().ok_or(DecodeError(self.pos))
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), DecodeError> {
+        if self.peek()? != byte {
+            return Err(DecodeError(self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, DecodeError> {
+        match self.peek()? {
+            b'i' => self.int().map(Value::Int),
+            b'l' => {
+                self.enter()?;
+                let mut list = Vec::new();
+                while self.peek()? != b'e' {
+                    list.push(self.value()?);
+                }
+                self.leave();
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.enter()?;
+                let mut dict = BTreeMap::new();
+                while self.peek()? != b'e' {
+                    let key = self.bytes()?.to_vec();
+                    dict.insert(key, self.value()?);
+                }
+                self.leave();
+                Ok(Value::Dict(dict))
+            }
+            b'0'..=b'9' => self.bytes().map(|bytes| Value::Bytes(bytes.to_vec())),
+            _ => Err(DecodeError(self.pos)),
+        }
+    }
+
+    /// Skips the start of a list or a dictionary.
+    fn enter(&mut self) -> Result<(), DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(DecodeError(self.pos));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Skips the end of a list or a dictionary.
+    fn leave(&mut self) {
+        self.depth -= 1;
+        self.pos += 1;
+    }
+
+    fn int(&mut self) -> Result<i64, DecodeError> {
+        self.expect(b'i')?;
+        let digits = self.until(b'e')?;
+        std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(DecodeError(self.pos))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let start = self.pos;
+        let len: usize = std::str::from_utf8(self.until(b':')?)
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or(DecodeError(start))?;
+
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or(DecodeError(self.pos))?;
+
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Returns the bytes until the delimiter and skips it.
+    fn until(&mut self, delimiter: u8) -> Result<&'a [u8], DecodeError> {
+        let start = self.pos;
+        let len = self.buf[start..]
+            .iter()
+            .position(|byte| *byte == delimiter)
+            .ok_or(DecodeError(start))?;
+
+        self.pos = start + len + 1;
+        Ok(&self.buf[start..start + len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{decode, encode_bytes, encode_int, raw_dict_value, DecodeError, Value, MAX_DEPTH};
+
+    #[test]
+    fn it_should_decode_nested_values() {
+        let (value, len) = decode(b"d1:ai-3e1:bl4:spamee").unwrap();
+
+        assert_eq!(
+            value,
+            Value::Dict(BTreeMap::from([
+                (b"a".to_vec(), Value::Int(-3)),
+                (
+                    b"b".to_vec(),
+                    Value::List(vec![Value::Bytes(b"spam".to_vec())])
+                ),
+            ]))
+        );
+        assert_eq!(len, 20);
+    }
+
+    #[test]
+    fn it_should_return_the_length_of_the_value_to_read_what_follows_it() {
+        let (value, len) = decode(b"d5:piecei0eeRAW DATA").unwrap();
+
+        assert_eq!(value.get(b"piece").and_then(Value::as_int), Some(0));
+        assert_eq!(len, 12);
+    }
+
+    #[test]
+    fn it_should_reject_truncated_values() {
+        assert_eq!(decode(b"5:spa"), Err(DecodeError(2)));
+        assert!(decode(b"d1:a").is_err());
+    }
+
+    #[test]
+    fn it_should_reject_values_nested_too_deeply() {
+        let deepest = [vec![b'l'; MAX_DEPTH], vec![b'e'; MAX_DEPTH]].concat();
+        let too_deep = vec![b'l'; 1024 * 1024];
+
+        assert!(decode(&deepest).is_ok());
+        assert_eq!(decode(&too_deep), Err(DecodeError(MAX_DEPTH)));
+        assert!(raw_dict_value(&[&b"d1:a"[..], &too_deep].concat(), b"a").is_err());
+    }
+
+    #[test]
+    fn it_should_return_the_raw_bytes_of_a_dictionary_value() {
+        let mut torrent = b"d8:announce3:url4:infod4:name1:xee".to_vec();
+
+        assert_eq!(
+            raw_dict_value(&torrent, b"info").unwrap(),
+            Some(&b"d4:name1:xe"[..])
+        );
+
+        torrent.clear();
+        torrent.push(b'd');
+        encode_bytes(&mut torrent, b"length");
+        encode_int(&mut torrent, 1);
+        torrent.push(b'e');
+
+        assert_eq!(raw_dict_value(&torrent, b"info").unwrap(), None);
+    }
+}
@@ -1,16 +1,34 @@
 use anyhow::Context;
+use async_trait::async_trait;
 use camino::Utf8PathBuf;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
+use tracing::{debug, warn};
 
 use bytes::Bytes;
+use futures_util::StreamExt;
 use librqbit::{
-    AddTorrent, AddTorrentOptions, AddTorrentResponse, ByteBufOwned, ListOnlyResponse, Session,
-    TorrentMetaV1Info,
+    api::TorrentIdOrHash,
+    dht::{Dht, Id20},
+    AddTorrent, AddTorrentOptions, AddTorrentResponse, ByteBufOwned, ListOnlyResponse,
+    ManagedTorrentState, Session, TorrentMetaV1Info,
 };
 
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::magnet::MagnetLink;
+use crate::bit_torrent::resolution_pool::{ResolutionPool, ResolutionPoolStats};
+use crate::bit_torrent::resolver::{
+    ManageTorrentError, MetadataResolver, Progress, ResolutionEvent, ResolveError, ResolveOptions,
+    ResolverStats, SessionManager, SessionStats, SessionTorrent, TorrentMetadata,
+};
 use crate::config::Client as ClientConfig;
 
+/// How often the number of peers found is reported.
+const PEERS_FOUND_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Error, Debug)]
 pub enum ResolveMagnetError {
     #[error("BitTorrent client session not started")]
@@ -19,12 +37,34 @@ pub enum ResolveMagnetError {
     AddedForDownloading,
     #[error("Torrent could not been added to the BitTorrent client")]
     NotAdded,
+    #[error("Torrent metadata could not be resolved in time")]
+    Timeout,
+    #[error("Too many torrents waiting to be resolved")]
+    QueueFull,
+}
+
+impl From<ResolveMagnetError> for ResolveError {
+    fn from(err: ResolveMagnetError) -> Self {
+        match err {
+            ResolveMagnetError::Timeout => ResolveError::Timeout,
+            ResolveMagnetError::QueueFull => ResolveError::QueueFull,
+            ResolveMagnetError::NotAdded => ResolveError::NotFound(err.to_string()),
+            ResolveMagnetError::NoSession | ResolveMagnetError::AddedForDownloading => {
+                ResolveError::Other(err.to_string())
+            }
+        }
+    }
 }
 
 pub struct Client {
     pub opt_session: Option<Arc<Session>>,
     pub output_dir: Utf8PathBuf,
     pub listen_port_range: Option<std::ops::Range<u16>>,
+    pub enable_dht: bool,
+    pub resolution_pool: ResolutionPool,
+    /// Number of resolutions in progress for each info-hash.
+    in_flight: Arc<Mutex<HashMap<InfoHash, usize>>>,
+    removed_torrents: Arc<AtomicU64>,
 }
 
 impl Client {
@@ -34,6 +74,13 @@ impl Client {
             opt_session: None,
             output_dir: config.session_output_dir,
             listen_port_range: config.listen_port_range,
+            enable_dht: config.enable_dht,
+            resolution_pool: ResolutionPool::new(
+                config.max_concurrent_resolutions,
+                config.max_queued_resolutions,
+            ),
+            in_flight: Arc::default(),
+            removed_torrents: Arc::default(),
         }
     }
 
@@ -42,7 +89,7 @@ impl Client {
     /// Will return an error if the session can't be created.
     pub async fn start_session(&mut self) -> Result<(), anyhow::Error> {
         let opts = librqbit::SessionOptions {
-            disable_dht: false, // DHT is needed to get the list of peers having the torrent.
+            disable_dht: !self.enable_dht,
             listen_port_range: self.listen_port_range.clone(),
             ..Default::default()
         };
@@ -58,28 +105,84 @@ impl Client {
 
     /// Return the torrent info and metainfo (torrent binary data) from the magnet link.
     ///
+    /// The trackers in the options are added to the magnet link. The timeout
+    /// includes the time waiting for a free slot in the resolution pool.
+    ///
+    /// Torrents are resolved in list-only mode, so they should not stay in
+    /// the session. If the session ends up managing the torrent anyway, it's
+    /// removed when the last resolution for the info-hash finishes or gives
+    /// up.
+    ///
     /// # Errors
     ///
     /// Will return an error if the torrent:
     ///
     /// - Can't be added in list-only mode to the `BitTorrent` client.
     /// - Was added for downloading. It shouldn't, it should be added in list-only mode.
+    /// - Can't be resolved before the timeout in the options.
+    /// - Can't wait for a free slot in the resolution pool because the queue
+    ///   is full.
     pub async fn resolve_magnet(
         &self,
-        magnet_link: String,
+        magnet_link: &MagnetLink,
+        options: &ResolveOptions,
     ) -> Result<(TorrentMetaV1Info<ByteBufOwned>, Bytes), ResolveMagnetError> {
         match &self.opt_session {
             Some(session) => {
-                let added = match session
-                    .add_torrent(
+                let info_hash = magnet_link.info_hash;
+                let _in_flight = self.start_resolution(session, info_hash);
+
+                let magnet_link = magnet_link
+                    .clone()
+                    .with_trackers(options.trackers.iter().cloned())
+                    .to_string();
+
+                let add_torrent = async {
+                    options.progress.report(ResolutionEvent::Queued);
+
+                    let _slot = self
+                        .resolution_pool
+                        .acquire()
+                        .await
+                        .map_err(|_queue_full| ResolveMagnetError::QueueFull)?;
+
+                    let add = session.add_torrent(
                         AddTorrent::from_url(&magnet_link),
                         Some(AddTorrentOptions {
                             list_only: true,
+                            initial_peers: Some(options.initial_peers.clone()),
                             ..Default::default()
                         }),
-                    )
-                    .await
-                {
+                    );
+
+                    // The session does not report its progress, so the peers
+                    // are counted with a separate DHT lookup.
+                    let Some(dht) = session
+                        .get_dht()
+                        .filter(|_| options.progress.is_enabled())
+                        .cloned()
+                    else {
+                        return Ok(add.await);
+                    };
+
+                    options.progress.report(ResolutionEvent::DhtLookupStarted);
+
+                    tokio::pin!(add);
+
+                    tokio::select! {
+                        added = &mut add => Ok(added),
+                        () = report_dht_peers(dht, info_hash, options.progress.clone()) => Ok(add.await),
+                    }
+                };
+
+                let result = match options.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, add_torrent)
+                        .await
+                        .map_err(|_elapsed| ResolveMagnetError::Timeout)??,
+                    None => add_torrent.await?,
+                };
+
+                let added = match result {
                     Ok(add_torrent_response) => add_torrent_response,
                     Err(_err) => return Err(ResolveMagnetError::NotAdded),
                 };
@@ -99,9 +202,398 @@ impl Client {
                     }
                 };
 
+                // The session checks the metadata hash.
+                options.progress.report(ResolutionEvent::Verified);
+
                 Ok((info, content))
             }
             None => Err(ResolveMagnetError::NoSession),
         }
     }
+
+    /// Returns the number of running and queued resolutions.
+    #[must_use]
+    pub fn resolution_pool_stats(&self) -> ResolutionPoolStats {
+        self.resolution_pool.stats()
+    }
+
+    /// Returns the number of torrents in the session and how many have been
+    /// removed after their resolution.
+    #[must_use]
+    pub fn session_stats(&self) -> SessionStats {
+        SessionStats {
+            torrents: self.opt_session.as_ref().map_or(0, |session| {
+                session.with_torrents(|torrents| torrents.count())
+            }),
+            removed_torrents: self.removed_torrents.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Removes the torrents managed by the session that are not being
+    /// resolved. They are left behind when a removal fails or when they are
+    /// added to the session by other means.
+    ///
+    /// Returns the number of removed torrents.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the in-flight resolutions lock is poisoned.
+    pub async fn sweep_orphan_torrents(&self) -> usize {
+        let Some(session) = &self.opt_session else {
+            return 0;
+        };
+
+        let orphans: Vec<Id20> = {
+            let in_flight = self.in_flight.lock().expect("in-flight lock poisoned");
+
+            session.with_torrents(|torrents| {
+                torrents
+                    .map(|(_, handle)| handle.info_hash())
+                    .filter(|info_hash| !in_flight.contains_key(&InfoHash(info_hash.0)))
+                    .collect()
+            })
+        };
+
+        let mut removed = 0;
+
+        for info_hash in orphans {
+            match session
+                .delete(TorrentIdOrHash::Hash(info_hash), false)
+                .await
+            {
+                Ok(()) => {
+                    removed += 1;
+                    self.removed_torrents.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) => warn!("error removing orphan torrent {info_hash:?}: {err}"),
+            }
+        }
+
+        removed
+    }
+
+    fn start_resolution(&self, session: &Arc<Session>, info_hash: InfoHash) -> InFlightResolution {
+        *self
+            .in_flight
+            .lock()
+            .expect("in-flight lock poisoned")
+            .entry(info_hash)
+            .or_insert(0) += 1;
+
+        InFlightResolution {
+            info_hash,
+            in_flight: self.in_flight.clone(),
+            session: session.clone(),
+            removed_torrents: self.removed_torrents.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl MetadataResolver for Client {
+    async fn resolve(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError> {
+        let (info, torrent_bytes) = self
+            .resolve_magnet(&MagnetLink::new(*info_hash), options)
+            .await?;
+
+        TorrentMetadata::from_info(*info_hash, &info, torrent_bytes)
+            .map_err(|err| ResolveError::Other(err.to_string()))
+    }
+
+    fn stats(&self) -> ResolverStats {
+        ResolverStats {
+            resolutions: Some(self.resolution_pool_stats()),
+            session: Some(self.session_stats()),
+        }
+    }
+
+    fn dht(&self) -> Option<Dht> {
+        self.opt_session
+            .as_ref()
+            .and_then(|session| session.get_dht().cloned())
+    }
+
+    fn session(&self) -> Option<&dyn SessionManager> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl SessionManager for Client {
+    fn list_torrents(&self) -> Result<Vec<SessionTorrent>, ManageTorrentError> {
+        let session = self
+            .opt_session
+            .as_ref()
+            .ok_or(ManageTorrentError::NoSession)?;
+
+        Ok(session.with_torrents(|torrents| {
+            torrents
+                .map(|(id, handle)| SessionTorrent {
+                    id,
+                    info_hash: InfoHash(handle.info_hash().0),
+                    name: handle
+                        .shared()
+                        .info
+                        .name
+                        .as_ref()
+                        .map(|name| String::from_utf8_lossy(name.as_ref()).to_string()),
+                    state: handle.with_state(ManagedTorrentState::name),
+                })
+                .collect()
+        }))
+    }
+
+    async fn remove_torrent(&self, info_hash: &InfoHash) -> Result<(), ManageTorrentError> {
+        let session = self
+            .opt_session
+            .as_ref()
+            .ok_or(ManageTorrentError::NoSession)?;
+
+        let id = TorrentIdOrHash::Hash(Id20::new(info_hash.bytes()));
+
+        if session.get(id).is_none() {
+            return Err(ManageTorrentError::NotManaged);
+        }
+
+        session
+            .delete(id, false)
+            .await
+            .map_err(ManageTorrentError::Session)
+    }
+}
+
+/// Counts the distinct peers the DHT finds for the torrent and reports the
+/// count when it changes, at most once per interval. It ends when the lookup
+/// ends.
+async fn report_dht_peers(dht: Dht, info_hash: InfoHash, progress: Progress) {
+    let Ok(mut peers) = dht.get_peers(Id20::new(info_hash.0), None) else {
+        return;
+    };
+
+    let mut found = HashSet::new();
+    let mut reported = 0;
+    let mut interval = tokio::time::interval(PEERS_FOUND_REPORT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            peer = peers.next() => match peer {
+                Some(peer) => {
+                    found.insert(peer);
+                }
+                None => break,
+            },
+            _ = interval.tick() => {
+                if found.len() != reported {
+                    reported = found.len();
+                    progress.report(ResolutionEvent::PeersFound { count: reported });
+                }
+            }
+        }
+    }
+
+    if found.len() != reported {
+        progress.report(ResolutionEvent::PeersFound { count: found.len() });
+    }
+}
+
+/// A resolution in progress. When the last resolution for an info-hash
+/// finishes, the torrent is removed from the session if it's there.
+struct InFlightResolution {
+    info_hash: InfoHash,
+    in_flight: Arc<Mutex<HashMap<InfoHash, usize>>>,
+    session: Arc<Session>,
+    removed_torrents: Arc<AtomicU64>,
+}
+
+impl Drop for InFlightResolution {
+    fn drop(&mut self) {
+        {
+            let mut in_flight = self.in_flight.lock().expect("in-flight lock poisoned");
+
+            let Some(count) = in_flight.get_mut(&self.info_hash) else {
+                return;
+            };
+
+            *count -= 1;
+
+            if *count > 0 {
+                return;
+            }
+
+            in_flight.remove(&self.info_hash);
+        }
+
+        let id = TorrentIdOrHash::Hash(Id20::new(self.info_hash.bytes()));
+
+        if self.session.get(id).is_none() {
+            return;
+        }
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let session = self.session.clone();
+        let removed_torrents = self.removed_torrents.clone();
+        let info_hash = self.info_hash;
+
+        runtime.spawn(async move {
+            match session.delete(id, false).await {
+                Ok(()) => {
+                    debug!("removed resolved torrent from the session: {info_hash}");
+                    removed_torrents.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) => warn!("error removing resolved torrent {info_hash}: {err}"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use camino::Utf8PathBuf;
+    use librqbit::api::TorrentIdOrHash;
+    use librqbit::dht::Id20;
+    use librqbit::{AddTorrent, AddTorrentOptions, Session};
+    use tempfile::TempDir;
+
+    use super::Client;
+    use crate::bit_torrent::info_hash::InfoHash;
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::resolver::{MetadataResolver, ResolveOptions};
+    use crate::bit_torrent::test_peer::TestPeer;
+    use crate::config::{Client as ClientConfig, ResolverBackend};
+
+    async fn start_client() -> (Client, TempDir) {
+        let session_dir = tempfile::tempdir().unwrap();
+        let mut client = Client::new(ClientConfig {
+            listen_port_range: None,
+            session_output_dir: Utf8PathBuf::from_path_buf(session_dir.path().to_path_buf())
+                .unwrap(),
+            enable_dht: false,
+            resolver_backend: ResolverBackend::Librqbit,
+            max_concurrent_resolutions: 1,
+            max_queued_resolutions: 1,
+            orphan_sweep_interval: Duration::from_mins(10),
+        });
+        client.start_session().await.unwrap();
+
+        (client, session_dir)
+    }
+
+    fn session(client: &Client) -> Arc<Session> {
+        client.opt_session.clone().unwrap()
+    }
+
+    /// Adds a paused torrent to the session, like the ones left behind by
+    /// the resolutions.
+    async fn add_torrent(client: &Client, name: &str) -> InfoHash {
+        let torrent = sample_torrent(name, b"Hello, World!");
+
+        session(client)
+            .add_torrent(
+                AddTorrent::from_bytes(torrent.torrent_bytes.clone()),
+                Some(AddTorrentOptions {
+                    paused: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        torrent.info_hash
+    }
+
+    fn is_managed(client: &Client, info_hash: &InfoHash) -> bool {
+        session(client)
+            .get(TorrentIdOrHash::Hash(Id20::new(info_hash.bytes())))
+            .is_some()
+    }
+
+    /// Waits for the removal spawned when the last resolution finishes.
+    async fn wait_until_removed(client: &Client, info_hash: &InfoHash) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while is_managed(client, info_hash) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the torrent should be removed from the session");
+    }
+
+    #[tokio::test]
+    async fn it_should_remove_the_torrent_from_the_session_when_the_resolution_finishes() {
+        let (client, _session_dir) = start_client().await;
+        let info_hash = add_torrent(&client, "sample.txt").await;
+
+        let resolution = client.start_resolution(&session(&client), info_hash);
+        drop(resolution);
+
+        wait_until_removed(&client, &info_hash).await;
+        assert_eq!(client.session_stats().removed_torrents, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_torrent_in_the_session_until_the_last_resolution_finishes() {
+        let (client, _session_dir) = start_client().await;
+        let info_hash = add_torrent(&client, "sample.txt").await;
+
+        let first = client.start_resolution(&session(&client), info_hash);
+        let second = client.start_resolution(&session(&client), info_hash);
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(is_managed(&client, &info_hash));
+        assert_eq!(client.session_stats().removed_torrents, 0);
+
+        drop(second);
+
+        wait_until_removed(&client, &info_hash).await;
+        assert_eq!(client.session_stats().removed_torrents, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_sweep_the_torrents_that_are_not_being_resolved() {
+        let (client, _session_dir) = start_client().await;
+        let orphan = add_torrent(&client, "orphan.txt").await;
+        let resolving = add_torrent(&client, "resolving.txt").await;
+        let _resolution = client.start_resolution(&session(&client), resolving);
+
+        let removed = client.sweep_orphan_torrents().await;
+
+        assert_eq!(removed, 1);
+        assert!(!is_managed(&client, &orphan));
+        assert!(is_managed(&client, &resolving));
+        assert_eq!(client.session_stats().removed_torrents, 1);
+        assert_eq!(client.session_stats().torrents, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_resolve_the_metadata_from_an_initial_peer() {
+        let (client, _session_dir) = start_client().await;
+
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+        let options = ResolveOptions {
+            timeout: Some(Duration::from_secs(10)),
+            initial_peers: vec![peer.address()],
+            ..Default::default()
+        };
+
+        let metadata = client.resolve(&torrent.info_hash, &options).await.unwrap();
+
+        assert_eq!(metadata.info_hash, torrent.info_hash);
+        assert_eq!(metadata.name, torrent.name);
+        assert_eq!(metadata.files, torrent.files);
+        assert_eq!(peer.served_pieces(), 1);
+    }
 }
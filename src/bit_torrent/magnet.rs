@@ -0,0 +1,175 @@
+//! Magnet links used to ask the `BitTorrent` client for a torrent.
+//!
+//! See [BEP 9. Extension for Peers to Send Metadata Files](https://www.bittorrent.org/beps/bep_0009.html)
+//! for the magnet URI format, and [BEP 52](https://www.bittorrent.org/beps/bep_0052.html)
+//! for the v2 info-hashes of hybrid torrents.
+use std::fmt;
+
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::resolver::TorrentMetadata;
+
+/// A magnet link for a v1 info-hash with an optional list of trackers (`tr`).
+///
+/// Links shared with users can also have the torrent name (`dn`), the total
+/// length (`xl`) and, for hybrid torrents, the v2 info-hash (`btmh`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagnetLink {
+    pub info_hash: InfoHash,
+    /// The SHA-256 info-hash of hybrid torrents.
+    pub v2_info_hash: Option<[u8; 32]>,
+    pub name: Option<String>,
+    pub length: Option<u64>,
+    pub trackers: Vec<String>,
+}
+
+impl MagnetLink {
+    #[must_use]
+    pub fn new(info_hash: InfoHash) -> Self {
+        Self {
+            info_hash,
+            v2_info_hash: None,
+            name: None,
+            length: None,
+            trackers: vec![],
+        }
+    }
+
+    /// A magnet link with the name, the total length and the v2 info-hash
+    /// of the torrent.
+    #[must_use]
+    pub fn for_torrent(metadata: &TorrentMetadata) -> Self {
+        Self {
+            v2_info_hash: metadata.v2_info_hash(),
+            name: metadata.name.clone(),
+            length: Some(metadata.total_length()),
+            ..Self::new(metadata.info_hash)
+        }
+    }
+
+    /// The v2 info-hash as a hexadecimal [multihash](https://multiformats.io/multihash/):
+    /// the SHA-256 code (`0x12`) and the digest length (`0x20`) before the
+    /// digest.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the digest can't be written in hexadecimal, which can't
+    /// happen with a 32-byte digest.
+    #[must_use]
+    pub fn v2_multihash(&self) -> Option<String> {
+        self.v2_info_hash.map(|hash| {
+            let mut hex = [0u8; 64];
+            binascii::bin2hex(&hash, &mut hex).expect("the buffer fits the hexadecimal digest");
+            format!(
+                "1220{}",
+                std::str::from_utf8(&hex).expect("hexadecimal digits are valid UTF-8")
+            )
+        })
+    }
+
+    /// Appends trackers to the magnet link skipping the ones already included.
+    #[must_use]
+    pub fn with_trackers<I, S>(mut self, trackers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for tracker in trackers {
+            let tracker = tracker.into();
+            if !self.trackers.contains(&tracker) {
+                self.trackers.push(tracker);
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for MagnetLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "magnet:?xt=urn:btih:{}", self.info_hash.to_hex_string())?;
+
+        if let Some(multihash) = self.v2_multihash() {
+            write!(f, "&xt=urn:btmh:{multihash}")?;
+        }
+
+        if let Some(name) = &self.name {
+            write!(f, "&dn=")?;
+            write_encoded(f, name)?;
+        }
+
+        if let Some(length) = self.length {
+            write!(f, "&xl={length}")?;
+        }
+
+        for tracker in &self.trackers {
+            write!(f, "&tr=")?;
+            write_encoded(f, tracker)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the percent-encoded value. Spaces are encoded as `%20`, because
+/// not every client decodes `+` in magnet links.
+fn write_encoded(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    for encoded in form_urlencoded::byte_serialize(value.as_bytes()) {
+        if encoded == "+" {
+            write!(f, "%20")?;
+        } else {
+            write!(f, "{encoded}")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::MagnetLink;
+    use crate::bit_torrent::info_hash::InfoHash;
+
+    fn info_hash() -> InfoHash {
+        InfoHash::from_str("443c7602b4fde83d1154d6d9da48808418b181b6").unwrap() // DevSkim: ignore DS173237
+    }
+
+    #[test]
+    fn it_should_only_include_the_info_hash_by_default() {
+        assert_eq!(
+            MagnetLink::new(info_hash()).to_string(),
+            "magnet:?xt=urn:btih:443c7602b4fde83d1154d6d9da48808418b181b6" // DevSkim: ignore DS173237
+        );
+    }
+
+    #[test]
+    fn it_should_include_url_encoded_trackers_without_duplicates() {
+        let magnet_link = MagnetLink::new(info_hash()).with_trackers([
+            "udp://tracker.torrust-demo.com:6969/announce",
+            "udp://tracker.torrust-demo.com:6969/announce",
+        ]);
+
+        assert_eq!(
+            magnet_link.to_string(),
+            "magnet:?xt=urn:btih:443c7602b4fde83d1154d6d9da48808418b181b6&tr=udp%3A%2F%2Ftracker.torrust-demo.com%3A6969%2Fannounce" // DevSkim: ignore DS173237
+        );
+    }
+
+    #[test]
+    fn it_should_include_the_name_the_length_and_the_v2_info_hash() {
+        let magnet_link = MagnetLink {
+            v2_info_hash: Some([0xab; 32]),
+            name: Some("Ubuntu 23.04 & more".to_string()),
+            length: Some(4_932_407_296),
+            ..MagnetLink::new(info_hash())
+        };
+
+        assert_eq!(
+            magnet_link.to_string(),
+            format!(
+                "magnet:?xt=urn:btih:443c7602b4fde83d1154d6d9da48808418b181b6&xt=urn:btmh:1220{}&dn=Ubuntu%2023.04%20%26%20more&xl=4932407296", // DevSkim: ignore DS173237
+                "ab".repeat(32)
+            )
+        );
+    }
+}
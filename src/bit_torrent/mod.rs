@@ -1,2 +1,14 @@
+pub mod bencode;
 pub mod client;
 pub mod info_hash;
+pub mod magnet;
+pub mod peer_discovery;
+pub mod peer_wire;
+pub mod resolution_pool;
+pub mod resolver;
+pub mod swarm;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_peer;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_tracker;
+pub mod trackers;
@@ -0,0 +1,621 @@
+//! Finds the peers that have a torrent and checks whether they can send its
+//! metadata. It's used for diagnostics.
+//!
+//! Peers come from three sources:
+//!
+//! - The DHT of the resolver, if it uses one.
+//! - Announces to the trackers, the same ones used for the resolutions.
+//! - [BEP 11](https://www.bittorrent.org/beps/bep_0011.html) `ut_pex`
+//!   messages sent by the peers found in the DHT and the trackers.
+//!
+//! Only peers on public addresses are kept: anybody can announce any address
+//! to the DHT and the trackers, or send it with `ut_pex`, and the service must
+//! not be used to probe its own network.
+//!
+//! Each peer is probed with a handshake to know if it supports the
+//! [BEP 9](https://www.bittorrent.org/beps/bep_0009.html) `ut_metadata`
+//! extension. Only a few peers are probed at the same time. Results are
+//! cached for a while, and concurrent lookups for the same torrent share the
+//! same work, so repeated requests don't flood the DHT and the trackers.
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+
+use crate::api::address;
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::peer_wire::{self, invalid_data, EXTENDED_HANDSHAKE_ID};
+use crate::bit_torrent::swarm::{http_tracker, udp_tracker, ScrapeError};
+use crate::config::Peers as PeersConfig;
+
+/// Time a peer has to answer the handshakes.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Time a peer that supports `ut_pex` has to send its peers after the
+/// handshakes.
+const PEX_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Number of peers probed at the same time.
+const MAX_CONCURRENT_PROBES: usize = 16;
+
+/// The `ut_metadata` message ID we announce. We never receive metadata.
+const UT_METADATA_ID: u8 = 1;
+
+/// The `ut_pex` message ID we announce.
+const UT_PEX_ID: u8 = 2;
+
+/// The port sent in the tracker announces. Nobody can connect to it, the
+/// announces are only used to get the peers.
+const ANNOUNCE_PORT: u16 = 6881;
+
+/// Where the peer was found.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerSource {
+    Dht,
+    Tracker,
+    Pex,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPeer {
+    #[schema(value_type = String, example = "203.0.113.7:51413")]
+    pub address: SocketAddr,
+    pub source: PeerSource,
+    /// Whether the peer supports the `ut_metadata` extension. It's `None` if
+    /// the peer could not be reached or does not have the torrent.
+    pub supports_ut_metadata: Option<bool>,
+}
+
+/// The result of a lookup.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct PeerLookup {
+    #[schema(value_type = String, example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: InfoHash,
+    pub peers: Vec<DiscoveredPeer>,
+}
+
+/// What a probe learned about a peer.
+#[derive(Debug, Default)]
+struct Probe {
+    supports_ut_metadata: Option<bool>,
+    /// The peers the peer sent in a `ut_pex` message.
+    pex_peers: Vec<SocketAddr>,
+}
+
+type PendingLookup = Arc<OnceCell<Arc<PeerLookup>>>;
+
+pub struct PeerDiscovery {
+    config: PeersConfig,
+    peer_id: [u8; 20],
+    http_client: reqwest::Client,
+    recent_lookups: Mutex<HashMap<InfoHash, (Instant, Arc<PeerLookup>)>>,
+    pending_lookups: Mutex<HashMap<InfoHash, PendingLookup>>,
+}
+
+impl PeerDiscovery {
+    #[must_use]
+    pub fn new(config: PeersConfig) -> Self {
+        Self {
+            config,
+            peer_id: librqbit::generate_peer_id().0,
+            http_client: reqwest::Client::new(),
+            recent_lookups: Mutex::default(),
+            pending_lookups: Mutex::default(),
+        }
+    }
+
+    /// Returns the peers found in the DHT stream and the trackers during the
+    /// configured lookup time, and the ones they send with `ut_pex`. It
+    /// returns a recent result for the same info-hash if there is one, or
+    /// waits for the lookup in progress.
+    ///
+    /// The DHT stream is only started if this call runs the lookup.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a cache lock is poisoned.
+    pub async fn lookup(
+        &self,
+        info_hash: &InfoHash,
+        trackers: &[String],
+        dht_peers: impl FnOnce() -> BoxStream<'static, SocketAddr>,
+    ) -> Arc<PeerLookup> {
+        if let Some(lookup) = self.recent_lookup(info_hash) {
+            return lookup;
+        }
+
+        let pending = self
+            .pending_lookups
+            .lock()
+            .expect("lock should not be poisoned")
+            .entry(*info_hash)
+            .or_default()
+            .clone();
+
+        // If the caller running the lookup goes away, the next one waiting
+        // runs it with its own DHT stream.
+        let lookup = pending
+            .get_or_init(|| self.find_peers(info_hash, trackers, dht_peers()))
+            .await
+            .clone();
+
+        let mut pending_lookups = self
+            .pending_lookups
+            .lock()
+            .expect("lock should not be poisoned");
+        if pending_lookups
+            .get(info_hash)
+            .is_some_and(|current| Arc::ptr_eq(current, &pending))
+        {
+            pending_lookups.remove(info_hash);
+
+            let mut recent_lookups = self
+                .recent_lookups
+                .lock()
+                .expect("lock should not be poisoned");
+            recent_lookups
+                .retain(|_, (looked_up_at, _)| looked_up_at.elapsed() < self.config.cache_ttl);
+            recent_lookups.insert(*info_hash, (Instant::now(), lookup.clone()));
+        }
+
+        lookup
+    }
+
+    async fn find_peers(
+        &self,
+        info_hash: &InfoHash,
+        trackers: &[String],
+        mut dht_peers: BoxStream<'static, SocketAddr>,
+    ) -> Arc<PeerLookup> {
+        let mut dht_addresses: Vec<SocketAddr> = Vec::new();
+        let collect_dht_peers = async {
+            while let Some(address) = dht_peers.next().await {
+                if !dht_addresses.contains(&address) {
+                    dht_addresses.push(address);
+                }
+                if dht_addresses.len() >= self.config.max_peers {
+                    break;
+                }
+            }
+        };
+        let announces = join_all(trackers.iter().map(|tracker| async {
+            tokio::time::timeout(
+                self.config.lookup_timeout,
+                self.announce(tracker, info_hash),
+            )
+            .await
+            .unwrap_or_default()
+        }));
+
+        let (_, tracker_addresses) = tokio::join!(
+            tokio::time::timeout(self.config.lookup_timeout, collect_dht_peers),
+            announces
+        );
+
+        let mut found: Vec<(SocketAddr, PeerSource)> = Vec::new();
+        self.add_peers(&mut found, dht_addresses, PeerSource::Dht);
+        self.add_peers(
+            &mut found,
+            tracker_addresses.into_iter().flatten().collect(),
+            PeerSource::Tracker,
+        );
+
+        let probes = self.probe_all(&found, info_hash, true).await;
+
+        let pex_addresses: Vec<SocketAddr> = probes
+            .iter()
+            .flat_map(|probe| probe.pex_peers.iter().copied())
+            .collect();
+        let known = found.len();
+        self.add_peers(&mut found, pex_addresses, PeerSource::Pex);
+
+        let pex_probes = self.probe_all(&found[known..], info_hash, false).await;
+
+        Arc::new(PeerLookup {
+            info_hash: *info_hash,
+            peers: found
+                .into_iter()
+                .zip(probes.into_iter().chain(pex_probes))
+                .map(|((address, source), probe)| DiscoveredPeer {
+                    address,
+                    source,
+                    supports_ut_metadata: probe.supports_ut_metadata,
+                })
+                .collect(),
+        })
+    }
+
+    /// Adds the addresses that are not in the list yet, up to the maximum
+    /// number of peers. Addresses that are not public are skipped, unless
+    /// they are allowed in the configuration.
+    fn add_peers(
+        &self,
+        found: &mut Vec<(SocketAddr, PeerSource)>,
+        addresses: Vec<SocketAddr>,
+        source: PeerSource,
+    ) {
+        for address in addresses {
+            if found.len() >= self.config.max_peers {
+                return;
+            }
+            if !self.config.allow_private_peers && !address::is_public(address.ip()) {
+                continue;
+            }
+            if !found.iter().any(|(known, _)| *known == address) {
+                found.push((address, source));
+            }
+        }
+    }
+
+    /// Probes the peers, a few at a time. The results are in the same order
+    /// as the peers.
+    async fn probe_all(
+        &self,
+        peers: &[(SocketAddr, PeerSource)],
+        info_hash: &InfoHash,
+        exchange_peers: bool,
+    ) -> Vec<Probe> {
+        let addresses: Vec<SocketAddr> = peers.iter().map(|(address, _)| *address).collect();
+        let (info_hash, peer_id) = (*info_hash, self.peer_id);
+
+        stream::iter(addresses)
+            .map(move |address| probe(address, info_hash, peer_id, exchange_peers))
+            .buffered(MAX_CONCURRENT_PROBES)
+            .collect()
+            .await
+    }
+
+    /// Returns the peers the tracker knows. Failures are ignored: the
+    /// lookup uses the other sources.
+    async fn announce(&self, tracker: &str, info_hash: &InfoHash) -> Vec<SocketAddr> {
+        let result = if tracker.starts_with("udp://") {
+            udp_tracker::announce(tracker, info_hash, &self.peer_id, ANNOUNCE_PORT).await
+        } else if tracker.starts_with("http://") || tracker.starts_with("https://") {
+            http_tracker::announce(
+                &self.http_client,
+                tracker,
+                info_hash,
+                &self.peer_id,
+                ANNOUNCE_PORT,
+            )
+            .await
+        } else {
+            Err(ScrapeError::UnsupportedTracker(tracker.to_string()))
+        };
+
+        result.unwrap_or_default()
+    }
+
+    fn recent_lookup(&self, info_hash: &InfoHash) -> Option<Arc<PeerLookup>> {
+        let recent_lookups = self
+            .recent_lookups
+            .lock()
+            .expect("lock should not be poisoned");
+
+        recent_lookups
+            .get(info_hash)
+            .filter(|(looked_up_at, _)| looked_up_at.elapsed() < self.config.cache_ttl)
+            .map(|(_, lookup)| lookup.clone())
+    }
+}
+
+/// Returns whether the peer supports `ut_metadata`, or `None` if it can't be
+/// reached or does not have the torrent. If `exchange_peers` is set and the
+/// peer supports `ut_pex`, it also waits a bit for the peers it sends.
+async fn probe(
+    address: SocketAddr,
+    info_hash: InfoHash,
+    peer_id: [u8; 20],
+    exchange_peers: bool,
+) -> Probe {
+    let handshakes = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        exchange_handshakes(address, info_hash, peer_id, exchange_peers),
+    );
+
+    let Ok(Ok((supports_ut_metadata, pex_stream))) = handshakes.await else {
+        return Probe::default();
+    };
+
+    let pex_peers = match pex_stream {
+        Some(mut stream) => tokio::time::timeout(PEX_TIMEOUT, read_pex_peers(&mut stream))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    Probe {
+        supports_ut_metadata: Some(supports_ut_metadata),
+        pex_peers,
+    }
+}
+
+/// Returns whether the peer supports `ut_metadata` and, if we want its
+/// peers and it supports `ut_pex`, the connection to receive them.
+async fn exchange_handshakes(
+    address: SocketAddr,
+    info_hash: InfoHash,
+    peer_id: [u8; 20],
+    exchange_peers: bool,
+) -> io::Result<(bool, Option<TcpStream>)> {
+    let mut stream = TcpStream::connect(address).await?;
+
+    stream
+        .write_all(&peer_wire::handshake(&info_hash, &peer_id))
+        .await?;
+
+    let handshake = peer_wire::read_handshake(&mut stream).await?;
+    if handshake.info_hash != info_hash {
+        return Err(invalid_data("the peer answered with another info-hash"));
+    }
+    if !handshake.supports_extensions {
+        return Ok((false, None));
+    }
+
+    let ut_pex_id = exchange_peers.then_some(UT_PEX_ID);
+
+    stream
+        .write_all(&peer_wire::extended_handshake(
+            UT_METADATA_ID,
+            ut_pex_id,
+            None,
+        ))
+        .await?;
+
+    loop {
+        let Some(message) = peer_wire::read_message(&mut stream).await? else {
+            continue;
+        };
+
+        if let Some((EXTENDED_HANDSHAKE_ID, payload)) = peer_wire::as_extended(&message) {
+            let handshake = peer_wire::parse_extended_handshake(payload)?;
+            let pex_stream = (exchange_peers && handshake.ut_pex_id.is_some()).then_some(stream);
+            return Ok((handshake.ut_metadata_id.is_some(), pex_stream));
+        }
+    }
+}
+
+/// Returns the peers in the first `ut_pex` message.
+async fn read_pex_peers(stream: &mut TcpStream) -> io::Result<Vec<SocketAddr>> {
+    loop {
+        let Some(message) = peer_wire::read_message(stream).await? else {
+            continue;
+        };
+
+        if let Some((UT_PEX_ID, payload)) = peer_wire::as_extended(&message) {
+            return peer_wire::parse_ut_pex(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::stream::{self, StreamExt};
+
+    use super::{DiscoveredPeer, PeerDiscovery, PeerSource};
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::swarm::ScrapeCounts;
+    use crate::bit_torrent::test_peer::TestPeer;
+    use crate::bit_torrent::test_tracker::TestTracker;
+    use crate::config::Peers as PeersConfig;
+
+    fn peer_discovery() -> PeerDiscovery {
+        PeerDiscovery::new(PeersConfig {
+            lookup_timeout: Duration::from_secs(1),
+            max_peers: 10,
+            cache_ttl: Duration::from_mins(1),
+            allow_private_peers: true,
+        })
+    }
+
+    /// An address where nobody is listening.
+    fn closed_address() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_should_probe_the_peers_found() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+        let unreachable = closed_address();
+
+        let lookup = peer_discovery()
+            .lookup(&torrent.info_hash, &[], || {
+                stream::iter(vec![peer.address(), unreachable, peer.address()]).boxed()
+            })
+            .await;
+
+        assert_eq!(
+            lookup.peers,
+            vec![
+                DiscoveredPeer {
+                    address: peer.address(),
+                    source: PeerSource::Dht,
+                    supports_ut_metadata: Some(true),
+                },
+                DiscoveredPeer {
+                    address: unreachable,
+                    source: PeerSource::Dht,
+                    supports_ut_metadata: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_announce_to_the_trackers() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+        let counts = ScrapeCounts {
+            seeders: 1,
+            leechers: 0,
+            completed: None,
+        };
+        let tracker = TestTracker::start_with_peers(counts, vec![peer.address()])
+            .await
+            .unwrap();
+
+        let lookup = peer_discovery()
+            .lookup(&torrent.info_hash, &[tracker.url()], || {
+                stream::empty().boxed()
+            })
+            .await;
+
+        assert_eq!(
+            lookup.peers,
+            vec![DiscoveredPeer {
+                address: peer.address(),
+                source: PeerSource::Tracker,
+                supports_ut_metadata: Some(true),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_probe_the_peers_received_with_pex() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let other_peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+        let peer = TestPeer::start_with_pex(vec![torrent.clone()], vec![other_peer.address()])
+            .await
+            .unwrap();
+
+        let lookup = peer_discovery()
+            .lookup(&torrent.info_hash, &[], || {
+                stream::iter(vec![peer.address()]).boxed()
+            })
+            .await;
+
+        assert_eq!(
+            lookup.peers,
+            vec![
+                DiscoveredPeer {
+                    address: peer.address(),
+                    source: PeerSource::Dht,
+                    supports_ut_metadata: Some(true),
+                },
+                DiscoveredPeer {
+                    address: other_peer.address(),
+                    source: PeerSource::Pex,
+                    supports_ut_metadata: Some(true),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_not_probe_peers_on_private_addresses() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let other_peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+        let peer = TestPeer::start_with_pex(vec![torrent.clone()], vec![other_peer.address()])
+            .await
+            .unwrap();
+        let peer_discovery = PeerDiscovery::new(PeersConfig {
+            allow_private_peers: false,
+            ..peer_discovery().config
+        });
+
+        let lookup = peer_discovery
+            .lookup(&torrent.info_hash, &[], || {
+                stream::iter(vec![peer.address()]).boxed()
+            })
+            .await;
+
+        assert!(lookup.peers.is_empty());
+    }
+
+    #[test]
+    fn it_should_skip_the_private_addresses_received_with_pex() {
+        let peer_discovery = PeerDiscovery::new(PeersConfig {
+            allow_private_peers: false,
+            ..peer_discovery().config
+        });
+        let public: SocketAddr = "1.1.1.1:51413".parse().unwrap();
+        let mut found = Vec::new();
+
+        peer_discovery.add_peers(
+            &mut found,
+            vec![
+                "127.0.0.1:51413".parse().unwrap(),
+                "192.168.1.10:51413".parse().unwrap(),
+                public,
+            ],
+            PeerSource::Pex,
+        );
+
+        assert_eq!(found, vec![(public, PeerSource::Pex)]);
+    }
+
+    #[tokio::test]
+    async fn it_should_share_the_lookups_in_progress_for_the_same_torrent() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer_discovery = peer_discovery();
+
+        let (first, second) = tokio::join!(
+            peer_discovery.lookup(&torrent.info_hash, &[], || stream::iter(vec![
+                closed_address()
+            ])
+            .boxed()),
+            peer_discovery.lookup(&torrent.info_hash, &[], || stream::iter(vec![
+                closed_address()
+            ])
+            .boxed()),
+        );
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.peers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_reuse_recent_lookups() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer_discovery = peer_discovery();
+        let address = closed_address();
+
+        peer_discovery
+            .lookup(&torrent.info_hash, &[], move || {
+                stream::iter(vec![address]).boxed()
+            })
+            .await;
+
+        let lookup = peer_discovery
+            .lookup(&torrent.info_hash, &[], || {
+                unreachable!("the DHT should not be searched again")
+            })
+            .await;
+
+        assert_eq!(lookup.peers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_looking_for_peers_after_the_lookup_timeout() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let lookup = tokio::time::timeout(
+            Duration::from_secs(5),
+            peer_discovery().lookup(&torrent.info_hash, &[], || stream::pending().boxed()),
+        )
+        .await
+        .unwrap();
+
+        assert!(lookup.peers.is_empty());
+    }
+}
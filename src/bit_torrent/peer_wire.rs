@@ -0,0 +1,411 @@
+//! The parts of the peer wire protocol needed to exchange torrent metadata:
+//!
+//! - The [BEP 3](https://www.bittorrent.org/beps/bep_0003.html) handshake and
+//!   message framing.
+//! - The [BEP 10](https://www.bittorrent.org/beps/bep_0010.html) extension
+//!   protocol handshake.
+//! - The [BEP 9](https://www.bittorrent.org/beps/bep_0009.html) `ut_metadata`
+//!   messages.
+//! - The [BEP 11](https://www.bittorrent.org/beps/bep_0011.html) `ut_pex`
+//!   messages, to learn other peers from the ones we connect to.
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::bit_torrent::bencode::{self, Value};
+use crate::bit_torrent::info_hash::InfoHash;
+
+const PROTOCOL: &[u8] = b"BitTorrent protocol";
+
+pub const HANDSHAKE_LEN: usize = 1 + 19 + 8 + 20 + 20;
+
+pub const EXTENDED_MESSAGE_ID: u8 = 20;
+pub const EXTENDED_HANDSHAKE_ID: u8 = 0;
+
+/// Size of the metadata pieces. Only the last one can be smaller.
+pub const METADATA_PIECE_LEN: usize = 16384;
+
+/// Messages bigger than this are rejected. Metadata pieces are much smaller.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// The handshake sent by the other peer.
+pub struct Handshake {
+    pub info_hash: InfoHash,
+    pub supports_extensions: bool,
+}
+
+/// The extension protocol handshake sent by the other peer.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExtendedHandshake {
+    /// The ID the peer wants in the `ut_metadata` messages it receives.
+    pub ut_metadata_id: Option<u8>,
+    /// The ID the peer wants in the `ut_pex` messages it receives.
+    pub ut_pex_id: Option<u8>,
+    pub metadata_size: Option<usize>,
+}
+
+/// A `ut_metadata` message.
+#[derive(Debug, PartialEq, Eq)]
+pub enum UtMetadata<'a> {
+    Request { piece: usize },
+    Data { piece: usize, data: &'a [u8] },
+    Reject { piece: usize },
+}
+
+/// Builds the handshake. It announces support for the extension protocol.
+#[must_use]
+pub fn handshake(info_hash: &InfoHash, peer_id: &[u8; 20]) -> Vec<u8> {
+    let mut reserved = [0u8; 8];
+    reserved[5] |= 0x10;
+
+    let mut handshake = Vec::with_capacity(HANDSHAKE_LEN);
+    handshake.push(19);
+    handshake.extend_from_slice(PROTOCOL);
+    handshake.extend_from_slice(&reserved);
+    handshake.extend_from_slice(&info_hash.0);
+    handshake.extend_from_slice(peer_id);
+    handshake
+}
+
+/// Reads the handshake sent by the other peer.
+///
+/// # Errors
+///
+/// Will return an error if it can't read the handshake or it's not a
+/// `BitTorrent` handshake.
+pub async fn read_handshake<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<Handshake> {
+    let mut handshake = [0u8; HANDSHAKE_LEN];
+    stream.read_exact(&mut handshake).await?;
+
+    if usize::from(handshake[0]) != PROTOCOL.len() || &handshake[1..20] != PROTOCOL {
+        return Err(invalid_data("not a BitTorrent handshake"));
+    }
+
+    Ok(Handshake {
+        info_hash: InfoHash::from_bytes(&handshake[28..48]),
+        supports_extensions: handshake[25] & 0x10 != 0,
+    })
+}
+
+/// Reads the next message. It returns `None` for keep-alive messages.
+///
+/// # Errors
+///
+/// Will return an error if it can't read the message or it's too long.
+pub async fn read_message<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let len = stream.read_u32().await? as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+    if len > MAX_MESSAGE_LEN {
+        return Err(invalid_data("message too long"));
+    }
+
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message).await?;
+
+    Ok(Some(message))
+}
+
+/// Returns the extended message ID and payload if the message is an
+/// extension protocol message.
+#[must_use]
+pub fn as_extended(message: &[u8]) -> Option<(u8, &[u8])> {
+    match message {
+        [EXTENDED_MESSAGE_ID, extended_id, payload @ ..] => Some((*extended_id, payload)),
+        _ => None,
+    }
+}
+
+/// Builds the extension protocol handshake with the IDs we want in the
+/// messages we receive. `ut_pex` is only announced if its ID is given. The
+/// metadata size is only sent by peers that have the metadata.
+#[must_use]
+pub fn extended_handshake(
+    ut_metadata_id: u8,
+    ut_pex_id: Option<u8>,
+    metadata_size: Option<usize>,
+) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.push(b'd');
+    bencode::encode_bytes(&mut payload, b"m");
+    payload.push(b'd');
+    bencode::encode_bytes(&mut payload, b"ut_metadata");
+    bencode::encode_int(&mut payload, i64::from(ut_metadata_id));
+    if let Some(ut_pex_id) = ut_pex_id {
+        bencode::encode_bytes(&mut payload, b"ut_pex");
+        bencode::encode_int(&mut payload, i64::from(ut_pex_id));
+    }
+    payload.push(b'e');
+    if let Some(metadata_size) = metadata_size {
+        bencode::encode_bytes(&mut payload, b"metadata_size");
+        bencode::encode_int(&mut payload, to_i64(metadata_size));
+    }
+    payload.push(b'e');
+
+    extended_message(EXTENDED_HANDSHAKE_ID, &payload)
+}
+
+/// Parses the other peer's extension protocol handshake.
+///
+/// # Errors
+///
+/// Will return an error if the payload is not a valid handshake.
+pub fn parse_extended_handshake(payload: &[u8]) -> io::Result<ExtendedHandshake> {
+    let (handshake, _) = bencode::decode(payload).map_err(|err| invalid_data(&err.to_string()))?;
+
+    let extension_id = |name: &[u8]| {
+        handshake
+            .get(b"m")
+            .and_then(|m| m.get(name))
+            .and_then(Value::as_int)
+            .and_then(|id| u8::try_from(id).ok())
+            .filter(|id| *id != 0) // 0 means the extension is disabled
+    };
+
+    let metadata_size = handshake
+        .get(b"metadata_size")
+        .and_then(Value::as_int)
+        .and_then(|size| usize::try_from(size).ok());
+
+    Ok(ExtendedHandshake {
+        ut_metadata_id: extension_id(b"ut_metadata"),
+        ut_pex_id: extension_id(b"ut_pex"),
+        metadata_size,
+    })
+}
+
+/// Builds a `ut_metadata` message with the ID the other peer has chosen.
+#[must_use]
+pub fn ut_metadata_message(
+    ut_metadata_id: u8,
+    message: &UtMetadata<'_>,
+    total_size: usize,
+) -> Vec<u8> {
+    let (msg_type, piece, data) = match message {
+        UtMetadata::Request { piece } => (0, piece, None),
+        UtMetadata::Data { piece, data } => (1, piece, Some(data)),
+        UtMetadata::Reject { piece } => (2, piece, None),
+    };
+
+    let mut payload = Vec::new();
+    payload.push(b'd');
+    bencode::encode_bytes(&mut payload, b"msg_type");
+    bencode::encode_int(&mut payload, msg_type);
+    bencode::encode_bytes(&mut payload, b"piece");
+    bencode::encode_int(&mut payload, to_i64(*piece));
+    if data.is_some() {
+        bencode::encode_bytes(&mut payload, b"total_size");
+        bencode::encode_int(&mut payload, to_i64(total_size));
+    }
+    payload.push(b'e');
+    if let Some(data) = data {
+        payload.extend_from_slice(data);
+    }
+
+    extended_message(ut_metadata_id, &payload)
+}
+
+/// Parses a `ut_metadata` message payload.
+///
+/// # Errors
+///
+/// Will return an error if the payload is not a valid `ut_metadata` message.
+pub fn parse_ut_metadata(payload: &[u8]) -> io::Result<UtMetadata<'_>> {
+    let (message, len) = bencode::decode(payload).map_err(|err| invalid_data(&err.to_string()))?;
+
+    let piece = message
+        .get(b"piece")
+        .and_then(Value::as_int)
+        .and_then(|piece| usize::try_from(piece).ok())
+        .ok_or_else(|| invalid_data("ut_metadata message without piece"))?;
+
+    match message.get(b"msg_type").and_then(Value::as_int) {
+        Some(0) => Ok(UtMetadata::Request { piece }),
+        Some(1) => Ok(UtMetadata::Data {
+            piece,
+            data: &payload[len..],
+        }),
+        Some(2) => Ok(UtMetadata::Reject { piece }),
+        _ => Err(invalid_data("unknown ut_metadata message type")),
+    }
+}
+
+/// Builds a `ut_pex` message that adds the given peers, with the ID the other
+/// peer has chosen.
+#[must_use]
+pub fn ut_pex_message(ut_pex_id: u8, added: &[SocketAddr]) -> Vec<u8> {
+    let (added, added6): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        added.iter().copied().partition(SocketAddr::is_ipv4);
+
+    let mut payload = Vec::new();
+    payload.push(b'd');
+    bencode::encode_bytes(&mut payload, b"added");
+    bencode::encode_bytes(&mut payload, &compact_peers(&added));
+    bencode::encode_bytes(&mut payload, b"added6");
+    bencode::encode_bytes(&mut payload, &compact_peers(&added6));
+    payload.push(b'e');
+
+    extended_message(ut_pex_id, &payload)
+}
+
+/// Returns the peers added in a `ut_pex` message payload.
+///
+/// # Errors
+///
+/// Will return an error if the payload is not a valid `ut_pex` message.
+pub fn parse_ut_pex(payload: &[u8]) -> io::Result<Vec<SocketAddr>> {
+    let (message, _) = bencode::decode(payload).map_err(|err| invalid_data(&err.to_string()))?;
+
+    let compact = |key: &[u8]| {
+        message
+            .get(key)
+            .and_then(Value::as_bytes)
+            .unwrap_or_default()
+    };
+
+    let mut added = parse_compact_peers(compact(b"added"));
+    added.extend(parse_compact_peers6(compact(b"added6")));
+
+    Ok(added)
+}
+
+/// Parses IPv4 peers in the compact format used by trackers and `ut_pex`:
+/// 4 bytes for the address and 2 for the port, in big-endian. An incomplete
+/// entry at the end is ignored.
+#[must_use]
+pub fn parse_compact_peers(compact: &[u8]) -> Vec<SocketAddr> {
+    compact
+        .chunks_exact(6)
+        .map(|peer| {
+            let ip = Ipv4Addr::new(peer[0], peer[1], peer[2], peer[3]);
+            SocketAddr::new(IpAddr::V4(ip), u16::from_be_bytes([peer[4], peer[5]]))
+        })
+        .collect()
+}
+
+/// Parses IPv6 peers in the compact format: 16 bytes for the address and 2
+/// for the port.
+#[must_use]
+pub fn parse_compact_peers6(compact: &[u8]) -> Vec<SocketAddr> {
+    compact
+        .chunks_exact(18)
+        .map(|peer| {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&peer[..16]);
+            SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(octets)),
+                u16::from_be_bytes([peer[16], peer[17]]),
+            )
+        })
+        .collect()
+}
+
+fn compact_peers(peers: &[SocketAddr]) -> Vec<u8> {
+    let mut compact = Vec::new();
+    for peer in peers {
+        match peer.ip() {
+            IpAddr::V4(ip) => compact.extend_from_slice(&ip.octets()),
+            IpAddr::V6(ip) => compact.extend_from_slice(&ip.octets()),
+        }
+        compact.extend_from_slice(&peer.port().to_be_bytes());
+    }
+    compact
+}
+
+fn extended_message(extended_id: u8, payload: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(payload.len() + 2).expect("the message length should fit in a u32");
+
+    let mut message = Vec::with_capacity(payload.len() + 6);
+    message.extend_from_slice(&len.to_be_bytes());
+    message.push(EXTENDED_MESSAGE_ID);
+    message.push(extended_id);
+    message.extend_from_slice(payload);
+    message
+}
+
+fn to_i64(len: usize) -> i64 {
+    i64::try_from(len).expect("the length should fit in an i64")
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::{
+        as_extended, extended_handshake, parse_compact_peers, parse_extended_handshake,
+        parse_ut_metadata, parse_ut_pex, ut_metadata_message, ut_pex_message, ExtendedHandshake,
+        UtMetadata, MAX_MESSAGE_LEN,
+    };
+
+    #[test]
+    fn it_should_parse_the_extended_handshake_it_builds() {
+        let message = extended_handshake(3, Some(4), Some(1234));
+
+        let (extended_id, payload) = as_extended(&message[4..]).unwrap();
+
+        assert_eq!(extended_id, 0);
+        assert_eq!(
+            parse_extended_handshake(payload).unwrap(),
+            ExtendedHandshake {
+                ut_metadata_id: Some(3),
+                ut_pex_id: Some(4),
+                metadata_size: Some(1234),
+            }
+        );
+    }
+
+    #[test]
+    fn it_should_parse_the_ut_pex_messages_it_builds() {
+        let peers: Vec<SocketAddr> = vec![
+            "203.0.113.7:51413".parse().unwrap(),
+            "[2001:db8::1]:6881".parse().unwrap(),
+        ];
+
+        let message = ut_pex_message(5, &peers);
+
+        let (extended_id, payload) = as_extended(&message[4..]).unwrap();
+
+        assert_eq!(extended_id, 5);
+        assert_eq!(parse_ut_pex(payload).unwrap(), peers);
+    }
+
+    #[test]
+    fn it_should_ignore_incomplete_compact_peers() {
+        let compact = [203, 0, 113, 7, 0xc8, 0xd5, 1, 2, 3];
+
+        assert_eq!(
+            parse_compact_peers(&compact),
+            vec!["203.0.113.7:51413".parse::<SocketAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn it_should_parse_the_ut_metadata_messages_it_builds() {
+        let data = UtMetadata::Data {
+            piece: 1,
+            data: b"metadata",
+        };
+
+        let message = ut_metadata_message(7, &data, 16392);
+
+        let (extended_id, payload) = as_extended(&message[4..]).unwrap();
+
+        assert_eq!(extended_id, 7);
+        assert_eq!(parse_ut_metadata(payload).unwrap(), data);
+    }
+
+    #[test]
+    fn it_should_reject_messages_nested_too_deeply() {
+        let payload = vec![b'l'; MAX_MESSAGE_LEN];
+
+        assert!(parse_extended_handshake(&payload).is_err());
+        assert!(parse_ut_metadata(&payload).is_err());
+        assert!(parse_ut_pex(&payload).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! A bounded pool for magnet link resolutions.
+//!
+//! Every resolution adds a list-only torrent to the `BitTorrent` session, which
+//! opens sockets and keeps some state in memory. The pool limits how many
+//! resolutions run at the same time. The rest wait in a FIFO queue, and new
+//! resolutions are rejected when the queue is full.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use utoipa::ToSchema;
+
+/// The queue is full. The caller should try again later.
+#[derive(Debug, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// Current state of the pool.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolutionPoolStats {
+    pub running: usize,
+    pub queued: usize,
+    pub max_concurrency: usize,
+    pub max_queue_length: usize,
+}
+
+pub struct ResolutionPool {
+    semaphore: Arc<Semaphore>,
+    running: Arc<AtomicUsize>,
+    queued: AtomicUsize,
+    max_concurrency: usize,
+    max_queue_length: usize,
+}
+
+impl ResolutionPool {
+    #[must_use]
+    pub fn new(max_concurrency: usize, max_queue_length: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency)),
+            running: Arc::new(AtomicUsize::new(0)),
+            queued: AtomicUsize::new(0),
+            max_concurrency,
+            max_queue_length,
+        }
+    }
+
+    /// Waits for a free slot. Slots are granted in FIFO order. The slot is
+    /// released when the returned guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if there is no free slot and the queue is full.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the semaphore is closed. It's never closed.
+    pub async fn acquire(&self) -> Result<ResolutionSlot, QueueFull> {
+        let permit = if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            permit
+        } else {
+            let queued = self
+                .queued
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                    (queued < self.max_queue_length).then_some(queued + 1)
+                });
+
+            if queued.is_err() {
+                return Err(QueueFull);
+            }
+
+            // The counter is decremented even if the caller gives up while
+            // waiting.
+            let _queued = QueuedGuard(&self.queued);
+
+            self.semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("resolution pool semaphore should never be closed")
+        };
+
+        self.running.fetch_add(1, Ordering::SeqCst);
+
+        Ok(ResolutionSlot {
+            _permit: permit,
+            running: self.running.clone(),
+        })
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> ResolutionPoolStats {
+        ResolutionPoolStats {
+            running: self.running.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+            max_concurrency: self.max_concurrency,
+            max_queue_length: self.max_queue_length,
+        }
+    }
+}
+
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A running resolution. It frees its slot in the pool when dropped.
+pub struct ResolutionSlot {
+    _permit: OwnedSemaphorePermit,
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for ResolutionSlot {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{QueueFull, ResolutionPool};
+
+    #[tokio::test]
+    async fn it_should_reject_resolutions_when_the_queue_is_full() {
+        let pool = Arc::new(ResolutionPool::new(1, 1));
+
+        let running = pool.acquire().await.unwrap();
+
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await.map(|_slot| ()) }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(pool.stats().running, 1);
+        assert_eq!(pool.stats().queued, 1);
+        assert_eq!(pool.acquire().await.err(), Some(QueueFull));
+
+        drop(running);
+
+        assert!(queued.await.unwrap().is_ok());
+        assert_eq!(pool.stats().running, 0);
+        assert_eq!(pool.stats().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn it_should_leave_the_queue_when_the_caller_gives_up() {
+        let pool = ResolutionPool::new(1, 1);
+
+        let _running = pool.acquire().await.unwrap();
+
+        let gave_up = tokio::time::timeout(Duration::from_millis(10), pool.acquire()).await;
+
+        assert!(gave_up.is_err());
+        assert_eq!(pool.stats().queued, 0);
+    }
+}
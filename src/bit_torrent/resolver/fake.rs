@@ -0,0 +1,240 @@
+//! A deterministic resolver for tests. It does not use the network.
+//!
+//! ```
+//! use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+//!
+//! let torrent = sample_torrent("sample.txt", b"Hello, World!");
+//!
+//! let resolver = FakeResolver::default().with_torrent(torrent);
+//! ```
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use sha1::{Digest, Sha1};
+
+use super::{
+    MetadataResolver, ResolutionEvent, ResolveError, ResolveOptions, TorrentFile, TorrentMetadata,
+};
+use crate::bit_torrent::bencode;
+use crate::bit_torrent::info_hash::InfoHash;
+
+/// Piece length used by the sample torrents.
+pub const SAMPLE_PIECE_LENGTH: usize = 16384;
+
+/// Resolves the torrents it has been given and fails with `NotFound` for the
+/// rest. It can simulate slow resolutions and specific errors.
+#[derive(Default)]
+pub struct FakeResolver {
+    torrents: HashMap<InfoHash, TorrentMetadata>,
+    failures: HashMap<InfoHash, ResolveError>,
+    /// Errors for the first resolutions of an info-hash, with the number of
+    /// resolutions left to fail.
+    transient_failures: HashMap<InfoHash, (ResolveError, AtomicUsize)>,
+    delay: Duration,
+    calls: AtomicUsize,
+}
+
+impl FakeResolver {
+    /// Adds a torrent the resolver will find.
+    #[must_use]
+    pub fn with_torrent(mut self, torrent: TorrentMetadata) -> Self {
+        self.torrents.insert(torrent.info_hash, torrent);
+        self
+    }
+
+    /// Makes the resolution of the info-hash fail with the given error.
+    #[must_use]
+    pub fn with_failure(mut self, info_hash: InfoHash, error: ResolveError) -> Self {
+        self.failures.insert(info_hash, error);
+        self
+    }
+
+    /// Makes the first `times` resolutions of the info-hash fail with the
+    /// given error. The next ones go on as usual.
+    #[must_use]
+    pub fn with_transient_failure(
+        mut self,
+        info_hash: InfoHash,
+        error: ResolveError,
+        times: usize,
+    ) -> Self {
+        self.transient_failures
+            .insert(info_hash, (error, AtomicUsize::new(times)));
+        self
+    }
+
+    /// Makes every resolution take the given time. Resolutions with a
+    /// shorter timeout fail with `Timeout`.
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Number of times `resolve` has been called.
+    #[must_use]
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl MetadataResolver for FakeResolver {
+    async fn resolve(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        options.progress.report(ResolutionEvent::Queued);
+
+        match options.timeout {
+            Some(timeout) if timeout < self.delay => {
+                tokio::time::sleep(timeout).await;
+                return Err(ResolveError::Timeout);
+            }
+            _ => tokio::time::sleep(self.delay).await,
+        }
+
+        if let Some(error) = self.failures.get(info_hash) {
+            return Err(error.clone());
+        }
+
+        if let Some((error, left)) = self.transient_failures.get(info_hash) {
+            if left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok()
+            {
+                return Err(error.clone());
+            }
+        }
+
+        let torrent = self
+            .torrents
+            .get(info_hash)
+            .cloned()
+            .ok_or_else(|| ResolveError::NotFound(info_hash.to_hex_string()))?;
+
+        options.progress.report(ResolutionEvent::Verified);
+
+        Ok(torrent)
+    }
+}
+
+/// Builds a single-file torrent with the given file name and contents.
+///
+/// # Panics
+///
+/// Will panic if the contents length does not fit in an `i64`.
+#[must_use]
+pub fn sample_torrent(name: &str, contents: &[u8]) -> TorrentMetadata {
+    let pieces: Vec<u8> = contents
+        .chunks(SAMPLE_PIECE_LENGTH)
+        .flat_map(|piece| Sha1::digest(piece).to_vec())
+        .collect();
+
+    let length = i64::try_from(contents.len()).expect("sample contents should fit in an i64");
+    let piece_length = i64::try_from(SAMPLE_PIECE_LENGTH).expect("piece length fits in an i64");
+
+    // Keys in alphabetical order as required by bencode.
+    let mut info = Vec::new();
+    info.push(b'd');
+    bencode::encode_bytes(&mut info, b"length");
+    bencode::encode_int(&mut info, length);
+    bencode::encode_bytes(&mut info, b"name");
+    bencode::encode_bytes(&mut info, name.as_bytes());
+    bencode::encode_bytes(&mut info, b"piece length");
+    bencode::encode_int(&mut info, piece_length);
+    bencode::encode_bytes(&mut info, b"pieces");
+    bencode::encode_bytes(&mut info, &pieces);
+    info.push(b'e');
+
+    let info_hash = InfoHash::from_bytes(&Sha1::digest(&info));
+
+    let mut torrent = Vec::new();
+    torrent.push(b'd');
+    bencode::encode_bytes(&mut torrent, b"info");
+    torrent.extend_from_slice(&info);
+    torrent.push(b'e');
+
+    TorrentMetadata {
+        info_hash,
+        name: Some(name.to_string()),
+        piece_length: u32::try_from(SAMPLE_PIECE_LENGTH).expect("piece length fits in a u32"),
+        pieces: pieces.len() / 20,
+        files: vec![TorrentFile {
+            path: vec![name.to_string()],
+            length: contents.len() as u64,
+        }],
+        torrent_bytes: Bytes::from(torrent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use super::{sample_torrent, FakeResolver};
+    use crate::bit_torrent::info_hash::InfoHash;
+    use crate::bit_torrent::resolver::{
+        MetadataResolver, ResolveError, ResolveOptions, TorrentMetadata,
+    };
+
+    #[test]
+    fn sample_torrents_should_be_valid_torrent_files() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let parsed = TorrentMetadata::from_torrent_bytes(torrent.torrent_bytes.clone()).unwrap();
+
+        assert_eq!(parsed, torrent);
+    }
+
+    #[tokio::test]
+    async fn it_should_resolve_the_torrents_it_has_been_given() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let resolver = FakeResolver::default().with_torrent(torrent.clone());
+
+        let result = resolver
+            .resolve(&torrent.info_hash, &ResolveOptions::default())
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+        assert_eq!(resolver.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_not_find_unknown_torrents() {
+        let resolver = FakeResolver::default();
+        let info_hash = InfoHash::from_str("443c7602b4fde83d1154d6d9da48808418b181b6").unwrap(); // DevSkim: ignore DS173237
+
+        let result = resolver
+            .resolve(&info_hash, &ResolveOptions::default())
+            .await;
+
+        assert!(matches!(result, Err(ResolveError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn it_should_time_out_when_the_delay_is_longer_than_the_timeout() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let resolver = FakeResolver::default()
+            .with_torrent(torrent.clone())
+            .with_delay(Duration::from_mins(1));
+
+        let options = ResolveOptions {
+            timeout: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolver.resolve(&torrent.info_hash, &options).await,
+            Err(ResolveError::Timeout)
+        );
+    }
+}
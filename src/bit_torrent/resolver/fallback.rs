@@ -0,0 +1,179 @@
+//! A resolver that tries a list of resolvers in order.
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use super::{
+    MetadataResolver, ResolveError, ResolveOptions, ResolverStats, SessionManager, TorrentMetadata,
+};
+use crate::bit_torrent::info_hash::InfoHash;
+
+/// Returns the metadata from the first resolver that finds it. If all of
+/// them fail, it returns the error from the last one.
+///
+/// The timeout in the options applies to the whole chain. Each resolver gets
+/// an equal share of the time left, so the time a resolver does not use is
+/// given to the next ones.
+pub struct FallbackResolver {
+    resolvers: Vec<Arc<dyn MetadataResolver>>,
+}
+
+impl FallbackResolver {
+    #[must_use]
+    pub fn new(resolvers: Vec<Arc<dyn MetadataResolver>>) -> Self {
+        Self { resolvers }
+    }
+}
+
+#[async_trait]
+impl MetadataResolver for FallbackResolver {
+    async fn resolve(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError> {
+        let mut last_error = ResolveError::Other("no resolvers configured".to_string());
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+        for (index, resolver) in self.resolvers.iter().enumerate() {
+            let options = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    let resolvers_left =
+                        u32::try_from(self.resolvers.len() - index).unwrap_or(u32::MAX);
+
+                    if remaining.is_zero() {
+                        return Err(ResolveError::Timeout);
+                    }
+
+                    ResolveOptions {
+                        timeout: Some(remaining / resolvers_left),
+                        ..options.clone()
+                    }
+                }
+                None => options.clone(),
+            };
+
+            match resolver.resolve(info_hash, &options).await {
+                Ok(metadata) => return Ok(metadata),
+                Err(err) => last_error = err,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Returns the stats of the first resolver that reports them.
+    fn stats(&self) -> ResolverStats {
+        let stats: Vec<ResolverStats> = self.resolvers.iter().map(|r| r.stats()).collect();
+
+        ResolverStats {
+            resolutions: stats.iter().find_map(|stats| stats.resolutions),
+            session: stats.iter().find_map(|stats| stats.session),
+        }
+    }
+
+    /// Returns the session of the first resolver that uses one.
+    fn session(&self) -> Option<&dyn SessionManager> {
+        self.resolvers
+            .iter()
+            .find_map(|resolver| resolver.session())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use super::FallbackResolver;
+    use crate::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+    use crate::bit_torrent::resolver::{MetadataResolver, ResolveError, ResolveOptions};
+
+    #[tokio::test]
+    async fn it_should_return_the_metadata_from_the_first_resolver_that_finds_it() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let first = Arc::new(FakeResolver::default());
+        let second = Arc::new(FakeResolver::default().with_torrent(torrent.clone()));
+        let third = Arc::new(FakeResolver::default().with_torrent(torrent.clone()));
+
+        let resolver = FallbackResolver::new(vec![first.clone(), second.clone(), third.clone()]);
+
+        let result = resolver
+            .resolve(&torrent.info_hash, &ResolveOptions::default())
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+        assert_eq!((first.calls(), second.calls(), third.calls()), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn it_should_return_the_last_error_when_all_resolvers_fail() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let first = Arc::new(FakeResolver::default());
+        let second = Arc::new(
+            FakeResolver::default().with_failure(torrent.info_hash, ResolveError::QueueFull),
+        );
+
+        let resolver = FallbackResolver::new(vec![first, second]);
+
+        let result = resolver
+            .resolve(&torrent.info_hash, &ResolveOptions::default())
+            .await;
+
+        assert_eq!(result, Err(ResolveError::QueueFull));
+    }
+
+    #[tokio::test]
+    async fn it_should_split_the_timeout_across_the_resolvers() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let first = Arc::new(FakeResolver::default().with_delay(Duration::from_millis(200)));
+        let second = Arc::new(
+            FakeResolver::default()
+                .with_torrent(torrent.clone())
+                .with_delay(Duration::from_millis(200)),
+        );
+
+        let resolver = FallbackResolver::new(vec![first, second]);
+        let start = Instant::now();
+
+        let result = resolver
+            .resolve(
+                &torrent.info_hash,
+                &ResolveOptions {
+                    timeout: Some(Duration::from_millis(300)),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert_eq!(result, Err(ResolveError::Timeout));
+        assert!(start.elapsed() < Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn it_should_give_the_time_left_by_a_resolver_to_the_next_ones() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let first = Arc::new(FakeResolver::default());
+        let second = Arc::new(
+            FakeResolver::default()
+                .with_torrent(torrent.clone())
+                .with_delay(Duration::from_millis(200)),
+        );
+
+        let resolver = FallbackResolver::new(vec![first, second]);
+
+        let result = resolver
+            .resolve(
+                &torrent.info_hash,
+                &ResolveOptions {
+                    timeout: Some(Duration::from_millis(300)),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+    }
+}
@@ -0,0 +1,193 @@
+//! The torrent metadata returned by the resolvers.
+use bytes::Bytes;
+use librqbit::{torrent_from_bytes, ByteBufOwned, FileIteratorName, TorrentMetaV1Info};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::bit_torrent::bencode;
+use crate::bit_torrent::info_hash::InfoHash;
+
+#[derive(Error, Debug)]
+#[error("Invalid torrent: {0}")]
+pub struct InvalidTorrent(String);
+
+/// A file in the torrent.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct TorrentFile {
+    /// Path components. For single-file torrents it's the torrent name.
+    pub path: Vec<String>,
+    /// Length in bytes.
+    pub length: u64,
+}
+
+/// The info dictionary fields plus the torrent file (`.torrent`) contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorrentMetadata {
+    pub info_hash: InfoHash,
+    pub name: Option<String>,
+    pub piece_length: u32,
+    /// Number of pieces.
+    pub pieces: usize,
+    pub files: Vec<TorrentFile>,
+    /// The bencoded torrent file.
+    pub torrent_bytes: Bytes,
+}
+
+impl TorrentMetadata {
+    /// Builds the metadata from the decoded info dictionary.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the info dictionary has no files.
+    pub fn from_info(
+        info_hash: InfoHash,
+        info: &TorrentMetaV1Info<ByteBufOwned>,
+        torrent_bytes: Bytes,
+    ) -> Result<Self, InvalidTorrent> {
+        let files = info
+            .iter_filenames_and_lengths()
+            .map_err(|err| InvalidTorrent(err.to_string()))?
+            .map(|(name, length)| TorrentFile {
+                path: path_components(&name),
+                length,
+            })
+            .collect();
+
+        Ok(Self {
+            info_hash,
+            name: info
+                .name
+                .as_ref()
+                .map(|name| String::from_utf8_lossy(name.as_ref()).to_string()),
+            piece_length: info.piece_length,
+            pieces: info.pieces.as_ref().len() / 20,
+            files,
+            torrent_bytes,
+        })
+    }
+
+    /// Parses a torrent file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the bytes are not a valid torrent file.
+    pub fn from_torrent_bytes(torrent_bytes: Bytes) -> Result<Self, InvalidTorrent> {
+        let torrent = torrent_from_bytes::<ByteBufOwned>(&torrent_bytes)
+            .map_err(|err| InvalidTorrent(err.to_string()))?;
+
+        Self::from_info(InfoHash(torrent.info_hash.0), &torrent.info, torrent_bytes)
+    }
+
+    /// Sum of the lengths of all the files.
+    #[must_use]
+    pub fn total_length(&self) -> u64 {
+        self.files.iter().map(|file| file.length).sum()
+    }
+
+    /// Returns true if the info dictionary has the `private` flag
+    /// ([BEP 27](https://www.bittorrent.org/beps/bep_0027.html)).
+    #[must_use]
+    pub fn is_private(&self) -> bool {
+        bencode::raw_dict_value(&self.torrent_bytes, b"info")
+            .ok()
+            .flatten()
+            .and_then(|info| bencode::raw_dict_value(info, b"private").ok().flatten())
+            .and_then(|private| bencode::decode(private).ok())
+            .is_some_and(|(private, _)| private.as_int() == Some(1))
+    }
+
+    /// The SHA-256 info-hash, if it's a hybrid torrent: an info dictionary
+    /// with `meta version` 2 and the v1 `pieces`.
+    #[must_use]
+    pub fn v2_info_hash(&self) -> Option<[u8; 32]> {
+        let info = bencode::raw_dict_value(&self.torrent_bytes, b"info").ok()??;
+
+        let meta_version = bencode::raw_dict_value(info, b"meta version").ok()??;
+        let (meta_version, _) = bencode::decode(meta_version).ok()?;
+
+        if meta_version.as_int() != Some(2)
+            || bencode::raw_dict_value(info, b"pieces").ok()?.is_none()
+        {
+            return None;
+        }
+
+        Some(Sha256::digest(info).into())
+    }
+}
+
+fn path_components(name: &FileIteratorName<'_, ByteBufOwned>) -> Vec<String> {
+    match name {
+        FileIteratorName::Single(name) => name
+            .map(|name| String::from_utf8_lossy(name.as_ref()).to_string())
+            .into_iter()
+            .collect(),
+        FileIteratorName::Tree(components) => components
+            .iter()
+            .map(|component| String::from_utf8_lossy(component.as_ref()).to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use sha2::{Digest, Sha256};
+
+    use super::TorrentMetadata;
+    use crate::bit_torrent::bencode;
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+
+    #[test]
+    fn v1_torrents_should_not_have_a_v2_info_hash() {
+        assert_eq!(
+            sample_torrent("sample.txt", b"Hello, World!").v2_info_hash(),
+            None
+        );
+    }
+
+    #[test]
+    fn torrents_should_be_private_if_the_info_dictionary_has_the_private_flag() {
+        let mut info = vec![b'd'];
+        bencode::encode_bytes(&mut info, b"private");
+        bencode::encode_int(&mut info, 1);
+        info.push(b'e');
+
+        let mut torrent = vec![b'd'];
+        bencode::encode_bytes(&mut torrent, b"info");
+        torrent.extend_from_slice(&info);
+        torrent.push(b'e');
+
+        let sample = sample_torrent("sample.txt", b"Hello, World!");
+
+        assert!(!sample.is_private());
+        assert!(TorrentMetadata {
+            torrent_bytes: Bytes::from(torrent),
+            ..sample
+        }
+        .is_private());
+    }
+
+    #[test]
+    fn hybrid_torrents_should_have_the_sha256_of_the_info_dictionary_as_v2_info_hash() {
+        let mut info = vec![b'd'];
+        bencode::encode_bytes(&mut info, b"meta version");
+        bencode::encode_int(&mut info, 2);
+        bencode::encode_bytes(&mut info, b"pieces");
+        bencode::encode_bytes(&mut info, &[0; 20]);
+        info.push(b'e');
+
+        let mut torrent = vec![b'd'];
+        bencode::encode_bytes(&mut torrent, b"info");
+        torrent.extend_from_slice(&info);
+        torrent.push(b'e');
+
+        let metadata = TorrentMetadata {
+            torrent_bytes: Bytes::from(torrent),
+            ..sample_torrent("sample.txt", b"Hello, World!")
+        };
+
+        assert_eq!(metadata.v2_info_hash(), Some(Sha256::digest(&info).into()));
+    }
+}
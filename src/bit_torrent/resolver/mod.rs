@@ -0,0 +1,148 @@
+//! Resolvers get the torrent metadata for an info-hash.
+//!
+//! The API only depends on the [`MetadataResolver`] trait. The implementations
+//! are:
+//!
+//! - [`Client`](crate::bit_torrent::client::Client): the `librqbit` client. It
+//!   uses the DHT and trackers to find peers and [BEP 9](https://www.bittorrent.org/beps/bep_0009.html)
+//!   to get the metadata from them.
+//! - [`NativeResolver`](native::NativeResolver): a lightweight fetcher that
+//!   gets the metadata directly from the peers, without a `librqbit` session.
+//! - [`FakeResolver`](fake::FakeResolver): a deterministic resolver for tests.
+//! - [`FallbackResolver`](fallback::FallbackResolver): tries other resolvers
+//!   in order until one of them succeeds.
+//!
+//! Resolvers backed by a `BitTorrent` session also implement
+//! [`SessionManager`], so the admin API can list and remove the torrents
+//! left in the session without knowing the resolver type.
+pub mod fake;
+pub mod fallback;
+pub mod metadata;
+pub mod native;
+pub mod progress;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use librqbit::dht::Dht;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::resolution_pool::ResolutionPoolStats;
+
+pub use metadata::{TorrentFile, TorrentMetadata};
+pub use progress::{Progress, ResolutionEvent};
+
+/// Options for a single resolution.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResolveOptions {
+    /// Maximum time to get the metadata. There is no limit if it's not set.
+    pub timeout: Option<Duration>,
+    /// Trackers to announce to in order to find peers.
+    pub trackers: Vec<String>,
+    /// Peers dialed directly to get the metadata, besides the ones found with
+    /// the DHT and trackers.
+    pub initial_peers: Vec<SocketAddr>,
+    /// Receives the phases of the resolution.
+    pub progress: Progress,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("Torrent metadata could not be resolved in time")]
+    Timeout,
+    #[error("Too many torrents waiting to be resolved")]
+    QueueFull,
+    #[error("Torrent metadata not found: {0}")]
+    NotFound(String),
+    #[error("Resolver error: {0}")]
+    Other(String),
+}
+
+/// Runtime statistics a resolver can expose for health checks and metrics.
+#[derive(Serialize, ToSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResolverStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolutions: Option<ResolutionPoolStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionStats>,
+}
+
+/// Number of torrents in the `BitTorrent` session and how many have been
+/// cleaned up.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStats {
+    pub torrents: usize,
+    pub removed_torrents: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum ManageTorrentError {
+    #[error("BitTorrent client session not started")]
+    NoSession,
+    #[error("Torrent is not managed by the BitTorrent client session")]
+    NotManaged,
+    #[error("BitTorrent client session error: {0}")]
+    Session(anyhow::Error),
+}
+
+/// A torrent managed by the `BitTorrent` session.
+#[derive(Serialize, Debug)]
+pub struct SessionTorrent {
+    pub id: usize,
+    pub info_hash: InfoHash,
+    pub name: Option<String>,
+    pub state: &'static str,
+}
+
+/// Management of the torrents in the `BitTorrent` session of a resolver.
+#[async_trait]
+pub trait SessionManager: Send + Sync {
+    /// Returns the torrents currently managed by the session.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the session has not been started.
+    fn list_torrents(&self) -> Result<Vec<SessionTorrent>, ManageTorrentError>;
+
+    /// Removes a torrent from the session. Downloaded files are not deleted.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the session has not been started, the torrent
+    /// is not managed by the session or the session fails to remove it.
+    async fn remove_torrent(&self, info_hash: &InfoHash) -> Result<(), ManageTorrentError>;
+}
+
+#[async_trait]
+pub trait MetadataResolver: Send + Sync {
+    /// Gets the metadata of the torrent with the given info-hash.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the metadata can't be found, the resolution
+    /// times out or the resolver is overloaded.
+    async fn resolve(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError>;
+
+    /// Returns the resolver runtime statistics.
+    fn stats(&self) -> ResolverStats {
+        ResolverStats::default()
+    }
+
+    /// Returns the DHT the resolver uses to find peers, if any.
+    fn dht(&self) -> Option<Dht> {
+        None
+    }
+
+    /// Returns the `BitTorrent` session the resolver uses, if any.
+    fn session(&self) -> Option<&dyn SessionManager> {
+        None
+    }
+}
@@ -0,0 +1,500 @@
+//! A lightweight resolver that gets the metadata directly from the peers with
+//! the [BEP 9](https://www.bittorrent.org/beps/bep_0009.html) `ut_metadata`
+//! extension.
+//!
+//! Unlike the `librqbit` [`Client`](crate::bit_torrent::client::Client), it
+//! does not need a session, storage or a listen port. It only opens outgoing
+//! connections to the peers found with the DHT and the initial peers in the
+//! request. Trackers are not used.
+//!
+//! Several peers are asked in parallel. The first metadata whose hash
+//! matches the info-hash is returned and the other connections are closed.
+use std::collections::HashSet;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use librqbit::dht::{Dht, DhtBuilder, Id20};
+use sha1::{Digest, Sha1};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tracing::debug;
+
+use super::{
+    MetadataResolver, Progress, ResolutionEvent, ResolveError, ResolveOptions, ResolverStats,
+    TorrentMetadata,
+};
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::peer_wire::{
+    self, invalid_data, UtMetadata, EXTENDED_HANDSHAKE_ID, METADATA_PIECE_LEN,
+};
+use crate::bit_torrent::resolution_pool::ResolutionPool;
+use crate::config::Client as ClientConfig;
+
+/// Number of peers asked for the metadata at the same time.
+const PARALLEL_PEERS: usize = 8;
+
+/// Time a peer has to send the whole metadata.
+const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bigger metadata is rejected.
+const MAX_METADATA_SIZE: usize = 8 * 1024 * 1024;
+
+/// The `ut_metadata` message ID we expect in the messages we receive.
+const UT_METADATA_ID: u8 = 1;
+
+pub struct NativeResolver {
+    dht: Option<Dht>,
+    peer_id: [u8; 20],
+    resolution_pool: ResolutionPool,
+}
+
+impl NativeResolver {
+    /// Creates the resolver and starts the DHT if it's enabled.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the DHT can't be started.
+    pub async fn new(config: &ClientConfig) -> Result<Self, anyhow::Error> {
+        let dht = if config.enable_dht {
+            Some(DhtBuilder::new().await.context("error starting the DHT")?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            dht,
+            peer_id: librqbit::generate_peer_id().0,
+            resolution_pool: ResolutionPool::new(
+                config.max_concurrent_resolutions,
+                config.max_queued_resolutions,
+            ),
+        })
+    }
+
+    /// The initial peers followed by the ones found with the DHT.
+    fn peers(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<BoxStream<'static, SocketAddr>, ResolveError> {
+        let initial_peers = stream::iter(options.initial_peers.clone());
+
+        match &self.dht {
+            Some(dht) => {
+                options.progress.report(ResolutionEvent::DhtLookupStarted);
+
+                let dht_peers = dht
+                    .get_peers(Id20::new(info_hash.0), None)
+                    .map_err(|err| ResolveError::Other(err.to_string()))?;
+                Ok(initial_peers.chain(dht_peers).boxed())
+            }
+            None => Ok(initial_peers.boxed()),
+        }
+    }
+
+    async fn fetch(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError> {
+        let mut peers = self.peers(info_hash, options)?;
+        let mut seen_peers = HashSet::new();
+        let mut no_more_peers = false;
+        // Dropping the set aborts the fetches still running.
+        let mut fetches = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                peer = peers.next(), if !no_more_peers && fetches.len() < PARALLEL_PEERS => {
+                    match peer {
+                        Some(peer) if seen_peers.insert(peer) => {
+                            options.progress.report(ResolutionEvent::PeersFound {
+                                count: seen_peers.len(),
+                            });
+                            fetches.spawn(fetch_from_peer(
+                                peer,
+                                *info_hash,
+                                self.peer_id,
+                                options.progress.clone(),
+                            ));
+                        }
+                        Some(_) => {}
+                        None => no_more_peers = true,
+                    }
+                }
+                Some(joined) = fetches.join_next(), if !fetches.is_empty() => {
+                    match joined {
+                        Ok((_, Ok(info))) => return build_metadata(*info_hash, &info),
+                        Ok((peer, Err(err))) => debug!("no metadata from peer {peer}: {err}"),
+                        Err(err) => debug!("metadata fetch failed: {err}"),
+                    }
+                }
+                else => break,
+            }
+        }
+
+        Err(ResolveError::NotFound(format!(
+            "no peer sent the metadata for {info_hash}"
+        )))
+    }
+}
+
+#[async_trait]
+impl MetadataResolver for NativeResolver {
+    async fn resolve(
+        &self,
+        info_hash: &InfoHash,
+        options: &ResolveOptions,
+    ) -> Result<TorrentMetadata, ResolveError> {
+        let resolve = async {
+            options.progress.report(ResolutionEvent::Queued);
+
+            let _slot = self
+                .resolution_pool
+                .acquire()
+                .await
+                .map_err(|_queue_full| ResolveError::QueueFull)?;
+
+            self.fetch(info_hash, options).await
+        };
+
+        match options.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, resolve)
+                .await
+                .map_err(|_elapsed| ResolveError::Timeout)?,
+            None => resolve.await,
+        }
+    }
+
+    fn stats(&self) -> ResolverStats {
+        ResolverStats {
+            resolutions: Some(self.resolution_pool.stats()),
+            session: None,
+        }
+    }
+
+    fn dht(&self) -> Option<Dht> {
+        self.dht.clone()
+    }
+}
+
+/// Builds a torrent file with only the info dictionary.
+fn build_metadata(info_hash: InfoHash, info: &[u8]) -> Result<TorrentMetadata, ResolveError> {
+    let mut torrent = Vec::with_capacity(info.len() + 8);
+    torrent.extend_from_slice(b"d4:info");
+    torrent.extend_from_slice(info);
+    torrent.push(b'e');
+
+    let metadata = TorrentMetadata::from_torrent_bytes(Bytes::from(torrent))
+        .map_err(|err| ResolveError::Other(err.to_string()))?;
+
+    debug_assert_eq!(metadata.info_hash, info_hash);
+
+    Ok(metadata)
+}
+
+async fn fetch_from_peer(
+    peer: SocketAddr,
+    info_hash: InfoHash,
+    peer_id: [u8; 20],
+    progress: Progress,
+) -> (SocketAddr, io::Result<Vec<u8>>) {
+    let result = tokio::time::timeout(
+        PEER_TIMEOUT,
+        fetch_info(peer, info_hash, peer_id, &progress),
+    )
+    .await
+    .unwrap_or_else(|_elapsed| Err(io::ErrorKind::TimedOut.into()));
+
+    (peer, result)
+}
+
+/// Gets the info dictionary from the peer and checks its hash.
+async fn fetch_info(
+    peer: SocketAddr,
+    info_hash: InfoHash,
+    peer_id: [u8; 20],
+    progress: &Progress,
+) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(peer).await?;
+
+    stream
+        .write_all(&peer_wire::handshake(&info_hash, &peer_id))
+        .await?;
+
+    let handshake = peer_wire::read_handshake(&mut stream).await?;
+    if handshake.info_hash != info_hash {
+        return Err(invalid_data("the peer answered with another info-hash"));
+    }
+    if !handshake.supports_extensions {
+        return Err(invalid_data("the peer does not support extensions"));
+    }
+
+    progress.report(ResolutionEvent::ConnectedToPeer { peer });
+
+    stream
+        .write_all(&peer_wire::extended_handshake(UT_METADATA_ID, None, None))
+        .await?;
+
+    let mut metadata: Option<MetadataBuffer> = None;
+
+    loop {
+        let Some(message) = peer_wire::read_message(&mut stream).await? else {
+            continue;
+        };
+
+        match peer_wire::as_extended(&message) {
+            Some((EXTENDED_HANDSHAKE_ID, payload)) => {
+                let handshake = peer_wire::parse_extended_handshake(payload)?;
+                let (Some(remote_id), Some(size)) =
+                    (handshake.ut_metadata_id, handshake.metadata_size)
+                else {
+                    return Err(invalid_data("the peer does not share the metadata"));
+                };
+
+                let buffer = MetadataBuffer::new(size)?;
+
+                let mut requests = Vec::new();
+                for piece in 0..buffer.pieces() {
+                    requests.extend(peer_wire::ut_metadata_message(
+                        remote_id,
+                        &UtMetadata::Request { piece },
+                        size,
+                    ));
+                }
+                stream.write_all(&requests).await?;
+
+                metadata = Some(buffer);
+            }
+            Some((UT_METADATA_ID, payload)) => {
+                let Some(buffer) = metadata.as_mut() else {
+                    return Err(invalid_data("metadata received before the handshake"));
+                };
+
+                match peer_wire::parse_ut_metadata(payload)? {
+                    UtMetadata::Data { piece, data } => {
+                        let complete = buffer.add(piece, data)?;
+
+                        progress.report(ResolutionEvent::MetadataPieceReceived {
+                            peer,
+                            piece,
+                            pieces: buffer.pieces(),
+                        });
+
+                        if complete {
+                            let info = std::mem::take(&mut buffer.data);
+
+                            if Sha1::digest(&info).as_slice() != info_hash.0 {
+                                return Err(invalid_data("the metadata hash does not match"));
+                            }
+
+                            progress.report(ResolutionEvent::Verified);
+
+                            return Ok(info);
+                        }
+                    }
+                    UtMetadata::Reject { piece } => {
+                        return Err(invalid_data(&format!(
+                            "the peer rejected the metadata piece {piece}"
+                        )));
+                    }
+                    // We don't have the metadata.
+                    UtMetadata::Request { .. } => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The metadata pieces received so far.
+struct MetadataBuffer {
+    data: Vec<u8>,
+    received: Vec<bool>,
+}
+
+impl MetadataBuffer {
+    fn new(size: usize) -> io::Result<Self> {
+        if size == 0 || size > MAX_METADATA_SIZE {
+            return Err(invalid_data(&format!("invalid metadata size {size}")));
+        }
+
+        Ok(Self {
+            data: vec![0u8; size],
+            received: vec![false; size.div_ceil(METADATA_PIECE_LEN)],
+        })
+    }
+
+    fn pieces(&self) -> usize {
+        self.received.len()
+    }
+
+    /// Stores the piece. It returns true when all the pieces have been
+    /// received.
+    fn add(&mut self, piece: usize, data: &[u8]) -> io::Result<bool> {
+        let Some(chunk) = self.data.chunks_mut(METADATA_PIECE_LEN).nth(piece) else {
+            return Err(invalid_data(&format!("unexpected metadata piece {piece}")));
+        };
+
+        if chunk.len() != data.len() {
+            return Err(invalid_data(&format!(
+                "metadata piece {piece} has {} bytes, expected {}",
+                data.len(),
+                chunk.len()
+            )));
+        }
+
+        chunk.copy_from_slice(data);
+        self.received[piece] = true;
+
+        Ok(self.received.iter().all(|received| *received))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{MetadataBuffer, NativeResolver, METADATA_PIECE_LEN};
+    use crate::bit_torrent::resolver::fake::{sample_torrent, SAMPLE_PIECE_LENGTH};
+    use crate::bit_torrent::resolver::{
+        MetadataResolver, Progress, ResolutionEvent, ResolveError, ResolveOptions,
+    };
+    use crate::bit_torrent::test_peer::TestPeer;
+    use crate::config::{Client as ClientConfig, ResolverBackend};
+
+    async fn resolver() -> NativeResolver {
+        NativeResolver::new(&ClientConfig {
+            listen_port_range: None,
+            session_output_dir: "unused".into(),
+            enable_dht: false,
+            resolver_backend: ResolverBackend::Native,
+            max_concurrent_resolutions: 4,
+            max_queued_resolutions: 4,
+            orphan_sweep_interval: Duration::from_mins(10),
+        })
+        .await
+        .unwrap()
+    }
+
+    fn options(peers: &[&TestPeer]) -> ResolveOptions {
+        ResolveOptions {
+            timeout: Some(Duration::from_secs(10)),
+            initial_peers: peers.iter().map(|peer| peer.address()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_get_the_metadata_from_a_peer() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+        let result = resolver()
+            .await
+            .resolve(&torrent.info_hash, &options(&[&peer]))
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+    }
+
+    #[tokio::test]
+    async fn it_should_get_metadata_sent_in_several_pieces() {
+        let contents = vec![0u8; SAMPLE_PIECE_LENGTH * 1000];
+        let torrent = sample_torrent("big.bin", &contents);
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+        let result = resolver()
+            .await
+            .resolve(&torrent.info_hash, &options(&[&peer]))
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+        assert_eq!(peer.served_pieces(), 2);
+    }
+
+    #[tokio::test]
+    async fn it_should_report_the_progress_of_the_resolution() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+        let (progress, mut receiver) = Progress::channel();
+
+        let result = resolver()
+            .await
+            .resolve(
+                &torrent.info_hash,
+                &ResolveOptions {
+                    progress,
+                    ..options(&[&peer])
+                },
+            )
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+
+        let mut events = vec![];
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                ResolutionEvent::Queued,
+                ResolutionEvent::PeersFound { count: 1 },
+                ResolutionEvent::ConnectedToPeer {
+                    peer: peer.address()
+                },
+                ResolutionEvent::MetadataPieceReceived {
+                    peer: peer.address(),
+                    piece: 0,
+                    pieces: 1
+                },
+                ResolutionEvent::Verified,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_skip_peers_that_do_not_have_the_metadata() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let empty_peer = TestPeer::start(vec![]).await.unwrap();
+        let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+        let result = resolver()
+            .await
+            .resolve(&torrent.info_hash, &options(&[&empty_peer, &peer]))
+            .await;
+
+        assert_eq!(result, Ok(torrent));
+    }
+
+    #[tokio::test]
+    async fn it_should_not_find_the_metadata_when_no_peer_has_it() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let empty_peer = TestPeer::start(vec![]).await.unwrap();
+
+        let result = resolver()
+            .await
+            .resolve(&torrent.info_hash, &options(&[&empty_peer]))
+            .await;
+
+        assert!(matches!(result, Err(ResolveError::NotFound(_))));
+    }
+
+    #[test]
+    fn the_metadata_buffer_should_reject_pieces_with_the_wrong_size() {
+        let mut buffer = MetadataBuffer::new(METADATA_PIECE_LEN + 10).unwrap();
+
+        assert!(buffer.add(1, &[0u8; 9]).is_err());
+        assert!(buffer.add(2, &[0u8; 10]).is_err());
+        assert!(!buffer.add(1, &[0u8; 10]).unwrap());
+        assert!(buffer.add(0, &[0u8; METADATA_PIECE_LEN]).unwrap());
+    }
+}
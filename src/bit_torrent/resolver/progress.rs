@@ -0,0 +1,159 @@
+//! Progress of a resolution.
+//!
+//! Resolvers report the phases of a resolution through the [`Progress`] in
+//! the [`ResolveOptions`](super::ResolveOptions). Not every resolver can
+//! observe every phase: the `librqbit` client does not report the peer
+//! connections nor the metadata pieces.
+use std::fmt;
+use std::net::SocketAddr;
+
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use utoipa::ToSchema;
+
+/// A phase of a resolution.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum ResolutionEvent {
+    /// Waiting for a free slot in the resolution pool.
+    Queued,
+    /// Looking for peers in the DHT.
+    DhtLookupStarted,
+    /// Number of distinct peers found so far.
+    PeersFound { count: usize },
+    /// Connected to a peer to ask for the metadata.
+    ConnectedToPeer {
+        #[schema(value_type = String, example = "203.0.113.7:51413")]
+        peer: SocketAddr,
+    },
+    /// A metadata piece was received from a peer.
+    MetadataPieceReceived {
+        #[schema(value_type = String, example = "203.0.113.7:51413")]
+        peer: SocketAddr,
+        piece: usize,
+        pieces: usize,
+    },
+    /// The metadata matches the info-hash.
+    Verified,
+    /// The torrent file was stored in the cache.
+    Cached,
+    /// The resolution failed.
+    Failed { reason: String },
+}
+
+impl ResolutionEvent {
+    /// The name of the phase, like `peers_found`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::DhtLookupStarted => "dht_lookup_started",
+            Self::PeersFound { .. } => "peers_found",
+            Self::ConnectedToPeer { .. } => "connected_to_peer",
+            Self::MetadataPieceReceived { .. } => "metadata_piece_received",
+            Self::Verified => "verified",
+            Self::Cached => "cached",
+            Self::Failed { .. } => "failed",
+        }
+    }
+}
+
+/// Where the resolution events are sent. The default one discards them.
+#[derive(Clone, Default)]
+pub struct Progress {
+    sender: Option<UnboundedSender<ResolutionEvent>>,
+}
+
+impl Progress {
+    /// A progress and the receiver of its events. The receiver ends when the
+    /// progress and all its clones are dropped.
+    #[must_use]
+    pub fn channel() -> (Self, UnboundedReceiver<ResolutionEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        (
+            Self {
+                sender: Some(sender),
+            },
+            receiver,
+        )
+    }
+
+    /// Sends the event. It's discarded if nobody is listening.
+    pub fn report(&self, event: ResolutionEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Returns true if the events are sent somewhere. Resolvers can skip the
+    /// work needed only to report progress.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+impl PartialEq for Progress {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.sender, &other.sender) {
+            (Some(sender), Some(other)) => sender.same_channel(other),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Progress {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Progress, ResolutionEvent};
+
+    #[test]
+    fn it_should_send_the_events_to_the_receiver() {
+        let (progress, mut receiver) = Progress::channel();
+
+        progress.report(ResolutionEvent::PeersFound { count: 3 });
+        drop(progress);
+
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(ResolutionEvent::PeersFound { count: 3 })
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn it_should_discard_the_events_by_default() {
+        let progress = Progress::default();
+
+        progress.report(ResolutionEvent::Verified);
+
+        assert!(!progress.is_enabled());
+    }
+
+    #[test]
+    fn it_should_serialize_the_phase_with_the_event_fields() {
+        assert_eq!(
+            serde_json::to_string(&ResolutionEvent::MetadataPieceReceived {
+                peer: "203.0.113.7:51413".parse().unwrap(),
+                piece: 0,
+                pieces: 2
+            })
+            .unwrap(),
+            r#"{"phase":"metadata_piece_received","peer":"203.0.113.7:51413","piece":0,"pieces":2}"#
+        );
+        assert_eq!(ResolutionEvent::Verified.name(), "verified");
+    }
+}
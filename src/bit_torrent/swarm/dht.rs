@@ -0,0 +1,419 @@
+//! Estimates the swarm size with the DHT scrape extension ([BEP 33](https://www.bittorrent.org/beps/bep_0033.html)).
+//!
+//! The nodes that store the peers of a torrent answer the `get_peers` queries
+//! with the `scrape` flag with two bloom filters: one with the IPs of the
+//! seeders (`BFsd`) and one with the IPs of the leechers (`BFpe`). The filters
+//! of all the nodes are merged, and the number of bits set gives an estimate
+//! of the number of distinct IPs.
+//!
+//! `librqbit` does not support the extension, so the queries are sent from
+//! our own socket, starting from the nodes in its routing table.
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+use tokio::time::Instant;
+
+use super::{bind_udp_socket, ScrapeCounts, ScrapeError};
+use crate::bit_torrent::bencode::{self, Value};
+use crate::bit_torrent::info_hash::InfoHash;
+
+/// Size of the bloom filters in bits.
+const FILTER_BITS: u32 = 2048;
+
+/// Size of the bloom filters in bytes.
+pub const FILTER_LEN: usize = FILTER_BITS as usize / 8;
+
+/// Length of a node in the compact node info: ID, IPv4 address and port.
+const COMPACT_NODE_LEN: usize = 26;
+
+/// Maximum number of nodes queried for each scrape.
+const MAX_QUERIES: usize = 64;
+
+/// Maximum number of queries waiting for an answer.
+const PARALLEL_QUERIES: usize = 8;
+
+/// Time to wait for an answer. Queries without an answer after this time are
+/// given up.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The bloom filter used by the scrape extension. It has 2048 bits and two
+/// hash functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter([u8; FILTER_LEN]);
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        Self([0u8; FILTER_LEN])
+    }
+}
+
+impl BloomFilter {
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(Self)
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn insert(&mut self, ip: IpAddr) {
+        let hash = match ip {
+            IpAddr::V4(ip) => Sha1::digest(ip.octets()),
+            IpAddr::V6(ip) => Sha1::digest(ip.octets()),
+        };
+
+        for index in [
+            u16::from_le_bytes([hash[0], hash[1]]),
+            u16::from_le_bytes([hash[2], hash[3]]),
+        ] {
+            let index = usize::from(index) % (FILTER_BITS as usize);
+            self.0[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    pub fn union(&mut self, other: &Self) {
+        for (byte, other) in self.0.iter_mut().zip(other.0) {
+            *byte |= other;
+        }
+    }
+
+    /// The estimated number of IPs in the filter.
+    #[must_use]
+    pub fn estimated_len(&self) -> u64 {
+        let zeros: u32 = self.0.iter().map(|byte| byte.count_zeros()).sum();
+        if zeros == FILTER_BITS {
+            return 0;
+        }
+
+        let m = f64::from(FILTER_BITS);
+        let c = f64::from(zeros.min(FILTER_BITS - 1));
+        let estimate = (c / m).ln() / (2.0 * (1.0 - 1.0 / m).ln());
+
+        // The estimate is positive and below 8000 with 2048 bits.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let estimate = estimate.round() as u64;
+        estimate
+    }
+}
+
+/// A `get_peers` response.
+struct GetPeersResponse {
+    transaction_id: u16,
+    seeders: Option<BloomFilter>,
+    leechers: Option<BloomFilter>,
+    nodes: Vec<([u8; 20], SocketAddr)>,
+}
+
+/// Scrapes the DHT, starting from the given nodes, until no more nodes are
+/// found or the timeout expires.
+///
+/// # Errors
+///
+/// Will return an error if it can't open a socket or no node answers with the
+/// bloom filters.
+pub async fn scrape(
+    info_hash: &InfoHash,
+    nodes: Vec<SocketAddr>,
+    timeout: Duration,
+) -> Result<ScrapeCounts, ScrapeError> {
+    let deadline = Instant::now() + timeout;
+
+    // The socket can only reach nodes of the same IP version as the first
+    // one. The others are skipped, like the nodes that don't answer.
+    let Some(first_node) = nodes.first() else {
+        return Err(ScrapeError::NoDhtAnswer);
+    };
+    let socket = bind_udp_socket(*first_node).await?;
+    let node_id: [u8; 20] = rand::random();
+
+    let mut initial_nodes = nodes.into_iter();
+    // Nodes found in the answers, sorted by distance to the info-hash.
+    let mut found_nodes: Vec<([u8; 20], SocketAddr)> = Vec::new();
+    let mut queried: Vec<SocketAddr> = Vec::new();
+    let mut pending = 0;
+
+    let mut seeders = BloomFilter::default();
+    let mut leechers = BloomFilter::default();
+    let mut answers = 0;
+
+    let mut buf = vec![0u8; 1500];
+
+    while Instant::now() < deadline {
+        while pending < PARALLEL_QUERIES && queried.len() < MAX_QUERIES {
+            let next = initial_nodes
+                .next()
+                .or_else(|| (!found_nodes.is_empty()).then(|| found_nodes.remove(0).1));
+            let Some(address) = next else {
+                break;
+            };
+            if queried.contains(&address) {
+                continue;
+            }
+
+            let transaction_id = u16::try_from(queried.len()).unwrap_or(u16::MAX);
+            queried.push(address);
+            if socket
+                .send_to(
+                    &get_peers_query(transaction_id, &node_id, info_hash),
+                    address,
+                )
+                .await
+                .is_ok()
+            {
+                pending += 1;
+            }
+        }
+
+        if pending == 0 {
+            break;
+        }
+
+        let wait_until = deadline.min(Instant::now() + QUERY_TIMEOUT);
+        let Ok(received) = tokio::time::timeout_at(wait_until, socket.recv_from(&mut buf)).await
+        else {
+            pending = 0;
+            continue;
+        };
+        let Ok((len, from)) = received else {
+            continue;
+        };
+
+        let Some(response) = parse_get_peers_response(&buf[..len]) else {
+            continue;
+        };
+        if queried.get(usize::from(response.transaction_id)) != Some(&from) {
+            continue;
+        }
+        pending -= 1;
+
+        if let (Some(response_seeders), Some(response_leechers)) =
+            (&response.seeders, &response.leechers)
+        {
+            seeders.union(response_seeders);
+            leechers.union(response_leechers);
+            answers += 1;
+        }
+
+        for (id, address) in response.nodes {
+            if queried.contains(&address) || found_nodes.iter().any(|(_, found)| *found == address)
+            {
+                continue;
+            }
+            let distance = distance(&id, info_hash);
+            let position = found_nodes.partition_point(|(other, _)| *other < distance);
+            found_nodes.insert(position, (distance, address));
+        }
+    }
+
+    if answers == 0 {
+        return Err(ScrapeError::NoDhtAnswer);
+    }
+
+    Ok(ScrapeCounts {
+        seeders: seeders.estimated_len(),
+        leechers: leechers.estimated_len(),
+        completed: None,
+    })
+}
+
+fn get_peers_query(transaction_id: u16, node_id: &[u8; 20], info_hash: &InfoHash) -> Vec<u8> {
+    let mut query = Vec::new();
+    query.push(b'd');
+    bencode::encode_bytes(&mut query, b"a");
+    query.push(b'd');
+    bencode::encode_bytes(&mut query, b"id");
+    bencode::encode_bytes(&mut query, node_id);
+    bencode::encode_bytes(&mut query, b"info_hash");
+    bencode::encode_bytes(&mut query, &info_hash.0);
+    bencode::encode_bytes(&mut query, b"scrape");
+    bencode::encode_int(&mut query, 1);
+    query.push(b'e');
+    bencode::encode_bytes(&mut query, b"q");
+    bencode::encode_bytes(&mut query, b"get_peers");
+    bencode::encode_bytes(&mut query, b"t");
+    bencode::encode_bytes(&mut query, &transaction_id.to_be_bytes());
+    bencode::encode_bytes(&mut query, b"y");
+    bencode::encode_bytes(&mut query, b"q");
+    query.push(b'e');
+    query
+}
+
+fn parse_get_peers_response(packet: &[u8]) -> Option<GetPeersResponse> {
+    let (message, _) = bencode::decode(packet).ok()?;
+
+    if message.get(b"y").and_then(Value::as_bytes) != Some(b"r") {
+        return None;
+    }
+
+    let transaction_id = message
+        .get(b"t")
+        .and_then(Value::as_bytes)
+        .and_then(|t| t.try_into().ok())
+        .map(u16::from_be_bytes)?;
+
+    let response = message.get(b"r")?;
+    let filter = |key: &[u8]| {
+        response
+            .get(key)
+            .and_then(Value::as_bytes)
+            .and_then(BloomFilter::from_bytes)
+    };
+
+    let nodes = response
+        .get(b"nodes")
+        .and_then(Value::as_bytes)
+        .unwrap_or_default()
+        .chunks_exact(COMPACT_NODE_LEN)
+        .map(|node| {
+            let mut id = [0u8; 20];
+            id.copy_from_slice(&node[..20]);
+            let ip = Ipv4Addr::new(node[20], node[21], node[22], node[23]);
+            let port = u16::from_be_bytes([node[24], node[25]]);
+            (id, SocketAddr::from((ip, port)))
+        })
+        .collect();
+
+    Some(GetPeersResponse {
+        transaction_id,
+        seeders: filter(b"BFsd"),
+        leechers: filter(b"BFpe"),
+        nodes,
+    })
+}
+
+/// The XOR distance between the node ID and the info-hash.
+fn distance(id: &[u8; 20], info_hash: &InfoHash) -> [u8; 20] {
+    let mut distance = [0u8; 20];
+    for (i, byte) in distance.iter_mut().enumerate() {
+        *byte = id[i] ^ info_hash.0[i];
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+
+    use tokio::net::UdpSocket;
+
+    use super::{parse_get_peers_response, scrape, BloomFilter};
+    use crate::bit_torrent::bencode;
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::swarm::ScrapeCounts;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, last))
+    }
+
+    fn filter(ips: &[IpAddr]) -> BloomFilter {
+        let mut filter = BloomFilter::default();
+        for ip in ips {
+            filter.insert(*ip);
+        }
+        filter
+    }
+
+    /// Starts a node that answers the `get_peers` queries with the filters
+    /// and the nodes given.
+    async fn start_node(
+        seeders: BloomFilter,
+        leechers: BloomFilter,
+        nodes: Vec<SocketAddr>,
+    ) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            while let Ok((len, remote)) = socket.recv_from(&mut buf).await {
+                let (query, _) = bencode::decode(&buf[..len]).unwrap();
+                let transaction_id = query.get(b"t").unwrap().as_bytes().unwrap();
+
+                let mut compact_nodes = Vec::new();
+                for node in &nodes {
+                    let SocketAddr::V4(node) = node else {
+                        continue;
+                    };
+                    compact_nodes.extend_from_slice(&[0x11; 20]);
+                    compact_nodes.extend_from_slice(&node.ip().octets());
+                    compact_nodes.extend_from_slice(&node.port().to_be_bytes());
+                }
+
+                let mut response = b"d1:rd".to_vec();
+                bencode::encode_bytes(&mut response, b"BFpe");
+                bencode::encode_bytes(&mut response, leechers.as_bytes());
+                bencode::encode_bytes(&mut response, b"BFsd");
+                bencode::encode_bytes(&mut response, seeders.as_bytes());
+                bencode::encode_bytes(&mut response, b"id");
+                bencode::encode_bytes(&mut response, &[0x22; 20]);
+                bencode::encode_bytes(&mut response, b"nodes");
+                bencode::encode_bytes(&mut response, &compact_nodes);
+                response.push(b'e');
+                bencode::encode_bytes(&mut response, b"t");
+                bencode::encode_bytes(&mut response, transaction_id);
+                bencode::encode_bytes(&mut response, b"y");
+                bencode::encode_bytes(&mut response, b"r");
+                response.push(b'e');
+
+                socket.send_to(&response, remote).await.unwrap();
+            }
+        });
+
+        address
+    }
+
+    #[test]
+    fn it_should_estimate_the_number_of_ips_in_a_bloom_filter() {
+        assert_eq!(BloomFilter::default().estimated_len(), 0);
+        assert_eq!(filter(&[ip(1)]).estimated_len(), 1);
+
+        let ips: Vec<IpAddr> = (1..=100).map(ip).collect();
+        let estimate = filter(&ips).estimated_len();
+
+        assert!((90..=110).contains(&estimate), "estimate: {estimate}");
+    }
+
+    #[test]
+    fn it_should_parse_the_get_peers_responses() {
+        let response = b"d1:rd2:id20:aaaaaaaaaaaaaaaaaaaa5:nodes26:bbbbbbbbbbbbbbbbbbbb\x7f\x00\x00\x01\x1a\xe1e1:t2:\x00\x011:y1:re";
+
+        let response = parse_get_peers_response(response).unwrap();
+
+        assert_eq!(response.transaction_id, 1);
+        assert_eq!(response.seeders, None);
+        assert_eq!(
+            response.nodes,
+            vec![([b'b'; 20], "127.0.0.1:6881".parse().unwrap())]
+        );
+    }
+
+    #[test]
+    fn it_should_ignore_packets_nested_too_deeply() {
+        assert!(parse_get_peers_response(&vec![b'l'; 65_536]).is_none());
+    }
+
+    #[tokio::test]
+    async fn it_should_merge_the_filters_of_the_nodes_found() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let closest = start_node(filter(&[ip(2), ip(3)]), filter(&[ip(4)]), vec![]).await;
+        let first = start_node(filter(&[ip(1), ip(2)]), filter(&[]), vec![closest]).await;
+
+        let result = scrape(&torrent.info_hash, vec![first], Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            ScrapeCounts {
+                seeders: 3,
+                leechers: 1,
+                completed: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! Scrapes an HTTP tracker with the [BEP 48](https://www.bittorrent.org/beps/bep_0048.html)
+//! convention: the scrape URL is the announce URL with `announce` replaced
+//! by `scrape` in the last path segment.
+//!
+//! It also announces to the tracker, as described in [BEP 3](https://www.bittorrent.org/beps/bep_0003.html),
+//! to get the peers of a torrent. The peers can come in the [BEP 23](https://www.bittorrent.org/beps/bep_0023.html)
+//! compact format or as a list of dictionaries.
+use std::net::{IpAddr, SocketAddr};
+
+use reqwest::{Client, Response};
+
+use super::{ScrapeCounts, ScrapeError};
+use crate::bit_torrent::bencode::{self, Value};
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::peer_wire::{invalid_data, parse_compact_peers, parse_compact_peers6};
+
+/// Maximum size of a tracker response. A response for a single torrent is
+/// much smaller.
+const MAX_RESPONSE_LEN: usize = 64 * 1024;
+
+/// Number of peers asked for in the announces.
+const ANNOUNCE_NUM_WANT: usize = 50;
+
+/// Scrapes the tracker with the `http(s)://` announce URL.
+///
+/// # Errors
+///
+/// Will return an error if the tracker does not support scrapes, it can't be
+/// reached or it answers with an error.
+pub async fn scrape(
+    client: &Client,
+    tracker: &str,
+    info_hash: &InfoHash,
+) -> Result<ScrapeCounts, ScrapeError> {
+    let url = scrape_url(tracker, info_hash)
+        .ok_or_else(|| ScrapeError::UnsupportedTracker(tracker.to_string()))?;
+
+    let response = client.get(url).send().await?.error_for_status()?;
+    let body = read_body(response).await?;
+
+    parse_response(&body, info_hash)
+}
+
+/// Announces to the tracker with the `http(s)://` announce URL and returns
+/// the peers it knows. The announce says we have nothing yet, so the tracker
+/// includes the seeders.
+///
+/// # Errors
+///
+/// Will return an error if the tracker can't be reached or it answers with
+/// an error.
+pub async fn announce(
+    client: &Client,
+    tracker: &str,
+    info_hash: &InfoHash,
+    peer_id: &[u8; 20],
+    port: u16,
+) -> Result<Vec<SocketAddr>, ScrapeError> {
+    let url = announce_url(tracker, info_hash, peer_id, port);
+
+    let response = client.get(url).send().await?.error_for_status()?;
+    let body = read_body(response).await?;
+
+    parse_announce_response(&body)
+}
+
+/// Builds the scrape URL for the announce URL. It returns `None` if the
+/// tracker does not support scrapes.
+#[must_use]
+pub fn scrape_url(tracker: &str, info_hash: &InfoHash) -> Option<String> {
+    let (path, query) = match tracker.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (tracker, None),
+    };
+
+    let (base, last_segment) = path.rsplit_once('/')?;
+    let scrape_segment = last_segment
+        .strip_prefix("announce")
+        .map(|rest| format!("scrape{rest}"))?;
+
+    let mut url = format!("{base}/{scrape_segment}?");
+    if let Some(query) = query {
+        url.push_str(query);
+        url.push('&');
+    }
+    url.push_str("info_hash=");
+    url.push_str(&encode_bytes(&info_hash.0));
+
+    Some(url)
+}
+
+/// Builds the announce URL. The parameters are appended to the query the
+/// announce URL may already have.
+#[must_use]
+pub fn announce_url(tracker: &str, info_hash: &InfoHash, peer_id: &[u8; 20], port: u16) -> String {
+    let separator = if tracker.contains('?') { '&' } else { '?' };
+
+    format!(
+        "{tracker}{separator}info_hash={}&peer_id={}&port={port}&uploaded=0&downloaded=0&left=1&compact=1&numwant={ANNOUNCE_NUM_WANT}",
+        encode_bytes(&info_hash.0),
+        encode_bytes(peer_id),
+    )
+}
+
+/// Percent-encodes binary query values. Some trackers don't decode `+` as a
+/// space, so it's percent-encoded.
+fn encode_bytes(bytes: &[u8]) -> String {
+    form_urlencoded::byte_serialize(bytes)
+        .map(|encoded| if encoded == "+" { "%20" } else { encoded })
+        .collect()
+}
+
+/// Reads the response body, up to the maximum response size.
+async fn read_body(mut response: Response) -> Result<Vec<u8>, ScrapeError> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_RESPONSE_LEN as u64)
+    {
+        return Err(invalid_data("tracker response too large").into());
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_RESPONSE_LEN {
+            return Err(invalid_data("tracker response too large").into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+fn parse_announce_response(body: &[u8]) -> Result<Vec<SocketAddr>, ScrapeError> {
+    let (response, _) = bencode::decode(body).map_err(|err| invalid_data(&err.to_string()))?;
+
+    if let Some(reason) = response.get(b"failure reason").and_then(Value::as_bytes) {
+        return Err(ScrapeError::Tracker(
+            String::from_utf8_lossy(reason).to_string(),
+        ));
+    }
+
+    let mut peers = match response.get(b"peers") {
+        Some(Value::Bytes(compact)) => parse_compact_peers(compact),
+        Some(Value::List(peers)) => peers.iter().filter_map(dictionary_peer).collect(),
+        _ => Vec::new(),
+    };
+
+    if let Some(compact) = response.get(b"peers6").and_then(Value::as_bytes) {
+        peers.extend(parse_compact_peers6(compact));
+    }
+
+    Ok(peers)
+}
+
+/// A peer in the non-compact format: a dictionary with the `ip` and `port`.
+fn dictionary_peer(peer: &Value) -> Option<SocketAddr> {
+    let ip: IpAddr = std::str::from_utf8(peer.get(b"ip")?.as_bytes()?)
+        .ok()?
+        .parse()
+        .ok()?;
+    let port = u16::try_from(peer.get(b"port")?.as_int()?).ok()?;
+
+    Some(SocketAddr::new(ip, port))
+}
+
+fn parse_response(body: &[u8], info_hash: &InfoHash) -> Result<ScrapeCounts, ScrapeError> {
+    let (response, _) = bencode::decode(body).map_err(|err| invalid_data(&err.to_string()))?;
+
+    if let Some(reason) = response.get(b"failure reason").and_then(Value::as_bytes) {
+        return Err(ScrapeError::Tracker(
+            String::from_utf8_lossy(reason).to_string(),
+        ));
+    }
+
+    let file = response
+        .get(b"files")
+        .and_then(|files| files.get(&info_hash.0))
+        .ok_or(ScrapeError::TorrentNotFound)?;
+
+    let count = |key: &[u8]| {
+        file.get(key)
+            .and_then(Value::as_int)
+            .and_then(|count| u64::try_from(count).ok())
+    };
+
+    Ok(ScrapeCounts {
+        seeders: count(b"complete").ok_or_else(|| invalid_data("scrape without complete"))?,
+        leechers: count(b"incomplete").ok_or_else(|| invalid_data("scrape without incomplete"))?,
+        completed: count(b"downloaded"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+
+    use axum::extract::RawQuery;
+    use axum::routing::get;
+    use axum::Router;
+
+    use super::{announce, announce_url, scrape, scrape_url, MAX_RESPONSE_LEN};
+    use crate::bit_torrent::bencode;
+    use crate::bit_torrent::info_hash::InfoHash;
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::swarm::{ScrapeCounts, ScrapeError};
+
+    fn info_hash() -> InfoHash {
+        InfoHash([0x61; 20])
+    }
+
+    #[test]
+    fn it_should_build_the_scrape_url_from_the_announce_url() {
+        assert_eq!(
+            scrape_url("https://tracker.example.com/announce", &info_hash()).unwrap(),
+            "https://tracker.example.com/scrape?info_hash=aaaaaaaaaaaaaaaaaaaa"
+        );
+        assert_eq!(
+            scrape_url(
+                "http://tracker.example.com:6969/key/announce.php?passkey=1",
+                &info_hash()
+            )
+            .unwrap(),
+            "http://tracker.example.com:6969/key/scrape.php?passkey=1&info_hash=aaaaaaaaaaaaaaaaaaaa"
+        );
+    }
+
+    #[test]
+    fn it_should_not_build_a_scrape_url_if_the_tracker_does_not_support_it() {
+        assert_eq!(
+            scrape_url("http://tracker.example.com/a", &info_hash()),
+            None
+        );
+    }
+
+    #[test]
+    fn it_should_build_the_announce_url() {
+        assert_eq!(
+            announce_url(
+                "http://tracker.example.com:6969/announce?passkey=1",
+                &info_hash(),
+                &[0x62; 20],
+                6881
+            ),
+            "http://tracker.example.com:6969/announce?passkey=1&info_hash=aaaaaaaaaaaaaaaaaaaa&peer_id=bbbbbbbbbbbbbbbbbbbb&port=6881&uploaded=0&downloaded=0&left=1&compact=1&numwant=50"
+        );
+    }
+
+    /// Starts a tracker that answers announces with the given bencoded body.
+    fn start_announce_tracker(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let app = Router::new().route(
+            "/announce",
+            get(|RawQuery(query): RawQuery| async move {
+                assert!(query.unwrap().contains("compact=1"));
+                body
+            }),
+        );
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{address}/announce")
+    }
+
+    #[tokio::test]
+    async fn it_should_get_the_peers_in_the_compact_format_from_an_announce() {
+        let mut body = b"d8:intervali1800e5:peers".to_vec();
+        bencode::encode_bytes(&mut body, &[203, 0, 113, 7, 0xc8, 0xd5]);
+        body.extend_from_slice(b"6:peers6");
+        bencode::encode_bytes(
+            &mut body,
+            &[
+                0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x1a, 0xe1,
+            ],
+        );
+        body.push(b'e');
+        let tracker = start_announce_tracker(body);
+
+        let peers = announce(
+            &reqwest::Client::new(),
+            &tracker,
+            &info_hash(),
+            &[0x62; 20],
+            6881,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            peers,
+            vec![
+                "203.0.113.7:51413".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::1]:6881".parse::<SocketAddr>().unwrap()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_get_the_peers_in_the_dictionary_format_from_an_announce() {
+        let body = b"d8:intervali1800e5:peersld2:ip11:203.0.113.77:peer id20:bbbbbbbbbbbbbbbbbbbb4:porti51413eeee".to_vec();
+        let tracker = start_announce_tracker(body);
+
+        let peers = announce(
+            &reqwest::Client::new(),
+            &tracker,
+            &info_hash(),
+            &[0x62; 20],
+            6881,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            peers,
+            vec!["203.0.113.7:51413".parse::<SocketAddr>().unwrap()]
+        );
+    }
+
+    /// Starts a tracker that answers scrapes with the given bencoded body.
+    fn start_tracker(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let app = Router::new().route(
+            "/scrape",
+            get(|RawQuery(query): RawQuery| async move {
+                assert!(query.unwrap().starts_with("info_hash="));
+                body
+            }),
+        );
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{address}/announce")
+    }
+
+    #[tokio::test]
+    async fn it_should_scrape_an_http_tracker() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let mut body = b"d5:filesd".to_vec();
+        bencode::encode_bytes(&mut body, &torrent.info_hash.0);
+        body.extend_from_slice(b"d8:completei7e10:downloadedi30e10:incompletei3eeee");
+        let tracker = start_tracker(body);
+
+        let result = scrape(&reqwest::Client::new(), &tracker, &torrent.info_hash)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            ScrapeCounts {
+                seeders: 7,
+                leechers: 3,
+                completed: Some(30),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_when_the_tracker_answers_with_a_failure_reason() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let tracker = start_tracker(b"d14:failure reason9:forbiddene".to_vec());
+
+        let result = scrape(&reqwest::Client::new(), &tracker, &torrent.info_hash).await;
+
+        assert!(matches!(result, Err(ScrapeError::Tracker(reason)) if reason == "forbidden"));
+    }
+
+    #[tokio::test]
+    async fn it_should_reject_responses_larger_than_the_maximum() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let tracker = start_tracker(vec![b'0'; MAX_RESPONSE_LEN + 1]);
+
+        let result = scrape(&reqwest::Client::new(), &tracker, &torrent.info_hash).await;
+
+        assert!(matches!(result, Err(ScrapeError::Io(_))));
+    }
+}
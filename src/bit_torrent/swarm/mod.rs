@@ -0,0 +1,301 @@
+//! Estimates how many seeders and leechers a torrent has.
+//!
+//! The numbers come from two kinds of sources:
+//!
+//! - Tracker scrapes, for the configured UDP ([BEP 15](https://www.bittorrent.org/beps/bep_0015.html))
+//!   and HTTP ([BEP 48](https://www.bittorrent.org/beps/bep_0048.html)) trackers.
+//! - The DHT scrape bloom filters ([BEP 33](https://www.bittorrent.org/beps/bep_0033.html)),
+//!   if the resolver uses the DHT.
+//!
+//! Each source only knows part of the swarm, so the estimate is the highest
+//! count among them. Results are cached for a while, so repeated requests
+//! don't flood the trackers.
+pub mod dht;
+pub mod http_tracker;
+pub mod udp_tracker;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use futures_util::future::join_all;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::net::UdpSocket;
+use utoipa::ToSchema;
+
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::config::Swarm as SwarmConfig;
+
+/// The counts reported by a source.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrapeCounts {
+    pub seeders: u64,
+    pub leechers: u64,
+    /// Number of times the torrent has been downloaded. Only trackers know it.
+    pub completed: Option<u64>,
+}
+
+#[derive(Error, Debug)]
+pub enum ScrapeError {
+    #[error("Timed out")]
+    Timeout,
+    #[error("Unsupported tracker: {0}")]
+    UnsupportedTracker(String),
+    #[error("The tracker answered with an error: {0}")]
+    Tracker(String),
+    #[error("The torrent is not in the scrape response")]
+    TorrentNotFound,
+    #[error("No DHT node answered the scrape")]
+    NoDhtAnswer,
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// Where the counts come from.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SwarmSource {
+    Tracker,
+    Dht,
+}
+
+/// The result of scraping one source.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SourceScrape {
+    pub source: SwarmSource,
+    /// The tracker URL. It's `None` for the DHT.
+    pub tracker: Option<String>,
+    #[serde(flatten)]
+    pub counts: Option<ScrapeCounts>,
+    /// Why the source could not be scraped.
+    pub error: Option<String>,
+}
+
+/// The estimate for a torrent.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SwarmScrape {
+    #[schema(value_type = String, example = "443c7602b4fde83d1154d6d9da48808418b181b6")]
+    pub info_hash: InfoHash,
+    /// The highest count among the sources. It's `None` if no source could
+    /// be scraped.
+    pub seeders: Option<u64>,
+    pub leechers: Option<u64>,
+    pub sources: Vec<SourceScrape>,
+    /// Seconds since the Unix epoch when the sources were scraped.
+    pub scraped_at: u64,
+}
+
+pub struct SwarmScraper {
+    config: SwarmConfig,
+    http_client: reqwest::Client,
+    recent_scrapes: Mutex<HashMap<InfoHash, (Instant, Arc<SwarmScrape>)>>,
+}
+
+impl SwarmScraper {
+    #[must_use]
+    pub fn new(config: SwarmConfig) -> Self {
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+            recent_scrapes: Mutex::default(),
+        }
+    }
+
+    /// Scrapes the trackers and, if `dht_nodes` is given, the DHT starting
+    /// from those nodes. It returns a recent result for the same info-hash
+    /// if there is one.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the cache lock is poisoned.
+    pub async fn scrape(
+        &self,
+        info_hash: &InfoHash,
+        trackers: &[String],
+        dht_nodes: Option<Vec<SocketAddr>>,
+    ) -> Arc<SwarmScrape> {
+        if let Some(scrape) = self.recent_scrape(info_hash) {
+            return scrape;
+        }
+
+        let scraped_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        let tracker_scrapes = trackers
+            .iter()
+            .take(self.config.max_trackers)
+            .map(|tracker| self.scrape_tracker(info_hash, tracker));
+
+        let mut sources: Vec<SourceScrape> = join_all(tracker_scrapes).await;
+
+        if let Some(dht_nodes) = dht_nodes {
+            let result = dht::scrape(info_hash, dht_nodes, self.config.scrape_timeout).await;
+            sources.push(source_scrape(SwarmSource::Dht, None, result));
+        }
+
+        let successful = || sources.iter().filter_map(|source| source.counts);
+
+        let scrape = Arc::new(SwarmScrape {
+            info_hash: *info_hash,
+            seeders: successful().map(|counts| counts.seeders).max(),
+            leechers: successful().map(|counts| counts.leechers).max(),
+            sources,
+            scraped_at,
+        });
+
+        let mut recent_scrapes = self
+            .recent_scrapes
+            .lock()
+            .expect("lock should not be poisoned");
+        recent_scrapes.retain(|_, (scraped_at, _)| scraped_at.elapsed() < self.config.cache_ttl);
+        recent_scrapes.insert(*info_hash, (Instant::now(), scrape.clone()));
+
+        scrape
+    }
+
+    async fn scrape_tracker(&self, info_hash: &InfoHash, tracker: &str) -> SourceScrape {
+        let scrape = async {
+            if tracker.starts_with("udp://") {
+                udp_tracker::scrape(tracker, info_hash).await
+            } else if tracker.starts_with("http://") || tracker.starts_with("https://") {
+                http_tracker::scrape(&self.http_client, tracker, info_hash).await
+            } else {
+                Err(ScrapeError::UnsupportedTracker(tracker.to_string()))
+            }
+        };
+
+        let result = tokio::time::timeout(self.config.scrape_timeout, scrape)
+            .await
+            .unwrap_or(Err(ScrapeError::Timeout));
+
+        source_scrape(SwarmSource::Tracker, Some(tracker.to_string()), result)
+    }
+
+    fn recent_scrape(&self, info_hash: &InfoHash) -> Option<Arc<SwarmScrape>> {
+        let recent_scrapes = self
+            .recent_scrapes
+            .lock()
+            .expect("lock should not be poisoned");
+
+        recent_scrapes
+            .get(info_hash)
+            .filter(|(scraped_at, _)| scraped_at.elapsed() < self.config.cache_ttl)
+            .map(|(_, scrape)| scrape.clone())
+    }
+}
+
+/// Opens a UDP socket on an ephemeral port to send datagrams to `target`. It
+/// is bound to the unspecified address of the same IP version, because a
+/// socket bound to an IPv4 address can't reach IPv6 addresses.
+async fn bind_udp_socket(target: SocketAddr) -> io::Result<UdpSocket> {
+    let unspecified: IpAddr = if target.is_ipv6() {
+        Ipv6Addr::UNSPECIFIED.into()
+    } else {
+        Ipv4Addr::UNSPECIFIED.into()
+    };
+
+    UdpSocket::bind((unspecified, 0)).await
+}
+
+fn source_scrape(
+    source: SwarmSource,
+    tracker: Option<String>,
+    result: Result<ScrapeCounts, ScrapeError>,
+) -> SourceScrape {
+    match result {
+        Ok(counts) => SourceScrape {
+            source,
+            tracker,
+            counts: Some(counts),
+            error: None,
+        },
+        Err(err) => SourceScrape {
+            source,
+            tracker,
+            counts: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ScrapeCounts, SwarmScraper, SwarmSource};
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::test_tracker::TestTracker;
+    use crate::config::Swarm as SwarmConfig;
+
+    fn swarm_scraper() -> SwarmScraper {
+        SwarmScraper::new(SwarmConfig {
+            scrape_timeout: Duration::from_secs(1),
+            max_trackers: 10,
+            cache_ttl: Duration::from_mins(1),
+        })
+    }
+
+    fn counts(seeders: u64, leechers: u64, completed: u64) -> ScrapeCounts {
+        ScrapeCounts {
+            seeders,
+            leechers,
+            completed: Some(completed),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_take_the_highest_counts_among_the_trackers() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let first = TestTracker::start(counts(5, 1, 20)).await.unwrap();
+        let second = TestTracker::start(counts(3, 4, 10)).await.unwrap();
+
+        let scrape = swarm_scraper()
+            .scrape(&torrent.info_hash, &[first.url(), second.url()], None)
+            .await;
+
+        assert_eq!(scrape.seeders, Some(5));
+        assert_eq!(scrape.leechers, Some(4));
+        assert_eq!(scrape.sources.len(), 2);
+        assert_eq!(scrape.sources[1].source, SwarmSource::Tracker);
+        assert_eq!(scrape.sources[1].counts, Some(counts(3, 4, 10)));
+    }
+
+    #[tokio::test]
+    async fn it_should_report_the_sources_that_fail() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let scrape = swarm_scraper()
+            .scrape(
+                &torrent.info_hash,
+                &["wss://tracker.example.com/announce".to_string()],
+                Some(vec![]),
+            )
+            .await;
+
+        assert_eq!(scrape.seeders, None);
+        assert_eq!(scrape.sources.len(), 2);
+        assert!(scrape.sources.iter().all(|source| source.error.is_some()));
+        assert_eq!(scrape.sources[1].source, SwarmSource::Dht);
+    }
+
+    #[tokio::test]
+    async fn it_should_reuse_recent_scrapes() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let tracker = TestTracker::start(counts(5, 1, 20)).await.unwrap();
+        let swarm_scraper = swarm_scraper();
+
+        swarm_scraper
+            .scrape(&torrent.info_hash, &[tracker.url()], None)
+            .await;
+
+        let scrape = swarm_scraper.scrape(&torrent.info_hash, &[], None).await;
+
+        assert_eq!(scrape.seeders, Some(5));
+    }
+}
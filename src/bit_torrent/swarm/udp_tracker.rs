@@ -0,0 +1,249 @@
+//! Scrapes a UDP tracker with the [BEP 15](https://www.bittorrent.org/beps/bep_0015.html)
+//! protocol: a `connect` request to get a connection ID, followed by a
+//! `scrape` request for the info-hash. Announces work the same way, with an
+//! `announce` request that returns the peers.
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::UdpSocket;
+
+use super::{bind_udp_socket, ScrapeCounts, ScrapeError};
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::peer_wire::{invalid_data, parse_compact_peers, parse_compact_peers6};
+
+/// Magic constant sent in the `connect` requests.
+pub const PROTOCOL_ID: u64 = 0x0417_2710_1980;
+
+pub const ACTION_CONNECT: u32 = 0;
+pub const ACTION_ANNOUNCE: u32 = 1;
+pub const ACTION_SCRAPE: u32 = 2;
+pub const ACTION_ERROR: u32 = 3;
+
+/// Number of peers asked for in the announces.
+const ANNOUNCE_NUM_WANT: u32 = 50;
+
+/// Time to wait for an answer before sending the request again. UDP packets
+/// can be lost.
+const RETRANSMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Scrapes the tracker at the `udp://host:port` URL.
+///
+/// # Errors
+///
+/// Will return an error if the URL is not valid, the tracker can't be reached
+/// or it answers with an error.
+pub async fn scrape(tracker: &str, info_hash: &InfoHash) -> Result<ScrapeCounts, ScrapeError> {
+    let (socket, connection_id) = connect(tracker).await?;
+
+    let transaction_id = rand::random::<u32>();
+    let mut scrape = Vec::with_capacity(36);
+    scrape.extend_from_slice(&connection_id);
+    scrape.extend_from_slice(&ACTION_SCRAPE.to_be_bytes());
+    scrape.extend_from_slice(&transaction_id.to_be_bytes());
+    scrape.extend_from_slice(&info_hash.0);
+
+    let response = request(&socket, &scrape, ACTION_SCRAPE, transaction_id).await?;
+    let [seeders, completed, leechers] = [0, 4, 8].map(|offset| read_u32(&response, offset));
+
+    Ok(ScrapeCounts {
+        seeders: u64::from(seeders.ok_or_else(|| invalid_data("scrape response too short"))?),
+        leechers: u64::from(leechers.ok_or_else(|| invalid_data("scrape response too short"))?),
+        completed: completed.map(u64::from),
+    })
+}
+
+/// Announces to the tracker at the `udp://host:port` URL and returns the
+/// peers it knows. The announce says we have nothing yet, so the tracker
+/// includes the seeders.
+///
+/// # Errors
+///
+/// Will return an error if the URL is not valid, the tracker can't be reached
+/// or it answers with an error.
+pub async fn announce(
+    tracker: &str,
+    info_hash: &InfoHash,
+    peer_id: &[u8; 20],
+    port: u16,
+) -> Result<Vec<SocketAddr>, ScrapeError> {
+    let (socket, connection_id) = connect(tracker).await?;
+
+    let transaction_id = rand::random::<u32>();
+    let mut announce = Vec::with_capacity(98);
+    announce.extend_from_slice(&connection_id);
+    announce.extend_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+    announce.extend_from_slice(&transaction_id.to_be_bytes());
+    announce.extend_from_slice(&info_hash.0);
+    announce.extend_from_slice(peer_id);
+    announce.extend_from_slice(&0u64.to_be_bytes()); // downloaded
+    announce.extend_from_slice(&1u64.to_be_bytes()); // left
+    announce.extend_from_slice(&0u64.to_be_bytes()); // uploaded
+    announce.extend_from_slice(&0u32.to_be_bytes()); // event: none
+    announce.extend_from_slice(&0u32.to_be_bytes()); // IP: the sender's
+    announce.extend_from_slice(&rand::random::<u32>().to_be_bytes()); // key
+    announce.extend_from_slice(&ANNOUNCE_NUM_WANT.to_be_bytes());
+    announce.extend_from_slice(&port.to_be_bytes());
+
+    let response = request(&socket, &announce, ACTION_ANNOUNCE, transaction_id).await?;
+
+    // The interval, leechers and seeders come before the peers. IPv6
+    // trackers answer with IPv6 peers.
+    let peers = response
+        .get(12..)
+        .ok_or_else(|| invalid_data("announce response too short"))?;
+
+    Ok(if socket.peer_addr()?.is_ipv6() {
+        parse_compact_peers6(peers)
+    } else {
+        parse_compact_peers(peers)
+    })
+}
+
+/// Opens a socket to the tracker and gets a connection ID.
+async fn connect(tracker: &str) -> Result<(UdpSocket, [u8; 8]), ScrapeError> {
+    let url =
+        Url::parse(tracker).map_err(|_| ScrapeError::UnsupportedTracker(tracker.to_string()))?;
+    let (Some(host), Some(port)) = (url.host_str(), url.port()) else {
+        return Err(ScrapeError::UnsupportedTracker(tracker.to_string()));
+    };
+
+    // IPv6 hosts are written in brackets in URLs.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let address = tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "tracker host not found"))?;
+
+    let socket = bind_udp_socket(address).await?;
+    socket.connect(address).await?;
+
+    let transaction_id = rand::random::<u32>();
+    let mut connect = Vec::with_capacity(16);
+    connect.extend_from_slice(&PROTOCOL_ID.to_be_bytes());
+    connect.extend_from_slice(&ACTION_CONNECT.to_be_bytes());
+    connect.extend_from_slice(&transaction_id.to_be_bytes());
+
+    let response = request(&socket, &connect, ACTION_CONNECT, transaction_id).await?;
+    let connection_id: [u8; 8] = response
+        .get(..8)
+        .and_then(|id| id.try_into().ok())
+        .ok_or_else(|| invalid_data("connect response too short"))?;
+
+    Ok((socket, connection_id))
+}
+
+/// Sends the request until an answer with the same transaction ID arrives.
+/// It returns the body of the answer, after the action and the transaction
+/// ID.
+async fn request(
+    socket: &UdpSocket,
+    packet: &[u8],
+    action: u32,
+    transaction_id: u32,
+) -> Result<Vec<u8>, ScrapeError> {
+    let mut buf = vec![0u8; 1024];
+
+    loop {
+        socket.send(packet).await?;
+
+        let Ok(received) = tokio::time::timeout(RETRANSMIT_INTERVAL, socket.recv(&mut buf)).await
+        else {
+            continue;
+        };
+        let response = &buf[..received?];
+
+        if read_u32(response, 4) != Some(transaction_id) {
+            continue;
+        }
+
+        match read_u32(response, 0) {
+            Some(received_action) if received_action == action => {
+                return Ok(response[8..].to_vec());
+            }
+            Some(ACTION_ERROR) => {
+                return Err(ScrapeError::Tracker(
+                    String::from_utf8_lossy(&response[8..]).to_string(),
+                ));
+            }
+            _ => return Err(invalid_data("unexpected tracker action").into()),
+        }
+    }
+}
+
+/// Reads a big-endian `u32` at the offset.
+pub(crate) fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    buf.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{announce, scrape};
+    use crate::bit_torrent::resolver::fake::sample_torrent;
+    use crate::bit_torrent::swarm::{ScrapeCounts, ScrapeError};
+    use crate::bit_torrent::test_tracker::TestTracker;
+
+    #[tokio::test]
+    async fn it_should_scrape_a_udp_tracker() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let counts = ScrapeCounts {
+            seeders: 5,
+            leechers: 2,
+            completed: Some(40),
+        };
+        let tracker = TestTracker::start(counts).await.unwrap();
+
+        let result = scrape(&tracker.url(), &torrent.info_hash).await.unwrap();
+
+        assert_eq!(result, counts);
+        assert_eq!(tracker.scrapes(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_scrape_a_udp_tracker_on_an_ipv6_address() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let counts = ScrapeCounts {
+            seeders: 5,
+            leechers: 2,
+            completed: Some(40),
+        };
+        let tracker = TestTracker::start_ipv6(counts).await.unwrap();
+
+        let result = scrape(&tracker.url(), &torrent.info_hash).await.unwrap();
+
+        assert_eq!(result, counts);
+    }
+
+    #[tokio::test]
+    async fn it_should_get_the_peers_from_a_udp_tracker_announce() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+        let peers = vec!["203.0.113.7:51413".parse().unwrap()];
+        let counts = ScrapeCounts {
+            seeders: 1,
+            leechers: 0,
+            completed: None,
+        };
+        let tracker = TestTracker::start_with_peers(counts, peers.clone())
+            .await
+            .unwrap();
+
+        let result = announce(&tracker.url(), &torrent.info_hash, &[0x62; 20], 6881)
+            .await
+            .unwrap();
+
+        assert_eq!(result, peers);
+        assert_eq!(tracker.announces(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_for_urls_without_a_port() {
+        let torrent = sample_torrent("sample.txt", b"Hello, World!");
+
+        let result = scrape("udp://tracker.example.com/announce", &torrent.info_hash).await;
+
+        assert!(matches!(result, Err(ScrapeError::UnsupportedTracker(_))));
+    }
+}
@@ -0,0 +1,228 @@
+//! An in-process peer for tests. It serves the metadata of the torrents it
+//! has been given with the [BEP 9](https://www.bittorrent.org/beps/bep_0009.html)
+//! `ut_metadata` extension, so resolutions can be tested without the
+//! internet.
+//!
+//! Pass its address as an initial peer to get the metadata from it:
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use torrust_hash2torrent::bit_torrent::resolver::fake::sample_torrent;
+//! use torrust_hash2torrent::bit_torrent::resolver::ResolveOptions;
+//! use torrust_hash2torrent::bit_torrent::test_peer::TestPeer;
+//!
+//! let torrent = sample_torrent("sample.txt", b"Hello, World!");
+//! let peer = TestPeer::start(vec![torrent]).await?;
+//!
+//! let options = ResolveOptions {
+//!     initial_peers: vec![peer.address()],
+//!     ..Default::default()
+//! };
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! It only implements the messages needed to send the metadata: the
+//! handshake, the [BEP 10](https://www.bittorrent.org/beps/bep_0010.html)
+//! extension handshake and the `ut_metadata` requests. It does not have any
+//! piece of the torrent contents. It can also tell other peers about a list
+//! of peers with a [BEP 11](https://www.bittorrent.org/beps/bep_0011.html)
+//! `ut_pex` message.
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::bit_torrent::bencode;
+use crate::bit_torrent::info_hash::InfoHash;
+use crate::bit_torrent::peer_wire::{
+    self, invalid_data, UtMetadata, EXTENDED_HANDSHAKE_ID, METADATA_PIECE_LEN,
+};
+use crate::bit_torrent::resolver::TorrentMetadata;
+
+const PEER_ID: &[u8; 20] = b"-TH0001-test-peer-00";
+
+/// The `ut_metadata` message ID this peer expects in the messages it receives.
+const UT_METADATA_ID: u8 = 1;
+
+/// The `ut_pex` message ID this peer announces when it has peers to send. It
+/// ignores the `ut_pex` messages it receives.
+const UT_PEX_ID: u8 = 2;
+
+/// A peer listening on an ephemeral local port. It stops when dropped.
+pub struct TestPeer {
+    address: SocketAddr,
+    served_pieces: Arc<AtomicUsize>,
+    server: JoinHandle<()>,
+}
+
+impl TestPeer {
+    /// Starts a peer that serves the metadata of the given torrents.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't bind a local port.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a torrent file does not contain the info dictionary.
+    pub async fn start(torrents: Vec<TorrentMetadata>) -> io::Result<Self> {
+        Self::start_with_pex(torrents, Vec::new()).await
+    }
+
+    /// Starts a peer that serves the metadata of the given torrents and
+    /// sends the given peers in a `ut_pex` message to the peers that support
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't bind a local port.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a torrent file does not contain the info dictionary.
+    pub async fn start_with_pex(
+        torrents: Vec<TorrentMetadata>,
+        pex_peers: Vec<SocketAddr>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+
+        let info_dicts: HashMap<InfoHash, Bytes> = torrents
+            .into_iter()
+            .map(|torrent| {
+                let info = bencode::raw_dict_value(&torrent.torrent_bytes, b"info")
+                    .ok()
+                    .flatten()
+                    .expect("the torrent file should contain the info dictionary");
+                (torrent.info_hash, torrent.torrent_bytes.slice_ref(info))
+            })
+            .collect();
+        let info_dicts = Arc::new(info_dicts);
+        let pex_peers = Arc::new(pex_peers);
+
+        let served_pieces = Arc::new(AtomicUsize::new(0));
+
+        let server = tokio::spawn({
+            let served_pieces = served_pieces.clone();
+            async move {
+                while let Ok((stream, remote)) = listener.accept().await {
+                    let info_dicts = info_dicts.clone();
+                    let pex_peers = pex_peers.clone();
+                    let served_pieces = served_pieces.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) =
+                            serve(stream, &info_dicts, &pex_peers, &served_pieces).await
+                        {
+                            debug!("test peer connection with {remote} closed: {err}");
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            served_pieces,
+            server,
+        })
+    }
+
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Number of metadata pieces sent to other peers.
+    #[must_use]
+    pub fn served_pieces(&self) -> usize {
+        self.served_pieces.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for TestPeer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    info_dicts: &HashMap<InfoHash, Bytes>,
+    pex_peers: &[SocketAddr],
+    served_pieces: &AtomicUsize,
+) -> io::Result<()> {
+    let handshake = peer_wire::read_handshake(&mut stream).await?;
+
+    let Some(info) = info_dicts.get(&handshake.info_hash) else {
+        return Err(invalid_data("unknown info-hash"));
+    };
+
+    stream
+        .write_all(&peer_wire::handshake(&handshake.info_hash, PEER_ID))
+        .await?;
+    stream
+        .write_all(&peer_wire::extended_handshake(
+            UT_METADATA_ID,
+            (!pex_peers.is_empty()).then_some(UT_PEX_ID),
+            Some(info.len()),
+        ))
+        .await?;
+
+    // The ID the remote peer wants in the `ut_metadata` messages we send.
+    let mut remote_ut_metadata_id = None;
+
+    loop {
+        let Some(message) = peer_wire::read_message(&mut stream).await? else {
+            continue;
+        };
+
+        match peer_wire::as_extended(&message) {
+            Some((EXTENDED_HANDSHAKE_ID, payload)) => {
+                let handshake = peer_wire::parse_extended_handshake(payload)?;
+                remote_ut_metadata_id = handshake.ut_metadata_id;
+
+                if let Some(remote_ut_pex_id) =
+                    handshake.ut_pex_id.filter(|_| !pex_peers.is_empty())
+                {
+                    stream
+                        .write_all(&peer_wire::ut_pex_message(remote_ut_pex_id, pex_peers))
+                        .await?;
+                }
+            }
+            Some((UT_METADATA_ID, payload)) => {
+                let Some(remote_id) = remote_ut_metadata_id else {
+                    return Err(invalid_data("ut_metadata request before handshake"));
+                };
+
+                // Only requests are expected.
+                let UtMetadata::Request { piece } = peer_wire::parse_ut_metadata(payload)? else {
+                    continue;
+                };
+
+                let response = match info.chunks(METADATA_PIECE_LEN).nth(piece) {
+                    Some(data) => UtMetadata::Data { piece, data },
+                    None => UtMetadata::Reject { piece },
+                };
+
+                stream
+                    .write_all(&peer_wire::ut_metadata_message(
+                        remote_id,
+                        &response,
+                        info.len(),
+                    ))
+                    .await?;
+
+                served_pieces.fetch_add(1, Ordering::SeqCst);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! An in-process UDP tracker for tests. It answers the [BEP 15](https://www.bittorrent.org/beps/bep_0015.html)
+//! scrape requests with the counts it has been given, and the announces with
+//! the peers it has been given, for any info-hash, so swarm estimates and
+//! peer lookups can be tested without the internet.
+//!
+//! Announces are not recorded: the peers list never changes.
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+use crate::bit_torrent::swarm::udp_tracker::{
+    read_u32, ACTION_ANNOUNCE, ACTION_CONNECT, ACTION_SCRAPE, PROTOCOL_ID,
+};
+use crate::bit_torrent::swarm::ScrapeCounts;
+
+const CONNECTION_ID: u64 = 0x5445_5354_5452_4B52;
+
+/// A tracker listening on an ephemeral local port. It stops when dropped.
+pub struct TestTracker {
+    address: SocketAddr,
+    scrapes: Arc<AtomicUsize>,
+    announces: Arc<AtomicUsize>,
+    server: JoinHandle<()>,
+}
+
+impl TestTracker {
+    /// Starts a tracker that reports the given counts.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't bind a local port.
+    pub async fn start(counts: ScrapeCounts) -> io::Result<Self> {
+        Self::start_with_peers(counts, Vec::new()).await
+    }
+
+    /// Starts a tracker that reports the given counts and answers the
+    /// announces with the given IPv4 peers.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't bind a local port.
+    pub async fn start_with_peers(
+        counts: ScrapeCounts,
+        peers: Vec<SocketAddr>,
+    ) -> io::Result<Self> {
+        Self::start_on("127.0.0.1:0", counts, peers).await
+    }
+
+    /// Starts a tracker on the IPv6 loopback address that reports the given
+    /// counts.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't bind a local port.
+    pub async fn start_ipv6(counts: ScrapeCounts) -> io::Result<Self> {
+        Self::start_on("[::1]:0", counts, Vec::new()).await
+    }
+
+    async fn start_on(
+        address: &str,
+        counts: ScrapeCounts,
+        peers: Vec<SocketAddr>,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address).await?;
+        let address = socket.local_addr()?;
+
+        let scrapes = Arc::new(AtomicUsize::new(0));
+        let announces = Arc::new(AtomicUsize::new(0));
+
+        let server = tokio::spawn({
+            let scrapes = scrapes.clone();
+            let announces = announces.clone();
+            async move {
+                let mut buf = [0u8; 1024];
+                while let Ok((len, remote)) = socket.recv_from(&mut buf).await {
+                    let Some(response) = answer(&buf[..len], counts, &peers) else {
+                        continue;
+                    };
+                    match read_u32(&response, 0) {
+                        Some(ACTION_SCRAPE) => scrapes.fetch_add(1, Ordering::SeqCst),
+                        Some(ACTION_ANNOUNCE) => announces.fetch_add(1, Ordering::SeqCst),
+                        _ => 0,
+                    };
+                    let _ = socket.send_to(&response, remote).await;
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            scrapes,
+            announces,
+            server,
+        })
+    }
+
+    /// The `udp://` announce URL of the tracker.
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("udp://{}/announce", self.address)
+    }
+
+    /// Number of scrape requests answered.
+    #[must_use]
+    pub fn scrapes(&self) -> usize {
+        self.scrapes.load(Ordering::SeqCst)
+    }
+
+    /// Number of announce requests answered.
+    #[must_use]
+    pub fn announces(&self) -> usize {
+        self.announces.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for TestTracker {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+fn answer(request: &[u8], counts: ScrapeCounts, peers: &[SocketAddr]) -> Option<Vec<u8>> {
+    let transaction_id = read_u32(request, 12)?;
+
+    let mut response = Vec::new();
+
+    if request.get(..8)? == PROTOCOL_ID.to_be_bytes() && read_u32(request, 8)? == ACTION_CONNECT {
+        response.extend_from_slice(&ACTION_CONNECT.to_be_bytes());
+        response.extend_from_slice(&transaction_id.to_be_bytes());
+        response.extend_from_slice(&CONNECTION_ID.to_be_bytes());
+        return Some(response);
+    }
+
+    if request.get(..8)? != CONNECTION_ID.to_be_bytes() {
+        return None;
+    }
+
+    if read_u32(request, 8)? == ACTION_ANNOUNCE {
+        response.extend_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+        response.extend_from_slice(&transaction_id.to_be_bytes());
+        response.extend_from_slice(&1800u32.to_be_bytes()); // interval
+        for count in [counts.leechers, counts.seeders] {
+            let count = u32::try_from(count).unwrap_or(u32::MAX);
+            response.extend_from_slice(&count.to_be_bytes());
+        }
+        for peer in peers {
+            if let IpAddr::V4(ip) = peer.ip() {
+                response.extend_from_slice(&ip.octets());
+                response.extend_from_slice(&peer.port().to_be_bytes());
+            }
+        }
+        return Some(response);
+    }
+
+    if read_u32(request, 8)? != ACTION_SCRAPE {
+        return None;
+    }
+
+    response.extend_from_slice(&ACTION_SCRAPE.to_be_bytes());
+    response.extend_from_slice(&transaction_id.to_be_bytes());
+    // Each info-hash in the request takes 20 bytes.
+    for _ in request[16..].chunks_exact(20) {
+        for count in [
+            counts.seeders,
+            counts.completed.unwrap_or_default(),
+            counts.leechers,
+        ] {
+            let count = u32::try_from(count).unwrap_or(u32::MAX);
+            response.extend_from_slice(&count.to_be_bytes());
+        }
+    }
+
+    Some(response)
+}
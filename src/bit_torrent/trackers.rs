@@ -0,0 +1,194 @@
+//! Trackers added to the magnet links so that the client can find peers with
+//! tracker announces and not only with the DHT.
+//!
+//! There are two sources of trackers:
+//!
+//! - The default trackers in the configuration.
+//! - An optional tracker list file with one tracker URL per line, like the
+//!   ones published by <https://github.com/ngosang/trackerslist>. The file is
+//!   reloaded periodically so it can be updated without restarting the service.
+use std::fs;
+use std::io;
+use std::sync::RwLock;
+
+use camino::Utf8PathBuf;
+use tracing::{info, warn};
+
+/// Tracker URL schemes supported by the `BitTorrent` client.
+const SUPPORTED_SCHEMES: [&str; 3] = ["udp://", "http://", "https://"];
+
+pub struct Trackers {
+    default: Vec<String>,
+    list_file: Option<Utf8PathBuf>,
+    from_list_file: RwLock<Vec<String>>,
+}
+
+impl Trackers {
+    /// Loads the trackers. Unsupported default trackers are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the tracker list file exists but it can't be
+    /// read.
+    pub fn load(default: Vec<String>, list_file: Option<Utf8PathBuf>) -> io::Result<Self> {
+        let trackers = Self {
+            default: default
+                .into_iter()
+                .filter(|tracker| {
+                    let supported = is_supported(tracker);
+                    if !supported {
+                        warn!("ignoring unsupported default tracker: {tracker}");
+                    }
+                    supported
+                })
+                .collect(),
+            list_file,
+            from_list_file: RwLock::new(vec![]),
+        };
+
+        trackers.reload()?;
+
+        Ok(trackers)
+    }
+
+    /// Reads the tracker list file again. A missing file is an empty list.
+    ///
+    /// Returns the number of trackers in the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the tracker list file exists but it can't be
+    /// read. The previously loaded trackers are kept in that case.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    pub fn reload(&self) -> io::Result<usize> {
+        let Some(list_file) = &self.list_file else {
+            return Ok(0);
+        };
+
+        let trackers = match fs::read_to_string(list_file) {
+            Ok(contents) => parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err),
+        };
+        let len = trackers.len();
+
+        *self.from_list_file.write().expect("trackers lock poisoned") = trackers;
+
+        info!("loaded {len} trackers from {list_file}");
+
+        Ok(len)
+    }
+
+    /// Returns the default trackers followed by the ones in the tracker list
+    /// file, without duplicates.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock is poisoned.
+    #[must_use]
+    pub fn all(&self) -> Vec<String> {
+        let from_list_file = self.from_list_file.read().expect("trackers lock poisoned");
+
+        let mut trackers = self.default.clone();
+
+        for tracker in from_list_file.iter() {
+            if !trackers.contains(tracker) {
+                trackers.push(tracker.clone());
+            }
+        }
+
+        trackers
+    }
+}
+
+/// Returns true if the tracker URL uses a scheme supported by the client.
+#[must_use]
+pub fn is_supported(tracker: &str) -> bool {
+    SUPPORTED_SCHEMES
+        .iter()
+        .any(|scheme| tracker.len() > scheme.len() && tracker.starts_with(scheme))
+}
+
+fn parse(contents: &str) -> Vec<String> {
+    let mut trackers: Vec<String> = vec![];
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if !is_supported(line) {
+            warn!("ignoring unsupported tracker in tracker list: {line}");
+            continue;
+        }
+
+        if !trackers.iter().any(|tracker| tracker == line) {
+            trackers.push(line.to_string());
+        }
+    }
+
+    trackers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use camino::Utf8PathBuf;
+
+    use super::{is_supported, parse, Trackers};
+
+    #[test]
+    fn it_should_support_udp_and_http_trackers() {
+        assert!(is_supported("udp://tracker.torrust-demo.com:6969/announce"));
+        assert!(is_supported("http://tracker.torrust-demo.com/announce")); // DevSkim: ignore DS137138
+        assert!(is_supported("https://tracker.torrust-demo.com/announce"));
+        assert!(!is_supported("wss://tracker.torrust-demo.com"));
+        assert!(!is_supported("udp://"));
+    }
+
+    #[test]
+    fn it_should_parse_a_tracker_list_skipping_empty_lines_comments_and_duplicates() {
+        let trackers = parse(
+            "# best trackers\nudp://a.com:6969/announce\n\nudp://a.com:6969/announce\nwss://b.com\nhttps://c.com/announce\n",
+        );
+
+        assert_eq!(
+            trackers,
+            vec!["udp://a.com:6969/announce", "https://c.com/announce"]
+        );
+    }
+
+    #[test]
+    fn it_should_merge_default_trackers_with_the_ones_in_the_list_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let list_file = Utf8PathBuf::from_path_buf(dir.path().join("trackers.txt")).unwrap();
+        fs::write(
+            &list_file,
+            "udp://a.com:6969/announce\nudp://b.com:6969/announce\n",
+        )
+        .unwrap();
+
+        let trackers = Trackers::load(
+            vec!["udp://a.com:6969/announce".to_string()],
+            Some(list_file.clone()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            trackers.all(),
+            vec!["udp://a.com:6969/announce", "udp://b.com:6969/announce"]
+        );
+
+        fs::write(&list_file, "udp://c.com:6969/announce\n").unwrap();
+        trackers.reload().unwrap();
+
+        assert_eq!(
+            trackers.all(),
+            vec!["udp://a.com:6969/announce", "udp://c.com:6969/announce"]
+        );
+    }
+}
@@ -1,21 +1,293 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
 
 use camino::Utf8PathBuf;
+use hyper::header::HeaderName;
+use hyper::Method;
+use ipnet::IpNet;
 
 #[derive(Clone)]
 pub struct Config {
     pub api: Api,
     pub client: Client,
+    pub denylist: Denylist,
+    pub trackers: Trackers,
+    pub peers: Peers,
+    pub swarm: Swarm,
+    /// Notifications for background resolutions. They are disabled when it's
+    /// not set.
+    pub webhooks: Option<Webhooks>,
+    pub feeds: Feeds,
+    /// Persistent log of the torrent requests. It's disabled when it's not
+    /// set.
+    pub audit_log: Option<AuditLog>,
 }
 
 #[derive(Clone)]
 pub struct Client {
     pub listen_port_range: Option<std::ops::Range<u16>>,
     pub session_output_dir: Utf8PathBuf,
+    /// The DHT is needed to find the peers that have the torrent. Without it,
+    /// only trackers and the peers given in the request are used.
+    pub enable_dht: bool,
+    /// How the torrent metadata is fetched from the peers.
+    pub resolver_backend: ResolverBackend,
+    /// Maximum number of magnet links resolved at the same time.
+    pub max_concurrent_resolutions: usize,
+    /// Maximum number of resolutions waiting for a free slot. New
+    /// resolutions are rejected when the queue is full.
+    pub max_queued_resolutions: usize,
+    /// How often torrents left behind in the session are removed.
+    pub orphan_sweep_interval: Duration,
+}
+
+/// The implementation used to get the torrent metadata.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResolverBackend {
+    /// A `librqbit` session adding the torrents in list-only mode. It uses
+    /// the DHT, trackers and initial peers.
+    #[default]
+    Librqbit,
+    /// A lightweight fetcher that only speaks the metadata extension. It uses
+    /// the DHT and initial peers.
+    Native,
+}
+
+impl FromStr for ResolverBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "librqbit" => Ok(Self::Librqbit),
+            "native" => Ok(Self::Native),
+            _ => Err(format!("Unknown resolver backend: {s}")),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Api {
     pub bind_address: SocketAddr,
     pub torrents_cache_dir: Utf8PathBuf,
+    /// Bearer token required by the `/admin` endpoints. The admin API is
+    /// disabled when it's not set.
+    pub admin_token: Option<String>,
+    /// Time the client has to resolve a torrent when the request does not
+    /// include the `timeout` query parameter.
+    pub resolution_timeout: Duration,
+    /// Maximum value for the `timeout` query parameter.
+    pub max_resolution_timeout: Duration,
+    /// Time a connection can be idle, or take to send a request, before it's
+    /// closed.
+    pub connection_timeout: Duration,
+    /// Serves HTTPS instead of HTTP when it's set.
+    pub tls: Option<Tls>,
+    /// CORS policy for browsers calling the API from other origins. Cross
+    /// origin requests are not allowed when it's not set.
+    pub cors: Option<Cors>,
+    /// Networks of the reverse proxies in front of the service. The client
+    /// address of their requests is taken from the forwarding headers.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+#[derive(Clone)]
+pub struct Tls {
+    /// PEM file with the certificate chain.
+    pub certificate_path: Utf8PathBuf,
+    /// PEM file with the private key.
+    pub private_key_path: Utf8PathBuf,
+    /// How often the certificate files are checked for changes. They are
+    /// loaded again when they change, for example, after a renewal.
+    pub reload_check_interval: Duration,
+    /// Optional address for a plain HTTP listener that redirects every
+    /// request to HTTPS.
+    pub http_redirect_bind_address: Option<SocketAddr>,
+    /// The HTTPS port clients connect to, used in the redirects. It's the API
+    /// port when it's not set. It differs when the port is forwarded, for
+    /// example, from 443 by a firewall or a container runtime.
+    pub public_https_port: Option<u16>,
+}
+
+#[derive(Clone)]
+pub struct Cors {
+    pub allowed_origins: AllowedOrigins,
+    pub allowed_methods: Vec<Method>,
+    /// Response headers that scripts in the browser can read, besides the
+    /// CORS-safelisted ones.
+    pub exposed_headers: Vec<HeaderName>,
+    /// How long browsers can cache the preflight responses.
+    pub max_age: Duration,
+}
+
+/// The origins allowed to call the API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AllowedOrigins {
+    Any,
+    /// Origins like `https://hash2torrent.com`, without a trailing slash.
+    List(Vec<String>),
+}
+
+impl FromStr for AllowedOrigins {
+    type Err = String;
+
+    /// Parses a comma-separated list of origins, or `*` for any origin.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(Self::Any);
+        }
+
+        let origins: Vec<String> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(ToString::to_string)
+            .collect();
+
+        if origins.is_empty() {
+            return Err("Empty list of allowed origins".to_string());
+        }
+
+        if let Some(origin) = origins.iter().find(|origin| !is_origin(origin)) {
+            return Err(format!("Invalid origin: {origin}"));
+        }
+
+        Ok(Self::List(origins))
+    }
+}
+
+/// Returns true for a `scheme://host[:port]` origin.
+fn is_origin(origin: &str) -> bool {
+    let Some((scheme, host)) = origin.split_once("://") else {
+        return false;
+    };
+
+    (scheme == "http" || scheme == "https")
+        && !host.is_empty()
+        && !host.contains(['/', '?', '#'])
+        && origin.is_ascii()
+        && !origin.contains(|c: char| c.is_ascii_control() || c.is_ascii_whitespace())
+}
+
+#[derive(Clone)]
+pub struct Denylist {
+    /// File with the denied info-hashes, one per line.
+    pub path: Utf8PathBuf,
+    /// URL with information about why a torrent is not available. It's sent
+    /// in the `Link` header of the `451 Unavailable For Legal Reasons`
+    /// responses.
+    pub reference_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Trackers {
+    /// Trackers added to every magnet link.
+    pub default: Vec<String>,
+    /// Optional file with more trackers, one per line.
+    pub list_file: Option<Utf8PathBuf>,
+    /// How often the tracker list file is read again.
+    pub list_file_refresh_interval: Duration,
+}
+
+#[derive(Clone)]
+pub struct Peers {
+    /// Maximum time looking for peers in the DHT and the trackers for the
+    /// peers endpoint.
+    pub lookup_timeout: Duration,
+    /// Maximum number of peers returned by the peers endpoint.
+    pub max_peers: usize,
+    /// How long the peers found for a torrent are reused.
+    pub cache_ttl: Duration,
+    /// Allows probing peers on loopback, private and link-local addresses.
+    /// The DHT, the trackers and other peers choose the addresses, so it
+    /// should only be set when the service can't reach anything else there.
+    pub allow_private_peers: bool,
+}
+
+#[derive(Clone)]
+pub struct Swarm {
+    /// Maximum time scraping the trackers and the DHT for the swarm endpoint.
+    pub scrape_timeout: Duration,
+    /// Maximum number of trackers scraped for each request.
+    pub max_trackers: usize,
+    /// How long the swarm estimates for a torrent are reused.
+    pub cache_ttl: Duration,
+}
+
+#[derive(Clone)]
+pub struct Webhooks {
+    /// Secrets used to sign the notifications, by key id. Callers choose the
+    /// key when they give a callback URL.
+    pub keys: HashMap<String, String>,
+    /// Directory where pending deliveries are stored until they are sent.
+    pub store_dir: Utf8PathBuf,
+    /// Maximum number of attempts to send a notification.
+    pub max_attempts: u32,
+    /// Wait after the first failed attempt. It's doubled after every failed
+    /// attempt.
+    pub initial_backoff: Duration,
+    /// Maximum wait between attempts.
+    pub max_backoff: Duration,
+    /// Maximum time for the receiver to answer.
+    pub request_timeout: Duration,
+    /// Maximum number of notifications waiting to be sent. Background
+    /// resolutions are rejected while it's reached.
+    pub max_pending: usize,
+    /// Allows callback URLs on loopback, private and link-local addresses.
+    /// Callers choose the URLs, so it should only be set when all of them
+    /// are trusted.
+    pub allow_private_callbacks: bool,
+}
+
+#[derive(Clone)]
+pub struct Feeds {
+    /// Maximum number of torrents in the feeds of recently resolved
+    /// torrents.
+    pub length: usize,
+    /// Private torrents ([BEP 27](https://www.bittorrent.org/beps/bep_0027.html))
+    /// are left out of the feeds unless it's set.
+    pub include_private: bool,
+    /// The public URL of the service, like `https://hash2torrent.com`, used
+    /// for the links in the feeds. The links are relative to the root when
+    /// it's not set.
+    pub base_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    /// Directory of the log files, one per day.
+    pub dir: Utf8PathBuf,
+    /// Number of days the records are kept, including the current day.
+    pub retention_days: u64,
+    /// Zeroes the host part of the client IP addresses before they are
+    /// recorded.
+    pub anonymize_client_ip: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AllowedOrigins;
+
+    #[test]
+    fn it_should_parse_the_allowed_origins() {
+        assert_eq!("*".parse(), Ok(AllowedOrigins::Any));
+        assert_eq!(
+            "https://hash2torrent.com, http://localhost:8080".parse(),
+            Ok(AllowedOrigins::List(vec![
+                "https://hash2torrent.com".to_string(),
+                "http://localhost:8080".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn it_should_reject_invalid_origins() {
+        assert!("".parse::<AllowedOrigins>().is_err());
+        assert!("hash2torrent.com".parse::<AllowedOrigins>().is_err());
+        assert!("https://hash2torrent.com/"
+            .parse::<AllowedOrigins>()
+            .is_err());
+        assert!("ftp://hash2torrent.com".parse::<AllowedOrigins>().is_err());
+    }
 }
@@ -1,7 +1,15 @@
 use std::sync::Arc;
 
+use api::audit_log::AuditLog;
 use api::cache::Cache;
-use bit_torrent::client::Client;
+use api::denylist::Denylist;
+use api::feeds::FeedCache;
+use api::stats::Stats;
+use api::webhooks::Webhooks;
+use bit_torrent::peer_discovery::PeerDiscovery;
+use bit_torrent::resolver::MetadataResolver;
+use bit_torrent::swarm::SwarmScraper;
+use bit_torrent::trackers::Trackers;
 use config::Config;
 
 pub mod api;
@@ -10,11 +18,21 @@ pub mod config;
 
 pub struct AppState {
     pub config: Arc<Config>,
-    pub client: Arc<Client>,
+    /// Gets the torrent metadata for the requested info-hashes.
+    pub resolver: Arc<dyn MetadataResolver>,
     pub cache: Arc<Cache>,
-}
-
-#[must_use]
-pub fn run_app_for_integration_tests() -> String {
-    "No integration tests yet :-(".to_string()
+    pub denylist: Arc<Denylist>,
+    pub trackers: Arc<Trackers>,
+    pub peer_discovery: Arc<PeerDiscovery>,
+    pub swarm_scraper: Arc<SwarmScraper>,
+    /// Sends the notifications of background resolutions, if webhooks are
+    /// enabled.
+    pub webhooks: Option<Arc<Webhooks>>,
+    /// Records the torrent requests, if the audit log is enabled.
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// Counters of the torrent requests and resolutions for the statistics
+    /// page.
+    pub stats: Arc<Stats>,
+    /// The recently rendered feeds.
+    pub feed_cache: Arc<FeedCache>,
 }
@@ -1,15 +1,26 @@
+use ipnet::IpNet;
 use std::{
-    fs,
+    env, fs,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 use torrust_hash2torrent::bit_torrent::client::Client;
-use torrust_hash2torrent::config::{self, Config};
+use torrust_hash2torrent::bit_torrent::peer_discovery::PeerDiscovery;
+use torrust_hash2torrent::bit_torrent::resolver::native::NativeResolver;
+use torrust_hash2torrent::bit_torrent::resolver::MetadataResolver;
+use torrust_hash2torrent::bit_torrent::swarm::SwarmScraper;
+use torrust_hash2torrent::bit_torrent::trackers::Trackers;
+use torrust_hash2torrent::config::{self, Config, ResolverBackend};
 use torrust_hash2torrent::{
-    api::{self, cache::Cache},
+    api::{
+        self, audit_log::AuditLog, cache::Cache, denylist::Denylist, stats::Stats,
+        webhooks::Webhooks,
+    },
     AppState,
 };
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
@@ -17,39 +28,355 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let session_output_dir = "/var/lib/torrust/hash2torrent/session";
     let torrents_cache_dir = "/var/lib/torrust/hash2torrent/torrents";
+    let denylist_path = "/etc/torrust/hash2torrent/denylist.txt";
+    let tracker_list_file = "/etc/torrust/hash2torrent/trackers.txt";
+    let webhooks_store_dir = "/var/lib/torrust/hash2torrent/webhooks";
+    let audit_log_dir = "/var/log/torrust/hash2torrent/audit";
     let bind_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 3000);
 
     let config = Config {
         api: config::Api {
             bind_address,
             torrents_cache_dir: torrents_cache_dir.into(),
+            admin_token: admin_token_from_env()?,
+            resolution_timeout: Duration::from_secs(10),
+            max_resolution_timeout: Duration::from_mins(1),
+            connection_timeout: Duration::from_secs(10),
+            tls: tls_config_from_env()?,
+            cors: cors_config_from_env()?,
+            trusted_proxies: trusted_proxies_from_env()?,
         },
         client: config::Client {
             listen_port_range: Some(51000..51010),
             session_output_dir: session_output_dir.into(),
+            enable_dht: true,
+            resolver_backend: match env::var("TORRUST_HASH2TORRENT_RESOLVER_BACKEND") {
+                Ok(backend) => backend.parse().map_err(anyhow::Error::msg)?,
+                Err(_) => ResolverBackend::default(),
+            },
+            max_concurrent_resolutions: 32,
+            max_queued_resolutions: 256,
+            orphan_sweep_interval: Duration::from_mins(10),
         },
+        denylist: config::Denylist {
+            path: denylist_path.into(),
+            reference_url: env::var("TORRUST_HASH2TORRENT_DENYLIST_REFERENCE_URL").ok(),
+        },
+        trackers: config::Trackers {
+            default: vec![
+                "udp://tracker.opentrackr.org:1337/announce".to_string(),
+                "udp://open.demonii.com:1337/announce".to_string(),
+                "udp://tracker.torrust-demo.com:6969/announce".to_string(),
+                "https://tracker.torrust-demo.com/announce".to_string(),
+            ],
+            list_file: Some(tracker_list_file.into()),
+            list_file_refresh_interval: Duration::from_hours(1),
+        },
+        peers: config::Peers {
+            lookup_timeout: Duration::from_secs(5),
+            max_peers: 50,
+            cache_ttl: Duration::from_mins(5),
+            allow_private_peers: false,
+        },
+        swarm: config::Swarm {
+            scrape_timeout: Duration::from_secs(5),
+            max_trackers: 20,
+            cache_ttl: Duration::from_mins(5),
+        },
+        webhooks: webhooks_config_from_env(webhooks_store_dir)?,
+        feeds: feeds_config_from_env()?,
+        audit_log: audit_log_config_from_env(audit_log_dir)?,
     };
 
     check_storage(&config)?;
 
-    info!("creating BitTorrent client and starting the session ...");
+    let resolver = start_resolver(&config).await?;
+
+    let scheme = if config.api.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    info!("starting API on: {scheme}://{bind_address} ..."); // DevSkim: ignore DS137138
+
+    let denylist = Arc::new(Denylist::load(config.denylist.path.clone())?);
 
-    let mut client = Client::new(config.client.clone());
-    client.start_session().await?;
+    reload_denylist_on_sighup(denylist.clone())?;
 
-    info!("starting API on: http://{bind_address} ..."); // DevSkim: ignore DS137138
+    let trackers = Arc::new(Trackers::load(
+        config.trackers.default.clone(),
+        config.trackers.list_file.clone(),
+    )?);
+
+    refresh_trackers_periodically(trackers.clone(), config.trackers.list_file_refresh_interval);
 
     let app_state = AppState {
+        peer_discovery: Arc::new(PeerDiscovery::new(config.peers.clone())),
+        swarm_scraper: Arc::new(SwarmScraper::new(config.swarm.clone())),
+        webhooks: config.webhooks.clone().map(Webhooks::start).transpose()?,
+        audit_log: config.audit_log.clone().map(AuditLog::open).transpose()?,
+        stats: Arc::new(Stats::default()),
+        feed_cache: Arc::default(),
         config: Arc::new(config),
-        client: Arc::new(client),
+        resolver,
         cache: Arc::new(Cache::new(torrents_cache_dir.into())),
+        denylist,
+        trackers,
     };
 
-    api::start(&bind_address, app_state).await;
+    api::start(&bind_address, app_state).await?;
 
     Ok(())
 }
 
+/// Starts the resolver of the configured backend.
+async fn start_resolver(config: &Config) -> Result<Arc<dyn MetadataResolver>, anyhow::Error> {
+    match config.client.resolver_backend {
+        ResolverBackend::Librqbit => {
+            info!("creating BitTorrent client and starting the session ...");
+
+            let mut client = Client::new(config.client.clone());
+            client.start_session().await?;
+
+            let client = Arc::new(client);
+
+            sweep_orphan_torrents_periodically(client.clone(), config.client.orphan_sweep_interval);
+
+            Ok(client)
+        }
+        ResolverBackend::Native => {
+            info!("creating native metadata resolver ...");
+
+            Ok(Arc::new(NativeResolver::new(&config.client).await?))
+        }
+    }
+}
+
+/// The admin API is enabled when the token is set. An empty token is an
+/// error: requests with an empty bearer token would match it.
+fn admin_token_from_env() -> Result<Option<String>, anyhow::Error> {
+    match env::var("TORRUST_HASH2TORRENT_ADMIN_TOKEN") {
+        Ok(token) if token.trim().is_empty() => {
+            Err(anyhow::anyhow!("The admin token can't be empty"))
+        }
+        Ok(token) => Ok(Some(token)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// HTTPS is enabled when both the certificate and the private key paths are
+/// set.
+fn tls_config_from_env() -> Result<Option<config::Tls>, anyhow::Error> {
+    let (Ok(certificate_path), Ok(private_key_path)) = (
+        env::var("TORRUST_HASH2TORRENT_TLS_CERTIFICATE_PATH"),
+        env::var("TORRUST_HASH2TORRENT_TLS_PRIVATE_KEY_PATH"),
+    ) else {
+        return Ok(None);
+    };
+
+    let http_redirect_bind_address =
+        match env::var("TORRUST_HASH2TORRENT_HTTP_REDIRECT_BIND_ADDRESS") {
+            Ok(address) => Some(address.parse()?),
+            Err(_) => None,
+        };
+
+    let public_https_port = match env::var("TORRUST_HASH2TORRENT_PUBLIC_HTTPS_PORT") {
+        Ok(port) => Some(port.parse()?),
+        Err(_) => None,
+    };
+
+    Ok(Some(config::Tls {
+        certificate_path: certificate_path.into(),
+        private_key_path: private_key_path.into(),
+        reload_check_interval: Duration::from_mins(1),
+        http_redirect_bind_address,
+        public_https_port,
+    }))
+}
+
+/// The trusted proxies are a comma-separated list of addresses or networks,
+/// like `172.16.0.0/12`. There are none by default.
+fn trusted_proxies_from_env() -> Result<Vec<IpNet>, anyhow::Error> {
+    let Ok(trusted_proxies) = env::var("TORRUST_HASH2TORRENT_TRUSTED_PROXIES") else {
+        return Ok(vec![]);
+    };
+
+    trusted_proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy
+                .parse::<IpNet>()
+                .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| anyhow::anyhow!("Invalid trusted proxy: {proxy}"))
+        })
+        .collect()
+}
+
+/// CORS is enabled when the allowed origins are set. The allowed methods and
+/// the exposed headers are comma-separated lists.
+fn cors_config_from_env() -> Result<Option<config::Cors>, anyhow::Error> {
+    let Ok(allowed_origins) = env::var("TORRUST_HASH2TORRENT_CORS_ALLOWED_ORIGINS") else {
+        return Ok(None);
+    };
+
+    let allowed_methods = match env::var("TORRUST_HASH2TORRENT_CORS_ALLOWED_METHODS") {
+        Ok(methods) => comma_separated(&methods)?,
+        Err(_) => api::cors::default_methods(),
+    };
+
+    let exposed_headers = match env::var("TORRUST_HASH2TORRENT_CORS_EXPOSED_HEADERS") {
+        Ok(headers) => comma_separated(&headers)?,
+        Err(_) => api::cors::default_exposed_headers(),
+    };
+
+    Ok(Some(config::Cors {
+        allowed_origins: allowed_origins.parse().map_err(anyhow::Error::msg)?,
+        allowed_methods,
+        exposed_headers,
+        max_age: Duration::from_hours(1),
+    }))
+}
+
+/// Webhooks are enabled when the keys are set, as a comma-separated list of
+/// `key_id:secret` pairs.
+fn webhooks_config_from_env(store_dir: &str) -> Result<Option<config::Webhooks>, anyhow::Error> {
+    let Ok(keys) = env::var("TORRUST_HASH2TORRENT_WEBHOOK_KEYS") else {
+        return Ok(None);
+    };
+
+    let keys = keys
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| match key.split_once(':') {
+            Some((key_id, secret)) if !key_id.is_empty() && !secret.is_empty() => {
+                Ok((key_id.to_string(), secret.to_string()))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid webhook key, expected key_id:secret"
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some(config::Webhooks {
+        keys,
+        store_dir: store_dir.into(),
+        max_attempts: 10,
+        initial_backoff: Duration::from_secs(10),
+        max_backoff: Duration::from_hours(1),
+        request_timeout: Duration::from_secs(10),
+        max_pending: 1000,
+        allow_private_callbacks: false,
+    }))
+}
+
+/// The feeds list the 50 most recent torrents by default. Private torrents
+/// are only listed if `TORRUST_HASH2TORRENT_FEED_INCLUDE_PRIVATE` is `true`.
+fn feeds_config_from_env() -> Result<config::Feeds, anyhow::Error> {
+    let length = match env::var("TORRUST_HASH2TORRENT_FEED_LENGTH") {
+        Ok(length) => length.parse()?,
+        Err(_) => 50,
+    };
+
+    Ok(config::Feeds {
+        length,
+        include_private: env::var("TORRUST_HASH2TORRENT_FEED_INCLUDE_PRIVATE")
+            .is_ok_and(|include| include == "true"),
+        base_url: env::var("TORRUST_HASH2TORRENT_BASE_URL").ok(),
+    })
+}
+
+/// The audit log is enabled when the retention, in days, is set. The client
+/// IP addresses are anonymized if
+/// `TORRUST_HASH2TORRENT_AUDIT_LOG_ANONYMIZE_IP` is `true`.
+fn audit_log_config_from_env(dir: &str) -> Result<Option<config::AuditLog>, anyhow::Error> {
+    let Ok(retention_days) = env::var("TORRUST_HASH2TORRENT_AUDIT_LOG_RETENTION_DAYS") else {
+        return Ok(None);
+    };
+
+    Ok(Some(config::AuditLog {
+        dir: dir.into(),
+        retention_days: retention_days.parse()?,
+        anonymize_client_ip: env::var("TORRUST_HASH2TORRENT_AUDIT_LOG_ANONYMIZE_IP")
+            .is_ok_and(|anonymize| anonymize == "true"),
+    }))
+}
+
+fn comma_separated<T>(list: &str) -> Result<Vec<T>, anyhow::Error>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(anyhow::Error::from))
+        .collect()
+}
+
+/// Reloads the denylist file when the process receives a `SIGHUP` signal.
+#[cfg(unix)]
+fn reload_denylist_on_sighup(denylist: Arc<Denylist>) -> Result<(), anyhow::Error> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading denylist ...");
+
+            if let Err(err) = denylist.reload() {
+                error!("error reloading denylist: {err}");
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+fn reload_denylist_on_sighup(_denylist: Arc<Denylist>) -> Result<(), anyhow::Error> {
+    Ok(())
+}
+
+/// Reads the tracker list file again at the given interval.
+fn refresh_trackers_periodically(trackers: Arc<Trackers>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = trackers.reload() {
+                error!("error reloading tracker list: {err}");
+            }
+        }
+    });
+}
+
+/// Removes the torrents left behind in the session at the given interval.
+fn sweep_orphan_torrents_periodically(client: Arc<Client>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let removed = client.sweep_orphan_torrents().await;
+
+            if removed > 0 {
+                info!("removed {removed} orphan torrents from the session");
+            }
+        }
+    });
+}
+
 fn check_storage(config: &Config) -> Result<(), anyhow::Error> {
     // Check if the directories exist
     if fs::metadata(config.client.session_output_dir.clone()).is_err() {
@@ -0,0 +1,156 @@
+use hyper::StatusCode;
+use reqwest::Method;
+use serde_json::{json, Value};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+const ADMIN_TOKEN: &str = "MyAccessToken";
+
+fn start_with_admin_api(resolver: FakeResolver) -> TestApp {
+    TestApp::start_with(resolver, |config| {
+        config.api.admin_token = Some(ADMIN_TOKEN.to_string());
+    })
+}
+
+#[tokio::test]
+async fn it_should_reject_admin_requests_without_the_token() {
+    let app = start_with_admin_api(FakeResolver::default());
+
+    let without_token = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .send()
+        .await
+        .unwrap();
+    let wrong_token = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .bearer_auth("WrongToken")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(without_token.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong_token.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn it_should_reject_empty_tokens_even_if_the_configured_token_is_empty() {
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.api.admin_token = Some(String::new());
+    });
+
+    let response = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .header("Authorization", "Bearer ")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn it_should_not_serve_the_admin_api_without_a_configured_token() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn it_should_report_the_cache_usage_and_delete_cached_torrents() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = start_with_admin_api(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let usage: Value = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let deleted = app
+        .request(Method::DELETE, format!("/api/v1/admin/cache/{info_hash}"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let deleted_again = app
+        .request(Method::DELETE, format!("/api/v1/admin/cache/{info_hash}"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        usage,
+        json!({ "torrents": 1, "bytes": torrent.torrent_bytes.len() })
+    );
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert!(!app.cache.contains(&torrent.info_hash));
+    assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn it_should_resolve_a_torrent_again() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = start_with_admin_api(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .request(
+            Method::POST,
+            format!("/api/v1/admin/torrents/{info_hash}/resolve"),
+        )
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(app.cache.contains(&torrent.info_hash));
+    assert_eq!(app.resolver.calls(), 1);
+}
+
+#[tokio::test]
+async fn it_should_not_manage_the_session_if_the_resolver_does_not_use_one() {
+    let app = start_with_admin_api(FakeResolver::default());
+
+    let list = app
+        .request(Method::GET, "/api/v1/admin/session/torrents")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let remove = app
+        .request(
+            Method::DELETE,
+            format!("/api/v1/admin/session/torrents/{}", "0".repeat(40)),
+        )
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let invalid = app
+        .request(Method::DELETE, "/api/v1/admin/session/torrents/invalid")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(list.status(), StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(remove.status(), StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
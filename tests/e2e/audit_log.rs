@@ -0,0 +1,212 @@
+use hyper::StatusCode;
+use reqwest::Method;
+use serde_json::Value;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::config::{self, Config};
+
+use super::harness::TestApp;
+
+const ADMIN_TOKEN: &str = "MyAccessToken";
+
+fn enable_audit_log(config: &mut Config) {
+    config.api.admin_token = Some(ADMIN_TOKEN.to_string());
+    config.audit_log = Some(config::AuditLog {
+        dir: config.api.torrents_cache_dir.with_file_name("audit"),
+        retention_days: 30,
+        anonymize_client_ip: false,
+    });
+}
+
+async fn query_audit_log(app: &TestApp, query: &str) -> reqwest::Response {
+    app.request(Method::GET, format!("/api/v1/admin/audit{query}"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn audit_records(app: &TestApp, query: &str) -> Vec<Value> {
+    let response = query_audit_log(app, query).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn it_should_record_the_torrent_requests_with_the_cache_status() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(
+        FakeResolver::default().with_torrent(torrent),
+        enable_audit_log,
+    );
+
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+    app.get(format!("/torrents/{info_hash}/info")).await;
+    app.get("/api/v1/health_check").await;
+
+    let records = audit_records(&app, "").await;
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["info_hash"], info_hash.as_str());
+    assert_eq!(records[0]["endpoint"], "info");
+    assert_eq!(records[0]["cache"], "hit");
+    assert_eq!(records[0]["status"], 200);
+    assert_eq!(records[0]["outcome"], "served");
+    assert_eq!(records[0]["client"], "127.0.0.1");
+    assert_eq!(records[1]["endpoint"], "torrent");
+    assert_eq!(records[1]["cache"], "miss");
+    assert!(records[1]["timestamp"].as_u64().unwrap() > 0);
+    assert!(records[1]["latency_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn it_should_record_invalid_and_denied_requests() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        enable_audit_log(config);
+        std::fs::write(&config.denylist.path, &info_hash).unwrap();
+    });
+
+    app.get("/api/v1/torrents/invalid").await;
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+
+    let records = audit_records(&app, "").await;
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["outcome"], "denied");
+    assert_eq!(records[0]["status"], 451);
+    assert_eq!(records[1]["info_hash"], "invalid");
+    assert_eq!(records[1]["outcome"], "invalid_request");
+    assert!(records[1]["cache"].is_null());
+}
+
+#[tokio::test]
+async fn it_should_filter_the_audit_log_queries() {
+    let first = sample_torrent("first.txt", b"First");
+    let second = sample_torrent("second.txt", b"Second");
+    let app = TestApp::start_with(
+        FakeResolver::default()
+            .with_torrent(first.clone())
+            .with_torrent(second.clone()),
+        enable_audit_log,
+    );
+
+    for torrent in [&first, &second, &first] {
+        app.get(format!(
+            "/api/v1/torrents/{}",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+    }
+
+    let by_info_hash = audit_records(
+        &app,
+        &format!("?info_hash={}", first.info_hash.to_hex_string()),
+    )
+    .await;
+    let limited = audit_records(&app, "?limit=1").await;
+    let by_client = audit_records(&app, "?client=203.0.113.7").await;
+
+    assert_eq!(by_info_hash.len(), 2);
+    assert_eq!(limited.len(), 1);
+    assert_eq!(
+        limited[0]["info_hash"],
+        first.info_hash.to_hex_string().as_str()
+    );
+    assert!(by_client.is_empty());
+}
+
+#[tokio::test]
+async fn it_should_anonymize_the_client_addresses_if_configured() {
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        enable_audit_log(config);
+        if let Some(audit_log) = &mut config.audit_log {
+            audit_log.anonymize_client_ip = true;
+        }
+    });
+
+    app.get("/api/v1/torrents/invalid").await;
+
+    let records = audit_records(&app, "?client=127.0.0.0").await;
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["client"], "127.0.0.0");
+}
+
+#[tokio::test]
+async fn it_should_not_query_the_audit_log_if_it_is_disabled() {
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.api.admin_token = Some(ADMIN_TOKEN.to_string());
+    });
+
+    let response = query_audit_log(&app, "").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn it_should_record_the_forwarded_client_address_of_trusted_proxies() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(FakeResolver::default().with_torrent(torrent), |config| {
+        enable_audit_log(config);
+        config.api.trusted_proxies = vec!["127.0.0.0/8".parse().unwrap()];
+    });
+
+    app.request(Method::GET, format!("/api/v1/torrents/{info_hash}"))
+        .header("x-forwarded-for", "198.51.100.1, 203.0.113.7")
+        .send()
+        .await
+        .unwrap();
+
+    let records = audit_records(&app, "").await;
+
+    assert_eq!(records[0]["client"], "203.0.113.7");
+}
+
+#[tokio::test]
+async fn it_should_ignore_the_forwarding_headers_of_untrusted_clients() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(
+        FakeResolver::default().with_torrent(torrent),
+        enable_audit_log,
+    );
+
+    app.request(Method::GET, format!("/api/v1/torrents/{info_hash}"))
+        .header("x-forwarded-for", "203.0.113.7")
+        .header("x-real-ip", "203.0.113.7")
+        .send()
+        .await
+        .unwrap();
+
+    let records = audit_records(&app, "").await;
+
+    assert_eq!(records[0]["client"], "127.0.0.1");
+}
+
+#[tokio::test]
+async fn it_should_record_the_same_cache_status_as_the_statistics_for_conditional_requests() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(FakeResolver::default(), enable_audit_log);
+
+    let response = app
+        .request(Method::GET, format!("/api/v1/torrents/{info_hash}"))
+        .header("if-none-match", format!("\"{info_hash}\""))
+        .send()
+        .await
+        .unwrap();
+
+    let records = audit_records(&app, "").await;
+    let stats: Value = app.get("/api/v1/stats").await.json().await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(records[0]["outcome"], "not_modified");
+    assert_eq!(records[0]["cache"], "miss");
+    assert_eq!(stats["cache"]["misses"], 1);
+    assert_eq!(stats["cache"]["hits"], 0);
+}
@@ -0,0 +1,70 @@
+//! Slowloris protection: connections that don't send a request in time are
+//! closed by the `TimeoutAcceptor`.
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+use torrust_hash2torrent::bit_torrent::resolver::fake::FakeResolver;
+
+use super::harness::TestApp;
+
+/// Shorter than the one-second header read timeout of the HTTP server, so
+/// that the connection is closed by the `TimeoutAcceptor`.
+const CONNECTION_TIMEOUT: Duration = Duration::from_millis(300);
+
+fn start_app() -> TestApp {
+    TestApp::start_with(FakeResolver::default(), |config| {
+        config.api.connection_timeout = CONNECTION_TIMEOUT;
+    })
+}
+
+/// Reads until the server closes the connection and returns how long it took.
+async fn wait_until_closed(stream: &mut TcpStream, started: Instant) -> Duration {
+    let mut buf = Vec::new();
+
+    timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+        .await
+        .expect("the server should close the connection")
+        .ok();
+
+    started.elapsed()
+}
+
+#[tokio::test]
+async fn it_should_close_connections_that_send_the_request_headers_too_slowly() {
+    let app = start_app();
+    let mut stream = TcpStream::connect(app.address).await.unwrap();
+    let started = Instant::now();
+
+    stream
+        .write_all(b"GET /health_check HTTP/1.1\r\n")
+        .await
+        .unwrap();
+
+    let elapsed = wait_until_closed(&mut stream, started).await;
+
+    assert!(elapsed >= CONNECTION_TIMEOUT);
+    assert!(elapsed < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn it_should_close_idle_connections() {
+    let app = start_app();
+    let mut stream = TcpStream::connect(app.address).await.unwrap();
+
+    stream
+        .write_all(b"GET /health_check HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut buf = [0; 1024];
+    let read = stream.read(&mut buf).await.unwrap();
+    assert!(buf[..read].starts_with(b"HTTP/1.1 200 OK"));
+
+    let started = Instant::now();
+
+    let elapsed = wait_until_closed(&mut stream, started).await;
+
+    assert!(elapsed < Duration::from_secs(1));
+}
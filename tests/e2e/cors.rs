@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use hyper::StatusCode;
+use reqwest::Method;
+use torrust_hash2torrent::api::cors;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::config::{self, AllowedOrigins};
+
+use super::harness::TestApp;
+
+const FRONTEND: &str = "https://frontend.example.com";
+
+fn start_with_cors(resolver: FakeResolver, allowed_origins: AllowedOrigins) -> TestApp {
+    TestApp::start_with(resolver, |config| {
+        config.api.cors = Some(config::Cors {
+            allowed_origins,
+            allowed_methods: cors::default_methods(),
+            exposed_headers: cors::default_exposed_headers(),
+            max_age: Duration::from_hours(1),
+        });
+    })
+}
+
+#[tokio::test]
+async fn it_should_answer_preflight_requests_from_allowed_origins() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = start_with_cors(
+        FakeResolver::default(),
+        AllowedOrigins::List(vec![FRONTEND.to_string()]),
+    );
+
+    let response = app
+        .request(
+            Method::OPTIONS,
+            format!("/torrents/{}", torrent.info_hash.to_hex_string()),
+        )
+        .header("origin", FRONTEND)
+        .header("access-control-request-method", "GET")
+        .header("access-control-request-headers", "if-none-match")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], FRONTEND);
+    assert_eq!(
+        response.headers()["access-control-allow-methods"],
+        "GET,HEAD"
+    );
+    assert_eq!(response.headers()["access-control-max-age"], "3600");
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_expose_the_torrent_headers_to_allowed_origins() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = start_with_cors(
+        FakeResolver::default().with_torrent(torrent.clone()),
+        AllowedOrigins::List(vec![FRONTEND.to_string()]),
+    );
+
+    let response = app
+        .request(
+            Method::GET,
+            format!("/torrents/{}", torrent.info_hash.to_hex_string()),
+        )
+        .header("origin", FRONTEND)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], FRONTEND);
+    let exposed = response.headers()["access-control-expose-headers"]
+        .to_str()
+        .unwrap();
+    assert!(exposed.contains("x-torrust-torrent-infohash"));
+}
+
+#[tokio::test]
+async fn it_should_expose_the_deprecation_headers_of_the_legacy_routes() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = start_with_cors(
+        FakeResolver::default().with_torrent(torrent.clone()),
+        AllowedOrigins::List(vec![FRONTEND.to_string()]),
+    );
+
+    let response = app
+        .request(
+            Method::GET,
+            format!("/torrents/{}", torrent.info_hash.to_hex_string()),
+        )
+        .header("origin", FRONTEND)
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.headers().contains_key("deprecation"));
+    let exposed = response.headers()["access-control-expose-headers"]
+        .to_str()
+        .unwrap();
+    assert!(exposed.contains("deprecation"));
+    assert!(exposed.contains("link"));
+}
+
+#[tokio::test]
+async fn it_should_not_allow_other_origins() {
+    let app = start_with_cors(
+        FakeResolver::default(),
+        AllowedOrigins::List(vec![FRONTEND.to_string()]),
+    );
+
+    let response = app
+        .request(Method::GET, "/health_check")
+        .header("origin", "https://other.example.com")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+}
+
+#[tokio::test]
+async fn it_should_allow_any_origin_when_configured() {
+    let app = start_with_cors(FakeResolver::default(), AllowedOrigins::Any);
+
+    let response = app
+        .request(Method::GET, "/health_check")
+        .header("origin", FRONTEND)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+}
+
+#[tokio::test]
+async fn it_should_not_send_cors_headers_by_default() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app
+        .request(Method::GET, "/health_check")
+        .header("origin", FRONTEND)
+        .send()
+        .await
+        .unwrap();
+
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+}
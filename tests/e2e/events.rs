@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use hyper::{header, StatusCode};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::bit_torrent::resolver::ResolveError;
+
+use super::harness::TestApp;
+
+/// Reads the whole event stream and returns the events' name and data.
+async fn events(response: reqwest::Response) -> Vec<(String, String)> {
+    let body = response.text().await.unwrap();
+
+    body.split("\n\n")
+        .filter_map(|message| {
+            let mut name = None;
+            let mut data = None;
+            for line in message.lines() {
+                if let Some(value) = line.strip_prefix("event: ") {
+                    name = Some(value.to_string());
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data = Some(value.to_string());
+                }
+            }
+            Some((name?, data?))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn it_should_stream_the_resolution_phases_until_the_torrent_is_cached() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/events",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    assert_eq!(
+        events(response).await,
+        vec![
+            ("queued".to_string(), r#"{"phase":"queued"}"#.to_string()),
+            (
+                "verified".to_string(),
+                r#"{"phase":"verified"}"#.to_string()
+            ),
+            ("cached".to_string(), r#"{"phase":"cached"}"#.to_string()),
+        ]
+    );
+    assert_eq!(
+        app.cache.get(&torrent.info_hash).unwrap(),
+        torrent.torrent_bytes
+    );
+}
+
+#[tokio::test]
+async fn it_should_stream_the_reason_when_the_resolution_fails() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let resolver = FakeResolver::default().with_delay(Duration::from_mins(1));
+    let app = TestApp::start_with(resolver, |config| {
+        config.api.resolution_timeout = Duration::from_millis(100);
+    });
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/events",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    let events = events(response).await;
+    let (name, data) = events.last().expect("it should send events");
+
+    assert_eq!(name, "failed");
+    assert_eq!(
+        data,
+        &format!(
+            r#"{{"phase":"failed","reason":"{}"}}"#,
+            ResolveError::Timeout
+        )
+    );
+}
+
+#[tokio::test]
+async fn it_should_only_stream_the_cached_event_for_cached_torrents() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let response = app
+        .get(format!(
+            "/torrents/{}/events",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(
+        events(response).await,
+        vec![("cached".to_string(), r#"{"phase":"cached"}"#.to_string())]
+    );
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_not_stream_events_for_invalid_info_hashes() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/api/v1/torrents/invalid/events").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
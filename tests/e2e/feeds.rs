@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use hyper::{header, StatusCode};
+use reqwest::Method;
+use sha1::{Digest, Sha1};
+use torrust_hash2torrent::bit_torrent::bencode;
+use torrust_hash2torrent::bit_torrent::info_hash::InfoHash;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::bit_torrent::resolver::TorrentMetadata;
+
+use super::harness::TestApp;
+
+/// Adds the torrent to the cache as if it was resolved some minutes ago.
+fn add_to_cache(app: &TestApp, torrent: &TorrentMetadata, minutes_ago: u64) {
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    File::options()
+        .write(true)
+        .open(app.cache.path(&torrent.info_hash))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(minutes_ago * 60))
+        .unwrap();
+}
+
+/// A single-file torrent with the `private` flag.
+fn private_torrent(name: &str) -> TorrentMetadata {
+    let mut info = vec![b'd'];
+    bencode::encode_bytes(&mut info, b"length");
+    bencode::encode_int(&mut info, 1);
+    bencode::encode_bytes(&mut info, b"name");
+    bencode::encode_bytes(&mut info, name.as_bytes());
+    bencode::encode_bytes(&mut info, b"piece length");
+    bencode::encode_int(&mut info, 16384);
+    bencode::encode_bytes(&mut info, b"pieces");
+    bencode::encode_bytes(&mut info, &Sha1::digest(b"x"));
+    bencode::encode_bytes(&mut info, b"private");
+    bencode::encode_int(&mut info, 1);
+    info.push(b'e');
+
+    let mut torrent = vec![b'd'];
+    bencode::encode_bytes(&mut torrent, b"info");
+    torrent.extend_from_slice(&info);
+    torrent.push(b'e');
+
+    TorrentMetadata {
+        info_hash: InfoHash::from_bytes(&Sha1::digest(&info)),
+        torrent_bytes: Bytes::from(torrent),
+        ..sample_torrent(name, b"x")
+    }
+}
+
+#[tokio::test]
+async fn it_should_list_the_recently_resolved_torrents_in_an_atom_feed() {
+    let older = sample_torrent("older.txt", b"Older");
+    let newer = sample_torrent("newer & better.txt", b"Newer");
+    let app = TestApp::start(FakeResolver::default());
+    add_to_cache(&app, &older, 10);
+    add_to_cache(&app, &newer, 5);
+
+    let response = app.get("/api/v1/feeds/recent.atom").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/atom+xml; charset=utf-8"
+    );
+
+    let feed = response.text().await.unwrap();
+    let newer_url = format!("/api/v1/torrents/{}", newer.info_hash.to_hex_string());
+
+    assert!(feed.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+    assert!(feed.contains(r#"<link rel="self" href="/api/v1/feeds/recent.atom"/>"#));
+    assert!(feed.contains("<title>newer &amp; better.txt</title>"));
+    assert!(feed.contains(&format!(
+        "<id>urn:btih:{}</id>",
+        newer.info_hash.to_hex_string()
+    )));
+    assert!(feed.contains(&format!(
+        r#"<link rel="enclosure" type="application/x-bittorrent" length="{}" href="{newer_url}"/>"#,
+        newer.torrent_bytes.len()
+    )));
+    assert!(feed.contains(&format!(
+        r#"<link rel="alternate" type="application/json" href="{newer_url}/info"/>"#
+    )));
+    assert!(feed.contains(&format!(
+        r#"<link rel="related" href="magnet:?xt=urn:btih:{}&amp;dn=newer%20%26%20better.txt&amp;xl=5"/>"#,
+        newer.info_hash.to_hex_string()
+    )));
+    assert!(feed.find("newer &amp; better.txt").unwrap() < feed.find("older.txt").unwrap());
+}
+
+#[tokio::test]
+async fn it_should_list_the_recently_resolved_torrents_in_an_rss_feed() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.feeds.base_url = Some("https://hash2torrent.com/".to_string());
+    });
+    add_to_cache(&app, &torrent, 1);
+
+    let response = app.get("/feeds/recent.rss").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/rss+xml; charset=utf-8"
+    );
+
+    let feed = response.text().await.unwrap();
+    let torrent_url = format!(
+        "https://hash2torrent.com/api/v1/torrents/{}",
+        torrent.info_hash.to_hex_string()
+    );
+
+    assert!(feed.contains(r#"<rss version="2.0""#));
+    assert!(feed.contains("<title>sample.txt</title>"));
+    assert!(feed.contains(&format!("<link>{torrent_url}</link>")));
+    assert!(feed.contains(&format!(
+        r#"<enclosure url="{torrent_url}" length="{}" type="application/x-bittorrent"/>"#,
+        torrent.torrent_bytes.len()
+    )));
+}
+
+#[tokio::test]
+async fn it_should_not_build_the_links_from_the_request_host() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    add_to_cache(&app, &torrent, 1);
+
+    let feed = app
+        .request(Method::GET, "/api/v1/feeds/recent.rss")
+        .header(header::HOST, "attacker.example.com")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert!(feed.contains("<title>sample.txt</title>"));
+    assert!(!feed.contains("attacker.example.com"));
+}
+
+#[tokio::test]
+async fn it_should_limit_the_feed_length() {
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.feeds.length = 2;
+    });
+    for i in 0..3 {
+        add_to_cache(
+            &app,
+            &sample_torrent(&format!("sample-{i}.txt"), format!("{i}").as_bytes()),
+            i,
+        );
+    }
+
+    let feed = app
+        .get("/api/v1/feeds/recent.atom")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(feed.contains("sample-0.txt"));
+    assert!(feed.contains("sample-1.txt"));
+    assert!(!feed.contains("sample-2.txt"));
+}
+
+#[tokio::test]
+async fn it_should_leave_denylisted_and_private_torrents_out_of_the_feeds() {
+    let denied = sample_torrent("denied.txt", b"Denied");
+    let private = private_torrent("private.txt");
+    let public = sample_torrent("public.txt", b"Public");
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        std::fs::write(&config.denylist.path, denied.info_hash.to_hex_string()).unwrap();
+    });
+    add_to_cache(&app, &denied, 1);
+    add_to_cache(&app, &private, 2);
+    add_to_cache(&app, &public, 3);
+
+    let feed = app
+        .get("/api/v1/feeds/recent.atom")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(feed.contains("public.txt"));
+    assert!(!feed.contains("denied.txt"));
+    assert!(!feed.contains("private.txt"));
+}
+
+#[tokio::test]
+async fn it_should_list_private_torrents_if_the_configuration_allows_it() {
+    let private = private_torrent("private.txt");
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.feeds.include_private = true;
+    });
+    add_to_cache(&app, &private, 1);
+
+    let feed = app
+        .get("/api/v1/feeds/recent.atom")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(feed.contains("private.txt"));
+}
+
+#[tokio::test]
+async fn it_should_reuse_the_rendered_feed_for_a_minute() {
+    let app = TestApp::start(FakeResolver::default());
+    add_to_cache(&app, &sample_torrent("first.txt", b"First"), 1);
+
+    let first = app
+        .get("/api/v1/feeds/recent.atom")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    add_to_cache(&app, &sample_torrent("second.txt", b"Second"), 0);
+
+    let second = app
+        .get("/api/v1/feeds/recent.atom")
+        .await
+        .text()
+        .await
+        .unwrap();
+    let rss = app
+        .get("/api/v1/feeds/recent.rss")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(first, second);
+    assert!(!second.contains("second.txt"));
+    assert!(rss.contains("second.txt"));
+}
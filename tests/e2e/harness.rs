@@ -0,0 +1,170 @@
+//! Runs the API with temporary directories and a [`FakeResolver`].
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+use torrust_hash2torrent::api::{
+    self, audit_log::AuditLog, cache::Cache, denylist::Denylist, stats::Stats, webhooks::Webhooks,
+};
+use torrust_hash2torrent::bit_torrent::peer_discovery::PeerDiscovery;
+use torrust_hash2torrent::bit_torrent::resolver::fake::FakeResolver;
+use torrust_hash2torrent::bit_torrent::swarm::SwarmScraper;
+use torrust_hash2torrent::bit_torrent::trackers::Trackers;
+use torrust_hash2torrent::config::{self, Config, ResolverBackend};
+use torrust_hash2torrent::AppState;
+
+pub struct TestApp {
+    pub address: SocketAddr,
+    pub resolver: Arc<FakeResolver>,
+    pub cache: Cache,
+    server: JoinHandle<()>,
+    // Removed when the app is dropped.
+    _temp_dir: TempDir,
+}
+
+impl TestApp {
+    /// Starts the API with the default test configuration.
+    pub fn start(resolver: FakeResolver) -> Self {
+        Self::start_with(resolver, |_| {})
+    }
+
+    /// Starts the API after changing the default test configuration.
+    pub fn start_with(resolver: FakeResolver, configure: impl FnOnce(&mut Config)) -> Self {
+        let temp_dir = tempfile::tempdir().expect("it should create a temp dir");
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf())
+            .expect("the temp dir path should be valid UTF-8");
+
+        let mut config = test_config(&root);
+        configure(&mut config);
+
+        std::fs::create_dir_all(&config.api.torrents_cache_dir)
+            .expect("it should create the cache dir");
+        std::fs::create_dir_all(&config.client.session_output_dir)
+            .expect("it should create the session dir");
+
+        let socket = TcpListener::bind("127.0.0.1:0").expect("it should bind an ephemeral port");
+        socket
+            .set_nonblocking(true)
+            .expect("it should set the socket as non-blocking");
+        let address = socket
+            .local_addr()
+            .expect("it should get the local address");
+
+        let resolver = Arc::new(resolver);
+
+        let app_state = AppState {
+            resolver: resolver.clone(),
+            cache: Arc::new(Cache::new(config.api.torrents_cache_dir.clone())),
+            denylist: Arc::new(
+                Denylist::load(config.denylist.path.clone()).expect("it should load the denylist"),
+            ),
+            trackers: Arc::new(
+                Trackers::load(config.trackers.default.clone(), None)
+                    .expect("it should load the trackers"),
+            ),
+            peer_discovery: Arc::new(PeerDiscovery::new(config.peers.clone())),
+            swarm_scraper: Arc::new(SwarmScraper::new(config.swarm.clone())),
+            webhooks: config
+                .webhooks
+                .clone()
+                .map(|webhooks| Webhooks::start(webhooks).expect("it should start the webhooks")),
+            audit_log: config
+                .audit_log
+                .clone()
+                .map(|audit_log| AuditLog::open(audit_log).expect("it should open the audit log")),
+            stats: Arc::new(Stats::default()),
+            feed_cache: Arc::default(),
+            config: Arc::new(config.clone()),
+        };
+
+        let server = tokio::spawn(api::serve(socket, app_state));
+
+        Self {
+            address,
+            resolver,
+            cache: Cache::new(config.api.torrents_cache_dir),
+            server,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address) // DevSkim: ignore DS137138
+    }
+
+    pub async fn get(&self, path: impl AsRef<str>) -> reqwest::Response {
+        reqwest::get(self.url(path.as_ref()))
+            .await
+            .expect("the request should be sent")
+    }
+
+    /// Builds a request, for requests that need other methods or headers.
+    pub fn request(
+        &self,
+        method: reqwest::Method,
+        path: impl AsRef<str>,
+    ) -> reqwest::RequestBuilder {
+        reqwest::Client::new().request(method, self.url(path.as_ref()))
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+fn test_config(root: &Utf8PathBuf) -> Config {
+    Config {
+        api: config::Api {
+            bind_address: "127.0.0.1:0".parse().expect("it should be a valid address"),
+            torrents_cache_dir: root.join("torrents"),
+            admin_token: None,
+            resolution_timeout: Duration::from_secs(5),
+            max_resolution_timeout: Duration::from_secs(10),
+            connection_timeout: Duration::from_secs(10),
+            tls: None,
+            cors: None,
+            trusted_proxies: vec![],
+        },
+        client: config::Client {
+            listen_port_range: None,
+            session_output_dir: root.join("session"),
+            enable_dht: false,
+            resolver_backend: ResolverBackend::Librqbit,
+            max_concurrent_resolutions: 32,
+            max_queued_resolutions: 256,
+            orphan_sweep_interval: Duration::from_mins(10),
+        },
+        denylist: config::Denylist {
+            path: root.join("denylist.txt"),
+            reference_url: None,
+        },
+        trackers: config::Trackers {
+            default: vec![],
+            list_file: None,
+            list_file_refresh_interval: Duration::from_hours(1),
+        },
+        peers: config::Peers {
+            lookup_timeout: Duration::from_secs(1),
+            max_peers: 10,
+            cache_ttl: Duration::from_mins(1),
+            allow_private_peers: true,
+        },
+        swarm: config::Swarm {
+            scrape_timeout: Duration::from_secs(1),
+            max_trackers: 10,
+            cache_ttl: Duration::from_mins(1),
+        },
+        webhooks: None,
+        feeds: config::Feeds {
+            length: 50,
+            include_private: false,
+            base_url: None,
+        },
+        audit_log: None,
+    }
+}
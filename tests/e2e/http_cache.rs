@@ -0,0 +1,133 @@
+//! Caching headers, conditional requests and `HEAD` requests on the torrents
+//! routes.
+use std::time::{Duration, SystemTime};
+
+use hyper::{header, StatusCode};
+use reqwest::Method;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_send_immutable_caching_headers_with_torrent_files() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::ETAG],
+        format!("\"{}\"", torrent.info_hash.to_hex_string()).as_str()
+    );
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+    assert!(response.headers().contains_key(header::LAST_MODIFIED));
+}
+
+#[tokio::test]
+async fn it_should_answer_not_modified_to_a_matching_etag_without_resolving_the_torrent() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .request(
+            Method::GET,
+            format!("/torrents/{}", torrent.info_hash.to_hex_string()),
+        )
+        .header(
+            header::IF_NONE_MATCH,
+            format!("\"{}\"", torrent.info_hash.to_hex_string()),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_answer_not_modified_if_the_cached_torrent_has_not_changed_since_the_given_date()
+{
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let if_modified_since = |time| {
+        app.request(
+            Method::GET,
+            format!("/torrents/{}", torrent.info_hash.to_hex_string()),
+        )
+        .header(header::IF_MODIFIED_SINCE, httpdate::fmt_http_date(time))
+        .send()
+    };
+
+    let response = if_modified_since(SystemTime::now() + Duration::from_mins(1))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = if_modified_since(SystemTime::now() - Duration::from_hours(1))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn it_should_send_short_lived_caching_headers_with_failures() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let resolver = FakeResolver::default()
+        .with_torrent(torrent.clone())
+        .with_delay(Duration::from_mins(1));
+    let app = TestApp::start_with(resolver, |config| {
+        config.api.resolution_timeout = Duration::from_millis(100);
+    });
+
+    let response = app
+        .get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=10"
+    );
+}
+
+#[tokio::test]
+async fn it_should_answer_head_requests_on_every_torrents_route() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+    let info_hash = torrent.info_hash.to_hex_string();
+
+    let response = app
+        .request(Method::HEAD, format!("/torrents/{info_hash}"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_LENGTH],
+        torrent.torrent_bytes.len().to_string().as_str()
+    );
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    for path in ["peers", "swarm"] {
+        let response = app
+            .request(Method::HEAD, format!("/torrents/{info_hash}/{path}"))
+            .send()
+            .await
+            .unwrap();
+
+        assert_ne!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(response.bytes().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use hyper::{header, StatusCode};
+use serde_json::{json, Value};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+const TRACKER: &str = "udp://tracker.torrust-demo.com:6969/announce";
+
+#[tokio::test]
+async fn it_should_return_a_magnet_link_with_the_cached_torrent_name_and_length() {
+    let torrent = sample_torrent("sample file.txt", b"Hello, World!");
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.trackers.default = vec![TRACKER.to_string()];
+    });
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/magnet",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!(
+            "magnet:?xt=urn:btih:{}&dn=sample%20file.txt&xl=13&tr=udp%3A%2F%2Ftracker.torrust-demo.com%3A6969%2Fannounce",
+            torrent.info_hash.to_hex_string()
+        )
+    );
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_return_the_magnet_link_in_json_when_the_client_accepts_it() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let response = app
+        .request(
+            reqwest::Method::GET,
+            format!(
+                "/api/v1/torrents/{}/magnet?tr={}",
+                torrent.info_hash.to_hex_string(),
+                form_urlencoded::byte_serialize(TRACKER.as_bytes()).collect::<String>()
+            ),
+        )
+        .header(header::ACCEPT, "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::VARY], "Accept");
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({
+            "magnet": format!(
+                "magnet:?xt=urn:btih:{}&dn=sample.txt&xl=13&tr=udp%3A%2F%2Ftracker.torrust-demo.com%3A6969%2Fannounce",
+                torrent.info_hash.to_hex_string()
+            ),
+            "info_hash": torrent.info_hash.to_hex_string(),
+            "v2_info_hash": null,
+            "name": "sample.txt",
+            "length": 13,
+            "trackers": [TRACKER]
+        })
+    );
+}
+
+#[tokio::test]
+async fn it_should_return_a_magnet_link_without_resolving_torrents_that_are_not_cached() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/magnet",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!("magnet:?xt=urn:btih:{}", torrent.info_hash.to_hex_string())
+    );
+    assert_eq!(app.resolver.calls(), 0);
+}
@@ -0,0 +1,19 @@
+mod admin;
+mod audit_log;
+mod connections;
+mod cors;
+mod events;
+mod feeds;
+mod harness;
+mod http_cache;
+mod magnet;
+mod openapi;
+mod peers;
+mod resolution;
+mod stats;
+mod swarm;
+mod tls;
+mod torrents;
+mod ui;
+mod versioning;
+mod webhooks;
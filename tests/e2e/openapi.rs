@@ -0,0 +1,109 @@
+use hyper::StatusCode;
+use reqwest::Method;
+use serde_json::Value;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+/// Methods checked for every documented path. `HEAD` and `OPTIONS` are
+/// answered for every `GET` route.
+const METHODS: [Method; 5] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+async fn openapi(app: &TestApp) -> Value {
+    let response = app.get("/api/openapi.json").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    serde_json::from_str(&response.text().await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn it_should_serve_the_openapi_specification() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let openapi = openapi(&app).await;
+
+    assert!(openapi["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(openapi["paths"]["/torrents/{info_hash}"]["get"].is_object());
+}
+
+#[tokio::test]
+async fn it_should_serve_the_openapi_specification_of_each_version() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/api/v1/openapi.json").await;
+    let openapi: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+
+    assert_eq!(openapi["servers"][0]["url"], "/api/v1");
+}
+
+#[tokio::test]
+async fn it_should_serve_the_interactive_documentation() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/api/docs").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("/api/openapi.json"));
+    assert!(page.contains("/assets/swagger-ui-bundle.js"));
+    assert!(page.contains("/assets/swagger-ui.css"));
+}
+
+/// Fails when a documented route is removed or renamed, or when a method is
+/// added to or removed from a documented path without updating the spec.
+#[tokio::test]
+async fn the_openapi_specification_should_match_the_routes() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let openapi = openapi(&app).await;
+    let server = openapi["servers"][0]["url"].as_str().unwrap();
+    let paths = openapi["paths"].as_object().unwrap();
+
+    assert!(!paths.is_empty());
+
+    for (path, operations) in paths {
+        let url = format!(
+            "{server}{}",
+            path.replace("{info_hash}", &torrent.info_hash.to_hex_string())
+        );
+
+        for method in METHODS {
+            let documented = operations.get(method.as_str().to_lowercase()).is_some();
+
+            let status = app
+                .request(method.clone(), &url)
+                .send()
+                .await
+                .unwrap()
+                .status();
+
+            assert_ne!(
+                status,
+                StatusCode::NOT_FOUND,
+                "{method} {path} is not routed"
+            );
+
+            if documented {
+                assert_ne!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{method} {path} is documented but not routed"
+                );
+            } else {
+                assert_eq!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{method} {path} is routed but not documented"
+                );
+            }
+        }
+    }
+}
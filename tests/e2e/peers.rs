@@ -0,0 +1,58 @@
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::bit_torrent::swarm::ScrapeCounts;
+use torrust_hash2torrent::bit_torrent::test_peer::TestPeer;
+use torrust_hash2torrent::bit_torrent::test_tracker::TestTracker;
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_reject_invalid_info_hashes() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/torrents/not-an-info-hash/peers").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn it_should_find_the_peers_with_the_configured_trackers() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+    let tracker = TestTracker::start_with_peers(
+        ScrapeCounts {
+            seeders: 1,
+            leechers: 0,
+            completed: None,
+        },
+        vec![peer.address()],
+    )
+    .await
+    .unwrap();
+    let tracker_url = tracker.url();
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.trackers.default = vec![tracker_url];
+    });
+
+    let response = app
+        .get(format!(
+            "/torrents/{}/peers",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let lookup: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+
+    assert_eq!(
+        lookup["peers"],
+        json!([{
+            "address": peer.address().to_string(),
+            "source": "tracker",
+            "supports_ut_metadata": true,
+        }])
+    );
+    assert_eq!(tracker.announces(), 1);
+}
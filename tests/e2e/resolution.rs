@@ -0,0 +1,102 @@
+//! Resolutions with the `BitTorrent` client against a local test peer.
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use tempfile::TempDir;
+use torrust_hash2torrent::bit_torrent::bencode::raw_dict_value;
+use torrust_hash2torrent::bit_torrent::client::{Client, ResolveMagnetError};
+use torrust_hash2torrent::bit_torrent::magnet::MagnetLink;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, SAMPLE_PIECE_LENGTH};
+use torrust_hash2torrent::bit_torrent::resolver::{ResolveOptions, TorrentMetadata};
+use torrust_hash2torrent::bit_torrent::test_peer::TestPeer;
+use torrust_hash2torrent::config::{self, ResolverBackend};
+
+/// Starts a client that does not use the DHT, so it only connects to the
+/// peers given in the options.
+async fn start_client() -> (Client, TempDir) {
+    let session_dir = tempfile::tempdir().unwrap();
+
+    let mut client = Client::new(config::Client {
+        listen_port_range: None,
+        session_output_dir: Utf8PathBuf::from_path_buf(session_dir.path().to_path_buf()).unwrap(),
+        enable_dht: false,
+        resolver_backend: ResolverBackend::Librqbit,
+        max_concurrent_resolutions: 4,
+        max_queued_resolutions: 4,
+        orphan_sweep_interval: Duration::from_mins(10),
+    });
+    client.start_session().await.unwrap();
+
+    (client, session_dir)
+}
+
+fn options(peer: &TestPeer, timeout: Duration) -> ResolveOptions {
+    ResolveOptions {
+        timeout: Some(timeout),
+        initial_peers: vec![peer.address()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn it_should_resolve_magnet_links_with_the_metadata_from_a_local_peer() {
+    let (client, _session_dir) = start_client().await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+    let (info, torrent_bytes) = client
+        .resolve_magnet(
+            &MagnetLink::new(torrent.info_hash),
+            &options(&peer, Duration::from_secs(10)),
+        )
+        .await
+        .unwrap();
+
+    let metadata = TorrentMetadata::from_info(torrent.info_hash, &info, torrent_bytes).unwrap();
+
+    assert_eq!(
+        (metadata.name, metadata.piece_length, metadata.files),
+        (torrent.name, torrent.piece_length, torrent.files)
+    );
+    assert_eq!(
+        raw_dict_value(&metadata.torrent_bytes, b"info").unwrap(),
+        raw_dict_value(&torrent.torrent_bytes, b"info").unwrap()
+    );
+}
+
+#[tokio::test]
+async fn it_should_resolve_metadata_sent_in_several_pieces() {
+    let (client, _session_dir) = start_client().await;
+    // 20 bytes per content piece, so the metadata is bigger than one 16 KiB
+    // metadata piece.
+    let contents = vec![0u8; SAMPLE_PIECE_LENGTH * 1000];
+    let torrent = sample_torrent("big.bin", &contents);
+    let peer = TestPeer::start(vec![torrent.clone()]).await.unwrap();
+
+    let (info, _torrent_bytes) = client
+        .resolve_magnet(
+            &MagnetLink::new(torrent.info_hash),
+            &options(&peer, Duration::from_secs(10)),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(info.pieces.as_ref().len(), 20 * 1000);
+    assert_eq!(peer.served_pieces(), 2);
+}
+
+#[tokio::test]
+async fn it_should_fail_when_the_only_peer_does_not_have_the_torrent() {
+    let (client, _session_dir) = start_client().await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let peer = TestPeer::start(vec![]).await.unwrap();
+
+    let result = client
+        .resolve_magnet(
+            &MagnetLink::new(torrent.info_hash),
+            &options(&peer, Duration::from_secs(10)),
+        )
+        .await;
+
+    assert!(matches!(result, Err(ResolveMagnetError::NotAdded)));
+}
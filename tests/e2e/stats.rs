@@ -0,0 +1,86 @@
+use hyper::{header, StatusCode};
+use reqwest::Method;
+use serde_json::Value;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_report_the_request_and_resolution_statistics() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+    app.get(format!("/api/v1/torrents/{info_hash}/info")).await;
+    app.get(format!("/api/v1/torrents/{}", "0".repeat(40)))
+        .await;
+
+    let response = app.get("/api/v1/stats").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::VARY], "Accept");
+
+    let stats: Value = response.json().await.unwrap();
+
+    assert_eq!(stats["cache"]["hits"], 2);
+    assert_eq!(stats["cache"]["misses"], 2);
+    assert_eq!(stats["cache"]["hit_ratio"], 0.5);
+    assert_eq!(stats["cache"]["torrents"], 1);
+    assert_eq!(stats["cache"]["bytes"], torrent.torrent_bytes.len());
+    assert_eq!(stats["resolution_time"]["samples"], 1);
+    assert!(stats["resolution_time"]["median_ms"].is_u64());
+    assert_eq!(stats["failures"]["not_found"], 1);
+    assert_eq!(stats["most_requested"][0]["info_hash"], info_hash.as_str());
+    assert_eq!(stats["most_requested"][0]["requests"], 3);
+    assert_eq!(stats["requests_per_hour"].as_array().unwrap().len(), 24);
+    assert_eq!(stats["requests_per_hour"][23]["requests"], 4);
+}
+
+#[tokio::test]
+async fn it_should_not_count_invalid_or_denied_requests() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        std::fs::write(&config.denylist.path, &info_hash).unwrap();
+    });
+
+    app.get("/api/v1/torrents/invalid").await;
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+
+    let stats: Value = app.get("/api/v1/stats").await.json().await.unwrap();
+
+    assert_eq!(stats["cache"]["hits"], 0);
+    assert_eq!(stats["cache"]["misses"], 0);
+    assert!(stats["cache"]["hit_ratio"].is_null());
+    assert!(stats["resolution_time"]["median_ms"].is_null());
+    assert_eq!(stats["most_requested"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn it_should_render_the_statistics_page_for_browsers() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let info_hash = torrent.info_hash.to_hex_string();
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent));
+
+    app.get(format!("/api/v1/torrents/{info_hash}")).await;
+
+    let response = app
+        .request(Method::GET, "/api/v1/stats")
+        .header(header::ACCEPT, "text/html,application/xhtml+xml")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/html; charset=utf-8"
+    );
+
+    let page = response.text().await.unwrap();
+
+    assert!(page.contains("<h1>Hash2Torrent statistics</h1>"));
+    assert!(page.contains(&format!("<code>{info_hash}</code>")));
+}
@@ -0,0 +1,58 @@
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::bit_torrent::swarm::ScrapeCounts;
+use torrust_hash2torrent::bit_torrent::test_tracker::TestTracker;
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_reject_invalid_info_hashes() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/torrents/not-an-info-hash/swarm").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn it_should_estimate_the_swarm_with_the_configured_trackers() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let tracker = TestTracker::start(ScrapeCounts {
+        seeders: 12,
+        leechers: 3,
+        completed: Some(100),
+    })
+    .await
+    .unwrap();
+    let tracker_url = tracker.url();
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.trackers.default = vec![tracker_url];
+    });
+
+    let response = app
+        .get(format!(
+            "/torrents/{}/swarm",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let swarm: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+
+    assert_eq!(swarm["seeders"], 12);
+    assert_eq!(swarm["leechers"], 3);
+    assert!(swarm["scraped_at"].as_u64().unwrap() > 0);
+    assert_eq!(
+        swarm["sources"],
+        json!([{
+            "source": "tracker",
+            "tracker": tracker.url(),
+            "seeders": 12,
+            "leechers": 3,
+            "completed": 100,
+            "error": null,
+        }])
+    );
+}
@@ -0,0 +1,162 @@
+//! HTTPS with certificate hot reload, and the HTTP to HTTPS redirect.
+use std::net::TcpListener;
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use hyper::{header, StatusCode};
+use rcgen::CertifiedKey;
+use tempfile::TempDir;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use torrust_hash2torrent::api::tls;
+use torrust_hash2torrent::bit_torrent::resolver::fake::FakeResolver;
+use torrust_hash2torrent::config;
+
+use super::harness::TestApp;
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Self-signed certificate files for `localhost`.
+struct Certificate {
+    dir: TempDir,
+    der: Vec<u8>,
+}
+
+impl Certificate {
+    fn generate() -> Self {
+        let certificate = Self {
+            dir: tempfile::tempdir().unwrap(),
+            der: vec![],
+        };
+        certificate.renew()
+    }
+
+    /// Writes a new certificate in the same files.
+    fn renew(mut self) -> Self {
+        let CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        std::fs::write(self.path("key.pem"), key_pair.serialize_pem()).unwrap();
+        std::fs::write(self.path("cert.pem"), cert.pem()).unwrap();
+        self.der = cert.der().to_vec();
+
+        self
+    }
+
+    fn path(&self, file: &str) -> Utf8PathBuf {
+        Utf8PathBuf::from_path_buf(self.dir.path().join(file)).unwrap()
+    }
+
+    fn config(&self) -> config::Tls {
+        config::Tls {
+            certificate_path: self.path("cert.pem"),
+            private_key_path: self.path("key.pem"),
+            reload_check_interval: RELOAD_CHECK_INTERVAL,
+            http_redirect_bind_address: None,
+            public_https_port: None,
+        }
+    }
+}
+
+fn start_app(certificate: &Certificate) -> TestApp {
+    let tls = certificate.config();
+    TestApp::start_with(FakeResolver::default(), |config| {
+        config.api.tls = Some(tls);
+        config.api.connection_timeout = Duration::from_millis(300);
+    })
+}
+
+/// Gets the health check over HTTPS and returns the certificate the server
+/// presented. A new client is used so that a new connection is opened.
+async fn server_certificate(app: &TestApp) -> Vec<u8> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!(
+            "https://localhost:{}/health_check",
+            app.address.port()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|tls_info| tls_info.peer_certificate())
+        .unwrap()
+        .to_vec()
+}
+
+#[tokio::test]
+async fn it_should_serve_https() {
+    let certificate = Certificate::generate();
+    let app = start_app(&certificate);
+
+    assert_eq!(server_certificate(&app).await, certificate.der);
+}
+
+#[tokio::test]
+async fn it_should_reload_the_certificate_when_the_files_change() {
+    let certificate = Certificate::generate();
+    let app = start_app(&certificate);
+    let first_der = server_certificate(&app).await;
+
+    // Some file systems only store the modification time in seconds.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let certificate = certificate.renew();
+    tokio::time::sleep(RELOAD_CHECK_INTERVAL * 5).await;
+
+    let der = server_certificate(&app).await;
+
+    assert_ne!(der, first_der);
+    assert_eq!(der, certificate.der);
+}
+
+#[tokio::test]
+async fn it_should_close_connections_that_do_not_start_the_tls_handshake() {
+    let certificate = Certificate::generate();
+    let app = start_app(&certificate);
+    let mut stream = TcpStream::connect(app.address).await.unwrap();
+
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(1), stream.read_to_end(&mut buf))
+        .await
+        .expect("the server should close the connection")
+        .ok();
+}
+
+#[tokio::test]
+async fn it_should_redirect_http_requests_to_https() {
+    let socket = TcpListener::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let address = socket.local_addr().unwrap();
+    let server = tokio::spawn(tls::serve_redirect(socket, 3443, Duration::from_secs(10)));
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!(
+            "http://{address}/torrents/443c7602b4fde83d1154d6d9da48808418b181b6?timeout=30"
+        )) // DevSkim: ignore DS137138
+        .send()
+        .await
+        .unwrap();
+
+    server.abort();
+
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://127.0.0.1:3443/torrents/443c7602b4fde83d1154d6d9da48808418b181b6?timeout=30"
+    );
+}
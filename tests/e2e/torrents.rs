@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use hyper::StatusCode;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_resolve_and_cache_torrents_that_are_not_cached() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/x-bittorrent"
+    );
+    assert_eq!(
+        response.headers()["x-torrust-torrent-infohash"],
+        torrent.info_hash.to_hex_string().as_str()
+    );
+    assert_eq!(response.bytes().await.unwrap(), torrent.torrent_bytes);
+    assert_eq!(app.resolver.calls(), 1);
+    assert_eq!(
+        app.cache.get(&torrent.info_hash).unwrap(),
+        torrent.torrent_bytes
+    );
+}
+
+#[tokio::test]
+async fn it_should_serve_cached_torrents_without_resolving_them() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let response = app
+        .get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), torrent.torrent_bytes);
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_name_the_torrent_file_after_the_torrent() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+    let path = format!("/torrents/{}", torrent.info_hash.to_hex_string());
+
+    let resolved = app.get(&path).await;
+    let cached = app.get(&path).await;
+
+    for response in [resolved, cached] {
+        assert_eq!(
+            response.headers()["content-disposition"],
+            "attachment; filename=\"sample.txt.torrent\"; filename*=UTF-8''sample.txt.torrent"
+        );
+    }
+}
+
+#[tokio::test]
+async fn it_should_name_the_torrent_file_after_the_info_hash_when_requested() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+    let hex = torrent.info_hash.to_hex_string();
+
+    let response = app.get(format!("/torrents/{hex}?filename=info_hash")).await;
+
+    assert_eq!(
+        response.headers()["content-disposition"],
+        format!("attachment; filename=\"{hex}.torrent\"; filename*=UTF-8''{hex}.torrent").as_str()
+    );
+}
+
+#[tokio::test]
+async fn it_should_accept_uppercase_info_hashes() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!(
+            "/torrents/{}",
+            torrent.info_hash.to_hex_string().to_uppercase()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn it_should_reject_invalid_info_hashes() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/torrents/not-an-info-hash").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_reject_invalid_query_parameters() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app
+        .get(format!(
+            "/torrents/{}?timeout=0",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn it_should_time_out_when_the_torrent_is_not_resolved_in_time() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let resolver = FakeResolver::default()
+        .with_torrent(torrent.clone())
+        .with_delay(Duration::from_mins(1));
+    let app = TestApp::start_with(resolver, |config| {
+        config.api.resolution_timeout = Duration::from_millis(100);
+    });
+
+    let response = app
+        .get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert!(!app.cache.contains(&torrent.info_hash));
+}
+
+#[tokio::test]
+async fn it_should_serve_concurrent_requests() {
+    let torrents: Vec<_> = (0..10)
+        .map(|i| {
+            sample_torrent(
+                &format!("sample-{i}.txt"),
+                format!("Torrent {i}").as_bytes(),
+            )
+        })
+        .collect();
+
+    let resolver = torrents
+        .iter()
+        .fold(FakeResolver::default(), |resolver, torrent| {
+            resolver.with_torrent(torrent.clone())
+        })
+        .with_delay(Duration::from_millis(100));
+
+    let app = TestApp::start(resolver);
+
+    let responses = join_all(
+        torrents
+            .iter()
+            .map(|torrent| app.get(format!("/torrents/{}", torrent.info_hash.to_hex_string()))),
+    )
+    .await;
+
+    for (torrent, response) in torrents.iter().zip(responses) {
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.bytes().await.unwrap(), torrent.torrent_bytes);
+    }
+    assert_eq!(app.resolver.calls(), torrents.len());
+}
+
+#[tokio::test]
+async fn it_should_return_the_torrent_info_in_json() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/info",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({
+            "info_hash": torrent.info_hash.to_hex_string(),
+            "name": "sample.txt",
+            "total_length": 13,
+            "piece_length": torrent.piece_length,
+            "pieces": 1,
+            "files": [{ "path": ["sample.txt"], "length": 13 }]
+        })
+    );
+    assert_eq!(
+        app.cache.get(&torrent.info_hash).unwrap(),
+        torrent.torrent_bytes
+    );
+}
+
+#[tokio::test]
+async fn it_should_return_the_info_of_cached_torrents_without_resolving_them() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+    app.cache
+        .add(&torrent.info_hash, &torrent.torrent_bytes)
+        .unwrap();
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}/info",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.resolver.calls(), 0);
+}
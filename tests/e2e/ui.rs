@@ -0,0 +1,50 @@
+use hyper::{header, StatusCode};
+use torrust_hash2torrent::bit_torrent::resolver::fake::FakeResolver;
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_serve_the_web_ui() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/html; charset=utf-8"
+    );
+
+    let page = response.text().await.unwrap();
+
+    assert!(page.contains(r#"data-api="/api/v1""#));
+    assert!(page.contains("/assets/app.js"));
+    assert!(page.contains("/assets/style.css"));
+}
+
+#[tokio::test]
+async fn it_should_serve_the_web_ui_assets_from_the_binary() {
+    let app = TestApp::start(FakeResolver::default());
+
+    for (asset, content_type) in [
+        ("app.js", "text/javascript; charset=utf-8"),
+        ("style.css", "text/css; charset=utf-8"),
+        ("swagger-ui-bundle.js", "text/javascript; charset=utf-8"),
+        ("swagger-ui.css", "text/css; charset=utf-8"),
+    ] {
+        let response = app.get(format!("/assets/{asset}")).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "asset: {asset}");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+        assert!(response.headers().contains_key(header::CACHE_CONTROL));
+    }
+}
+
+#[tokio::test]
+async fn it_should_not_serve_unknown_assets() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/assets/missing.js").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
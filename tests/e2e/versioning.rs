@@ -0,0 +1,76 @@
+use hyper::StatusCode;
+use reqwest::Method;
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+
+use super::harness::TestApp;
+
+#[tokio::test]
+async fn it_should_serve_the_version_1_routes_without_deprecation() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+
+    let response = app
+        .get(format!(
+            "/api/v1/torrents/{}",
+            torrent.info_hash.to_hex_string()
+        ))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+}
+
+#[tokio::test]
+async fn it_should_serve_the_legacy_routes_as_deprecated_aliases() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default().with_torrent(torrent.clone()));
+    let path = format!("/torrents/{}", torrent.info_hash.to_hex_string());
+
+    let response = app.get(&path).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "@1792368000");
+    assert_eq!(
+        response.headers()["link"],
+        format!("</api/v1{path}>; rel=\"successor-version\"").as_str()
+    );
+    assert_eq!(response.bytes().await.unwrap(), torrent.torrent_bytes);
+}
+
+#[tokio::test]
+async fn it_should_mark_legacy_failures_as_deprecated_too() {
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = app.get("/torrents/invalid").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().contains_key("deprecation"));
+}
+
+#[tokio::test]
+async fn it_should_serve_the_admin_api_under_the_version_1_prefix() {
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        config.api.admin_token = Some("MyAccessToken".to_string());
+    });
+
+    let versioned = app
+        .request(Method::GET, "/api/v1/admin/cache")
+        .bearer_auth("MyAccessToken")
+        .send()
+        .await
+        .unwrap();
+    let legacy = app
+        .request(Method::GET, "/admin/cache")
+        .bearer_auth("MyAccessToken")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(versioned.status(), StatusCode::OK);
+    assert!(!versioned.headers().contains_key("deprecation"));
+    assert_eq!(legacy.status(), StatusCode::OK);
+    assert_eq!(
+        legacy.headers()["link"],
+        "</api/v1/admin/cache>; rel=\"successor-version\""
+    );
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::routing::post;
+use axum::Router;
+use hyper::{HeaderMap, StatusCode};
+use serde_json::{json, Value};
+use torrust_hash2torrent::api::webhooks::{
+    self, Callback, PendingResolution, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use torrust_hash2torrent::bit_torrent::resolver::fake::{sample_torrent, FakeResolver};
+use torrust_hash2torrent::bit_torrent::resolver::ResolveError;
+use torrust_hash2torrent::config::{self, Config};
+
+use super::harness::TestApp;
+
+const SECRET: &str = "s3cr3t";
+
+/// An HTTP server that records the webhook requests. It fails the first
+/// `failures` requests with a `500` response.
+struct Receiver {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+}
+
+impl Receiver {
+    async fn start(failures: usize) -> Self {
+        let requests: Arc<Mutex<Vec<(HeaderMap, String)>>> = Arc::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let app = Router::new().route(
+            "/hook",
+            post({
+                let requests = requests.clone();
+                move |headers: HeaderMap, body: String| async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) < failures {
+                        return StatusCode::INTERNAL_SERVER_ERROR;
+                    }
+                    requests.lock().unwrap().push((headers, body));
+                    StatusCode::NO_CONTENT
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Self { address, requests }
+    }
+
+    fn url(&self) -> String {
+        format!("http://{}/hook", self.address) // DevSkim: ignore DS137138
+    }
+
+    /// Waits for the first notification.
+    async fn notification(&self) -> (HeaderMap, String) {
+        for _ in 0..100 {
+            if let Some(request) = self.requests.lock().unwrap().first() {
+                return request.clone();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("no notification received");
+    }
+}
+
+fn enable_webhooks(config: &mut Config) {
+    config.webhooks = Some(config::Webhooks {
+        keys: HashMap::from([("pipeline".to_string(), SECRET.to_string())]),
+        store_dir: config.api.torrents_cache_dir.with_file_name("webhooks"),
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(1),
+        request_timeout: Duration::from_secs(5),
+        max_pending: 10,
+        allow_private_callbacks: true,
+    });
+}
+
+async fn request_resolution(app: &TestApp, info_hash: &str, body: &Value) -> reqwest::Response {
+    app.request(
+        reqwest::Method::POST,
+        format!("/api/v1/torrents/{info_hash}/resolutions"),
+    )
+    .json(body)
+    .send()
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn it_should_notify_the_callback_when_the_torrent_is_cached() {
+    let receiver = Receiver::start(0).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(
+        FakeResolver::default().with_torrent(torrent.clone()),
+        enable_webhooks,
+    );
+
+    let response = request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": receiver.url(), "key_id": "pipeline" }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let accepted: Value = response.json().await.unwrap();
+
+    let (headers, body) = receiver.notification().await;
+    let notification: Value = serde_json::from_str(&body).unwrap();
+    let timestamp: u64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+
+    assert_eq!(notification["id"], accepted["notification_id"]);
+    assert_eq!(notification["event"], "cached");
+    assert_eq!(notification["info_hash"], torrent.info_hash.to_hex_string());
+    assert_eq!(notification["name"], "sample.txt");
+    assert_eq!(headers[webhooks::KEY_HEADER], "pipeline");
+    assert_eq!(
+        headers[SIGNATURE_HEADER],
+        webhooks::signature(SECRET.as_bytes(), timestamp, &body).as_str()
+    );
+    assert_eq!(
+        app.cache.get(&torrent.info_hash).unwrap(),
+        torrent.torrent_bytes
+    );
+}
+
+#[tokio::test]
+async fn it_should_notify_the_callback_when_the_resolution_fails() {
+    let receiver = Receiver::start(0).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let resolver = FakeResolver::default().with_failure(
+        torrent.info_hash,
+        ResolveError::NotFound(torrent.info_hash.to_hex_string()),
+    );
+    let app = TestApp::start_with(resolver, enable_webhooks);
+
+    request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": receiver.url(), "key_id": "pipeline" }),
+    )
+    .await;
+
+    let (_, body) = receiver.notification().await;
+    let notification: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(notification["event"], "failed");
+    assert_eq!(
+        notification["reason"],
+        ResolveError::NotFound(torrent.info_hash.to_hex_string()).to_string()
+    );
+}
+
+#[tokio::test]
+async fn it_should_retry_failed_deliveries() {
+    let receiver = Receiver::start(2).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(
+        FakeResolver::default().with_torrent(torrent.clone()),
+        enable_webhooks,
+    );
+
+    request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": receiver.url(), "key_id": "pipeline" }),
+    )
+    .await;
+
+    let (_, body) = receiver.notification().await;
+    let notification: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(notification["event"], "cached");
+}
+
+#[tokio::test]
+async fn it_should_reject_unknown_webhook_keys() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(FakeResolver::default(), enable_webhooks);
+
+    let response = request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": "https://example.com/hook", "key_id": "unknown" }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_reject_callback_urls_that_are_not_http() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(FakeResolver::default(), enable_webhooks);
+
+    let response = request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": "file:///etc/passwd", "key_id": "pipeline" }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn it_should_not_accept_background_resolutions_when_webhooks_are_disabled() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start(FakeResolver::default());
+
+    let response = request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": "https://example.com/hook", "key_id": "pipeline" }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn it_should_reject_callbacks_to_private_addresses() {
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(FakeResolver::default(), |config| {
+        enable_webhooks(config);
+        if let Some(webhooks) = config.webhooks.as_mut() {
+            webhooks.allow_private_callbacks = false;
+        }
+    });
+
+    for callback_url in [
+        "http://127.0.0.1:8080/hook",
+        "http://[::1]/hook",
+        "http://10.0.0.1/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://localhost/hook",
+    ] {
+        let response = request_resolution(
+            &app,
+            &torrent.info_hash.to_hex_string(),
+            &json!({ "callback_url": callback_url, "key_id": "pipeline" }),
+        )
+        .await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "callback: {callback_url}"
+        );
+    }
+    assert_eq!(app.resolver.calls(), 0);
+}
+
+#[tokio::test]
+async fn it_should_reject_background_resolutions_when_too_many_notifications_are_pending() {
+    let receiver = Receiver::start(0).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(
+        FakeResolver::default()
+            .with_torrent(torrent.clone())
+            .with_delay(Duration::from_secs(2)),
+        |config| {
+            enable_webhooks(config);
+            if let Some(webhooks) = config.webhooks.as_mut() {
+                webhooks.max_pending = 1;
+            }
+        },
+    );
+    let body = json!({ "callback_url": receiver.url(), "key_id": "pipeline" });
+
+    let first = request_resolution(&app, &torrent.info_hash.to_hex_string(), &body).await;
+    let second = request_resolution(&app, &torrent.info_hash.to_hex_string(), &body).await;
+
+    assert_eq!(first.status(), StatusCode::ACCEPTED);
+    assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(second.headers().contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn it_should_resume_the_resolutions_accepted_before_a_restart() {
+    let receiver = Receiver::start(0).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let resolution = PendingResolution {
+        callback: Callback {
+            id: "0123".to_string(),
+            url: receiver.url(),
+            key_id: "pipeline".to_string(),
+        },
+        info_hash: torrent.info_hash.to_hex_string(),
+        query: None,
+    };
+
+    let app = TestApp::start_with(
+        FakeResolver::default().with_torrent(torrent.clone()),
+        |config| {
+            enable_webhooks(config);
+            let resolutions_dir = config
+                .api
+                .torrents_cache_dir
+                .with_file_name("webhooks/resolutions");
+            std::fs::create_dir_all(&resolutions_dir).unwrap();
+            std::fs::write(
+                resolutions_dir.join("0123.json"),
+                serde_json::to_vec(&resolution).unwrap(),
+            )
+            .unwrap();
+        },
+    );
+
+    let (_, body) = receiver.notification().await;
+    let notification: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(notification["id"], "0123");
+    assert_eq!(notification["event"], "cached");
+    assert_eq!(app.resolver.calls(), 1);
+}
+
+#[tokio::test]
+async fn it_should_resolve_again_when_the_resolution_queue_is_full() {
+    let receiver = Receiver::start(0).await;
+    let torrent = sample_torrent("sample.txt", b"Hello, World!");
+    let app = TestApp::start_with(
+        FakeResolver::default()
+            .with_torrent(torrent.clone())
+            .with_transient_failure(torrent.info_hash, ResolveError::QueueFull, 2),
+        enable_webhooks,
+    );
+
+    request_resolution(
+        &app,
+        &torrent.info_hash.to_hex_string(),
+        &json!({ "callback_url": receiver.url(), "key_id": "pipeline" }),
+    )
+    .await;
+
+    let (_, body) = receiver.notification().await;
+    let notification: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(notification["event"], "cached");
+    assert_eq!(app.resolver.calls(), 3);
+}